@@ -1016,6 +1016,44 @@ fn benchmark_tfhe_encrypted_modulus(c: &mut Criterion) {
     c.bench_function("tfhe_encrypted_modulus", |b| b.iter(tfhe_encrypted_modulus));
 }
 
+// Benchmark 39: Benchmarking benchmark_gateway_wide_adders_clear_serial
+// Both wide adder benchmarks evaluate in the clear, without garbling, so they compare layered
+// parallel evaluation against a serial pass over the same gates.
+fn benchmark_gateway_wide_adders_clear_serial(c: &mut Criterion) {
+    use compute::prelude::*;
+
+    let (circuit, inputs) = wide_adders();
+    c.bench_function("gateway_wide_adders_clear_serial", |b| {
+        b.iter(|| circuit.trace(&inputs).unwrap())
+    });
+}
+
+// Benchmark 40: Benchmarking benchmark_gateway_wide_adders_clear_parallel
+fn benchmark_gateway_wide_adders_clear_parallel(c: &mut Criterion) {
+    use compute::prelude::*;
+
+    let (circuit, inputs) = wide_adders();
+    let layered = LayeredCircuit::new(circuit).unwrap();
+    c.bench_function("gateway_wide_adders_clear_parallel", |b| {
+        b.iter(|| layered.execute_parallel(&inputs, &[]).unwrap())
+    });
+}
+
+// 64 independent u32 additions in a single circuit
+fn wide_adders() -> (compute::prelude::Circuit, Vec<bool>) {
+    use compute::prelude::*;
+
+    let mut builder = WRK17CircuitBuilder::default();
+    let mut output = GateIndexVec::default();
+    for i in 0..64_u32 {
+        let a = builder.input(&GarbledUint32::from(i.wrapping_mul(0x9e37_79b9)));
+        let b = builder.input(&GarbledUint32::from(i ^ 0xdead_beef));
+        let sum = builder.add(&a, &b);
+        output.push_all(&sum);
+    }
+    (builder.compile(&output), builder.inputs().to_vec())
+}
+
 // Configure Criterion with a sample size of 10
 fn custom_criterion() -> Criterion {
     Criterion::default().sample_size(10)
@@ -1064,5 +1102,7 @@ criterion_group!(
         benchmark_tfhe_encrypted_modulus,
         benchmark_gateway_encrypted_mux,
         benchmark_tfhe_encrypted_mux,
+        benchmark_gateway_wide_adders_clear_serial,
+        benchmark_gateway_wide_adders_clear_parallel,
 );
 criterion_main!(benches);
@@ -0,0 +1,487 @@
+//! A `no_std` core for executing precompiled circuits, e.g. on microcontrollers.
+//!
+//! Circuits compiled and serialized with `compute` are decoded with [`Circuit::from_bytes`]
+//! and evaluated in the clear with [`Circuit::execute_bits`]. Only `alloc` is required; the
+//! default `std` feature adds `std::error::Error` implementations.
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Index of a gate, which is also the index of the wire it drives.
+pub type GateIndex = u32;
+
+/// Magic bytes at the start of every serialized circuit.
+pub const CIRCUIT_MAGIC: &[u8; 4] = b"GCIR";
+
+/// Version of the serialized circuit layout, bumped whenever the encoding changes.
+pub const CIRCUIT_FORMAT_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = CIRCUIT_MAGIC.len() + core::mem::size_of::<u16>();
+
+/// A gate of a circuit, with the same variants as the gates `compute` compiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gate {
+    /// A single input bit coming from the circuit contributor.
+    InContrib,
+    /// A single input bit coming from the circuit evaluator.
+    InEval,
+    /// A gate computing the XOR of the two specified gates.
+    Xor(GateIndex, GateIndex),
+    /// A gate computing the AND of the two specified gates.
+    And(GateIndex, GateIndex),
+    /// A gate computing the NOT of the specified gate.
+    Not(GateIndex),
+}
+
+/// A circuit whose gates only read earlier gates and whose outputs are wires of the circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Circuit {
+    gates: Vec<Gate>,
+    output_gates: Vec<GateIndex>,
+    /// The outputs when none of them depends on the inputs, computed on creation.
+    constant_output: Option<Vec<bool>>,
+}
+
+/// Error returned when a circuit cannot be decoded or executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The bytes do not start with [`CIRCUIT_MAGIC`] and a version.
+    MissingHeader,
+    /// The bytes are encoded with a format version this crate does not read.
+    UnsupportedVersion(u16),
+    /// The circuit body ends early or holds an unknown gate tag.
+    MalformedBody,
+    /// The gate counts recorded in the body do not match the gate list.
+    CountMismatch,
+    /// A gate reads a wire that is not assigned before it, i.e. itself or a later gate.
+    UnassignedWire { gate: usize, wire: GateIndex },
+    /// A gate or output refers to a wire beyond the end of the circuit.
+    WireOutOfBounds { wire: GateIndex, gates: usize },
+    /// The number of input bits does not match the inputs of the circuit.
+    InputLengthMismatch { expected: usize, got: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingHeader => write!(f, "not a serialized circuit: missing magic header"),
+            Error::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported circuit format version {}, expected {}",
+                version, CIRCUIT_FORMAT_VERSION
+            ),
+            Error::MalformedBody => write!(f, "malformed circuit body"),
+            Error::CountMismatch => write!(f, "recorded gate counts do not match the gate list"),
+            Error::UnassignedWire { gate, wire } => write!(
+                f,
+                "gate {} reads wire {}, which is not assigned before it",
+                gate, wire
+            ),
+            Error::WireOutOfBounds { wire, gates } => write!(
+                f,
+                "wire {} is out of range for a circuit of {} gates",
+                wire, gates
+            ),
+            Error::InputLengthMismatch { expected, got } => {
+                write!(f, "expected {} input bits, got {}", expected, got)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl Circuit {
+    /// Creates a circuit, checking that every gate reads only earlier wires and that every
+    /// output is a wire of the circuit. Whether the outputs are constant is worked out here
+    /// too, once, see [`Self::constant_output`].
+    pub fn new(gates: Vec<Gate>, output_gates: Vec<GateIndex>) -> Result<Self, Error> {
+        for (gate, operands) in gates.iter().map(operands).enumerate() {
+            for wire in operands.into_iter().flatten() {
+                if wire as usize >= gates.len() {
+                    return Err(Error::WireOutOfBounds {
+                        wire,
+                        gates: gates.len(),
+                    });
+                }
+                if wire as usize >= gate {
+                    return Err(Error::UnassignedWire { gate, wire });
+                }
+            }
+        }
+        if let Some(wire) = output_gates
+            .iter()
+            .find(|wire| **wire as usize >= gates.len())
+        {
+            return Err(Error::WireOutOfBounds {
+                wire: *wire,
+                gates: gates.len(),
+            });
+        }
+        let constant_output = fold_constants(&gates, &output_gates);
+        Ok(Circuit {
+            gates,
+            output_gates,
+            constant_output,
+        })
+    }
+
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    pub fn output_gates(&self) -> &[GateIndex] {
+        &self.output_gates
+    }
+
+    /// Returns the value of every output wire when none of them depends on the inputs, e.g.
+    /// for `a & 0`, and `None` otherwise.
+    pub fn constant_output(&self) -> Option<&[bool]> {
+        self.constant_output.as_deref()
+    }
+
+    pub fn contrib_inputs(&self) -> usize {
+        self.count(|gate| matches!(gate, Gate::InContrib))
+    }
+
+    pub fn eval_inputs(&self) -> usize {
+        self.count(|gate| matches!(gate, Gate::InEval))
+    }
+
+    pub fn and_gates(&self) -> usize {
+        self.count(|gate| matches!(gate, Gate::And(_, _)))
+    }
+
+    fn count(&self, predicate: fn(&Gate) -> bool) -> usize {
+        self.gates.iter().filter(|gate| predicate(gate)).count()
+    }
+
+    fn check_input_length(&self, inputs: &[bool]) -> Result<(), Error> {
+        let expected = self.contrib_inputs() + self.eval_inputs();
+        if inputs.len() != expected {
+            return Err(Error::InputLengthMismatch {
+                expected,
+                got: inputs.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Decodes a circuit serialized by `compute`'s `CircuitBytes::to_bytes`: the magic
+    /// header, the little-endian format version and a body holding the gate list, the output
+    /// wires and the input counts. Bytes after the body are ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_LEN || !bytes.starts_with(CIRCUIT_MAGIC) {
+            return Err(Error::MissingHeader);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != CIRCUIT_FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let mut reader = Reader(&bytes[HEADER_LEN..]);
+        let gate_count = reader.len()?;
+        // a corrupted count cannot reserve more gates than the body could hold
+        let mut gates = Vec::with_capacity(gate_count.min(reader.0.len() / 4));
+        for _ in 0..gate_count {
+            gates.push(match reader.u32()? {
+                0 => Gate::InContrib,
+                1 => Gate::InEval,
+                2 => Gate::Xor(reader.u32()?, reader.u32()?),
+                3 => Gate::And(reader.u32()?, reader.u32()?),
+                4 => Gate::Not(reader.u32()?),
+                _ => return Err(Error::MalformedBody),
+            });
+        }
+        let output_count = reader.len()?;
+        let mut output_gates = Vec::with_capacity(output_count.min(reader.0.len() / 4));
+        for _ in 0..output_count {
+            output_gates.push(reader.u32()?);
+        }
+        let (and_gates, eval_inputs, contrib_inputs) =
+            (reader.len()?, reader.len()?, reader.len()?);
+
+        let circuit = Circuit::new(gates, output_gates)?;
+        if circuit.and_gates() != and_gates
+            || circuit.eval_inputs() != eval_inputs
+            || circuit.contrib_inputs() != contrib_inputs
+        {
+            return Err(Error::CountMismatch);
+        }
+        Ok(circuit)
+    }
+
+    /// Encodes the circuit in the layout [`Self::from_bytes`] reads, which is also the layout
+    /// `compute` reads.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 8 + 12 * self.gates.len());
+        bytes.extend_from_slice(CIRCUIT_MAGIC);
+        bytes.extend_from_slice(&CIRCUIT_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.gates.len() as u64).to_le_bytes());
+        for gate in &self.gates {
+            let (tag, operands) = match *gate {
+                Gate::InContrib => (0_u32, [None, None]),
+                Gate::InEval => (1, [None, None]),
+                Gate::Xor(a, b) => (2, [Some(a), Some(b)]),
+                Gate::And(a, b) => (3, [Some(a), Some(b)]),
+                Gate::Not(a) => (4, [Some(a), None]),
+            };
+            bytes.extend_from_slice(&tag.to_le_bytes());
+            for wire in operands.into_iter().flatten() {
+                bytes.extend_from_slice(&wire.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&(self.output_gates.len() as u64).to_le_bytes());
+        for wire in &self.output_gates {
+            bytes.extend_from_slice(&wire.to_le_bytes());
+        }
+        for count in [self.and_gates(), self.eval_inputs(), self.contrib_inputs()] {
+            bytes.extend_from_slice(&(count as u64).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Evaluates the circuit in the clear and returns the value of every output wire in
+    /// order. `inputs` holds the contributor inputs followed by the evaluator inputs, each in
+    /// the order their gates appear.
+    pub fn execute_bits(&self, inputs: &[bool]) -> Result<Vec<bool>, Error> {
+        if let Some(outputs) = &self.constant_output {
+            self.check_input_length(inputs)?;
+            return Ok(outputs.clone());
+        }
+        let wires = self.trace(inputs)?;
+        Ok(self
+            .output_gates
+            .iter()
+            .map(|wire| wires[*wire as usize])
+            .collect())
+    }
+
+    /// Evaluates the circuit in the clear and returns the value of every wire, indexed by the
+    /// gate that drives it. `inputs` is laid out as for [`Self::execute_bits`].
+    pub fn trace(&self, inputs: &[bool]) -> Result<Vec<bool>, Error> {
+        let mut wires = Vec::with_capacity(self.gates.len());
+        self.trace_into(inputs, &mut wires)?;
+        Ok(wires)
+    }
+
+    /// Like [`Self::trace`], but overwrites `wires`, so repeated evaluations can reuse one
+    /// buffer.
+    pub fn trace_into(&self, inputs: &[bool], wires: &mut Vec<bool>) -> Result<(), Error> {
+        self.check_input_length(inputs)?;
+
+        let (contrib, eval) = inputs.split_at(self.contrib_inputs());
+        let (mut contrib, mut eval) = (contrib.iter(), eval.iter());
+        wires.clear();
+        for gate in &self.gates {
+            let value = match *gate {
+                // the input counts were checked above
+                Gate::InContrib => *contrib.next().unwrap(),
+                Gate::InEval => *eval.next().unwrap(),
+                Gate::Xor(a, b) => wires[a as usize] ^ wires[b as usize],
+                Gate::And(a, b) => wires[a as usize] & wires[b as usize],
+                Gate::Not(a) => !wires[a as usize],
+            };
+            wires.push(value);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the value of every output wire when none of them depends on the inputs.
+/// Constants are propagated in one pass over the gates: `XOR(x, x)`, an AND with a constant
+/// false operand, and any gate over constant operands are constant. The wiring must be valid.
+fn fold_constants(gates: &[Gate], output_gates: &[GateIndex]) -> Option<Vec<bool>> {
+    let mut constant: Vec<Option<bool>> = Vec::with_capacity(gates.len());
+    for gate in gates {
+        let value = match *gate {
+            Gate::InContrib | Gate::InEval => None,
+            Gate::Not(a) => constant[a as usize].map(|value| !value),
+            Gate::Xor(a, b) if a == b => Some(false),
+            Gate::Xor(a, b) => match (constant[a as usize], constant[b as usize]) {
+                (Some(a), Some(b)) => Some(a ^ b),
+                _ => None,
+            },
+            Gate::And(a, b) => match (constant[a as usize], constant[b as usize]) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(a), Some(b)) => Some(a & b),
+                _ => None,
+            },
+        };
+        constant.push(value);
+    }
+    output_gates
+        .iter()
+        .map(|output| constant[*output as usize])
+        .collect()
+}
+
+/// Returns the wires a gate reads.
+fn operands(gate: &Gate) -> [Option<GateIndex>; 2] {
+    match *gate {
+        Gate::InContrib | Gate::InEval => [None, None],
+        Gate::Not(a) => [Some(a), None],
+        Gate::Xor(a, b) | Gate::And(a, b) => [Some(a), Some(b)],
+    }
+}
+
+/// Reads the little-endian integers of a circuit body.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        if self.0.len() < N {
+            return Err(Error::MalformedBody);
+        }
+        let (head, rest) = self.0.split_at(N);
+        self.0 = rest;
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(head);
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    /// Reads a length or count, which is encoded as 64 bits.
+    fn len(&mut self) -> Result<usize, Error> {
+        let value = u64::from_le_bytes(self.take()?);
+        usize::try_from(value).map_err(|_| Error::MalformedBody)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// A two-bit ripple-carry adder over contributor inputs `a0 a1` and evaluator inputs
+    /// `b0 b1`, returning three bits.
+    fn adder() -> Circuit {
+        Circuit::new(
+            vec![
+                Gate::InContrib,
+                Gate::InContrib,
+                Gate::InEval,
+                Gate::InEval,
+                Gate::Xor(0, 2),
+                Gate::And(0, 2),
+                Gate::Xor(1, 3),
+                Gate::Xor(6, 5),
+                Gate::And(1, 3),
+                Gate::And(6, 5),
+                Gate::Xor(8, 9),
+            ],
+            vec![4, 7, 10],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_execute_from_bytes() {
+        let circuit = Circuit::from_bytes(&adder().to_bytes()).unwrap();
+        assert_eq!(circuit, adder());
+
+        for a in 0..4_u8 {
+            for b in 0..4_u8 {
+                let bit = |value: u8, i: u8| (value >> i) & 1 == 1;
+                let inputs = [bit(a, 0), bit(a, 1), bit(b, 0), bit(b, 1)];
+                let outputs = circuit.execute_bits(&inputs).unwrap();
+                let wires = circuit.trace(&inputs).unwrap();
+                assert_eq!(outputs, [4, 7, 10].map(|wire| wires[wire]));
+                let sum = (0..3).filter(|i| outputs[*i]).map(|i| 1 << i).sum::<u8>();
+                assert_eq!(sum, a + b, "{a} + {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_malformed_input() {
+        let bytes = adder().to_bytes();
+        assert_eq!(Circuit::from_bytes(&bytes[..3]), Err(Error::MissingHeader));
+        assert_eq!(
+            Circuit::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::MalformedBody)
+        );
+
+        let mut version = bytes.clone();
+        version[4] = 0xff;
+        assert_eq!(
+            Circuit::from_bytes(&version),
+            Err(Error::UnsupportedVersion(0xff))
+        );
+
+        // the last output wire, 10, moved past the end of the circuit
+        let mut output = bytes.clone();
+        let at = bytes.len() - 3 * 8 - 4;
+        output[at] = 11;
+        assert_eq!(
+            Circuit::from_bytes(&output),
+            Err(Error::WireOutOfBounds {
+                wire: 11,
+                gates: 11
+            })
+        );
+
+        // one AND gate fewer recorded than the gate list holds
+        let mut counts = bytes;
+        let at = counts.len() - 3 * 8;
+        counts[at] = 2;
+        assert_eq!(Circuit::from_bytes(&counts), Err(Error::CountMismatch));
+    }
+
+    #[test]
+    fn test_constant_output() {
+        assert_eq!(adder().constant_output(), None);
+
+        // a0 & (a0 ^ a0), and its negation
+        let circuit = Circuit::new(
+            vec![
+                Gate::InContrib,
+                Gate::Xor(0, 0),
+                Gate::And(0, 1),
+                Gate::Not(2),
+            ],
+            vec![2, 3],
+        )
+        .unwrap();
+        assert_eq!(circuit.constant_output(), Some(&[false, true][..]));
+        assert_eq!(circuit.execute_bits(&[true]), Ok(vec![false, true]));
+        assert_eq!(
+            circuit.execute_bits(&[]),
+            Err(Error::InputLengthMismatch {
+                expected: 1,
+                got: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_new_rejects_forward_wires() {
+        assert_eq!(
+            Circuit::new(
+                vec![Gate::InContrib, Gate::Not(2), Gate::InContrib],
+                vec![1]
+            ),
+            Err(Error::UnassignedWire { gate: 1, wire: 2 })
+        );
+        assert_eq!(
+            Circuit::new(vec![Gate::InContrib, Gate::Not(5)], vec![1]),
+            Err(Error::WireOutOfBounds { wire: 5, gates: 2 })
+        );
+        assert_eq!(
+            adder().execute_bits(&[true; 3]),
+            Err(Error::InputLengthMismatch {
+                expected: 4,
+                got: 3
+            })
+        );
+    }
+}
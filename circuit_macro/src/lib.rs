@@ -1,142 +1,608 @@
 extern crate proc_macro;
-use core::panic;
 
 use proc_macro::TokenStream;
+use proc_macro2::{Delimiter, Literal, TokenTree};
 use quote::{format_ident, quote};
 use std::collections::HashSet;
+use syn::punctuated::Punctuated;
+use syn::visit_mut::{self, VisitMut};
 use syn::{
-    parse_macro_input, BinOp, Expr, ExprAssign, ExprBinary, ExprBlock, ExprIf, ExprLet, ExprMatch,
-    ExprReference, ExprUnary, FnArg, ItemFn, Lit, Pat, PatType,
+    parse_macro_input, BinOp, Expr, ExprAssign, ExprBinary, ExprBlock, ExprForLoop, ExprIf,
+    ExprLet, ExprMatch, ExprRange, ExprReference, ExprUnary, ExprWhile, FnArg, ItemFn, Lit, Pat,
+    PatType, RangeLimits,
 };
 
+/// State threaded through the lowering of one circuit body: the constants it collects, and
+/// what the signature of the circuit tells about its parameters.
+#[derive(Default)]
+struct Lowering {
+    /// Constants to add to the circuit context ahead of the transformed body.
+    constants: Vec<proc_macro2::TokenStream>,
+
+    /// Width in bits of the circuit, when its signature fixes it, so that integer literals
+    /// which do not fit can be rejected where they are lowered.
+    literal_width: Option<usize>,
+
+    /// Names and lengths of the array parameters of the circuit, so that `for x in array` can
+    /// be unrolled over their elements.
+    array_params: Vec<(syn::Ident, u128)>,
+
+    /// Names and types of the record parameters of the circuit, so that `p.x` can be lowered
+    /// to the bit range of the field.
+    record_params: Vec<(syn::Ident, syn::Type)>,
+
+    /// Names of the parameters of primitive integer types, which cannot be operands of `&&`
+    /// and `||`.
+    integer_params: Vec<syn::Ident>,
+
+    /// Names of the parameters passed by reference, so that `*a` can be lowered to the input
+    /// wires of `a`.
+    reference_params: Vec<syn::Ident>,
+}
+
+impl Lowering {
+    /// Checks that an integer constant, negated when `negative`, fits the width of the
+    /// circuit as either an unsigned or a two's complement value, e.g. `255` and `-128` for
+    /// 8 bits.
+    fn check_literal(
+        &self,
+        value: u128,
+        negative: bool,
+        tokens: &impl quote::ToTokens,
+    ) -> syn::Result<()> {
+        let Some(width) = self.literal_width else {
+            return Ok(());
+        };
+        let fits = match (negative, width) {
+            (false, width) if width >= 128 => true,
+            (false, width) => value >> width == 0,
+            (true, 0) => value == 0,
+            (true, width) if width > 128 => true,
+            (true, width) => value <= 1 << (width - 1),
+        };
+        if fits {
+            return Ok(());
+        }
+        let sign = if negative { "-" } else { "" };
+        Err(syn::Error::new_spanned(
+            tokens,
+            format!(
+                "Literal {}{} does not fit in the {}-bit width of the circuit",
+                sign, value, width
+            ),
+        ))
+    }
+
+    /// Returns the name and length of the array parameter that `expr` names, by value or by
+    /// reference.
+    fn array_param(&self, expr: &Expr) -> Option<(syn::Ident, u128)> {
+        match expr {
+            Expr::Path(path) => {
+                let ident = path.path.get_ident()?;
+                self.array_params
+                    .iter()
+                    .find(|(name, _)| name == ident)
+                    .cloned()
+            }
+            Expr::Reference(reference) => self.array_param(&reference.expr),
+            Expr::Paren(paren) => self.array_param(&paren.expr),
+            _ => None,
+        }
+    }
+
+    /// Returns whether `expr` is known to be an integer rather than a `bool`: an integer
+    /// constant, an integer parameter or its dereference, an arithmetic operation or a cast.
+    fn is_integer(&self, expr: &Expr) -> bool {
+        match expr {
+            _ if eval_const_expr(expr).is_some() => true,
+            Expr::Path(path) => path
+                .path
+                .get_ident()
+                .is_some_and(|ident| self.integer_params.contains(ident)),
+            Expr::Binary(ExprBinary { op, .. }) => matches!(
+                op,
+                BinOp::Add(_)
+                    | BinOp::Sub(_)
+                    | BinOp::Mul(_)
+                    | BinOp::Div(_)
+                    | BinOp::Rem(_)
+                    | BinOp::Shl(_)
+                    | BinOp::Shr(_)
+            ),
+            Expr::Cast(_) => true,
+            Expr::Paren(paren) => self.is_integer(&paren.expr),
+            Expr::Group(group) => self.is_integer(&group.expr),
+            Expr::Unary(ExprUnary {
+                op: syn::UnOp::Deref(_),
+                expr,
+                ..
+            }) => self.is_integer(expr),
+            _ => false,
+        }
+    }
+
+    /// Returns the type of the record parameter `name`, if it is one.
+    fn record_type(&self, name: &syn::Ident) -> Option<syn::Type> {
+        self.record_params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, ty)| ty.clone())
+    }
+
+    /// Returns the type of the record parameter `expr` names, if any.
+    fn record_param(&self, expr: &Expr) -> Option<syn::Type> {
+        match expr {
+            Expr::Path(path) => self.record_type(path.path.get_ident()?),
+            _ => None,
+        }
+    }
+
+    /// Returns whether `expr` names a parameter passed by reference.
+    fn is_reference_param(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Path(path) => path
+                .path
+                .get_ident()
+                .is_some_and(|ident| self.reference_params.contains(ident)),
+            _ => false,
+        }
+    }
+}
+
+/// Environment variable that, when set to `1`, dumps the generated code of every circuit to stderr.
+const DEBUG_ENV_VAR: &str = "CIRCUIT_MACRO_DEBUG";
+
+/// Boolean flags of an `#[encrypted]` attribute that shape the generated function.
+#[derive(Clone, Copy, Default)]
+struct Flags {
+    /// Dumps the generated code to stderr.
+    debug: bool,
+    /// Returns the input layout of the circuit as well, in compile mode.
+    layout: bool,
+    /// Returns the width of every element of the result as well, in compile mode.
+    output_widths: bool,
+}
+
 #[proc_macro_attribute]
 pub fn encrypted(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let mode = parse_macro_input!(attr as syn::Ident).to_string(); // Retrieve the mode (e.g., "compile" or "execute")
-    generate_macro(item, &mode)
-}
+    // Retrieve the mode (e.g., "compile" or "execute") followed by optional flags (e.g.,
+    // "debug", "layout", "output_widths" or "width = 16")
+    let args =
+        parse_macro_input!(attr with Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated);
+    let mut args = args.into_iter();
+    let Some(mode) = args.next().and_then(|mode| match mode {
+        syn::Meta::Path(path) => path.get_ident().cloned(),
+        _ => None,
+    }) else {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "Expected a mode, e.g. `#[encrypted(execute)]`",
+        )
+        .to_compile_error()
+        .into();
+    };
 
-/// Generates the macro code based on the mode (either "compile" or "execute")
-fn generate_macro(item: TokenStream, mode: &str) -> TokenStream {
-    let input_fn = parse_macro_input!(item as ItemFn);
-    let fn_name = &input_fn.sig.ident; // Function name
-    let inputs = &input_fn.sig.inputs; // Function input parameters
+    let mut flags = Flags {
+        debug: std::env::var(DEBUG_ENV_VAR).is_ok_and(|value| value == "1"),
+        ..Flags::default()
+    };
+    let mut width = None;
+    let mut signed = false;
+    for flag in args {
+        match &flag {
+            syn::Meta::Path(path) if path.is_ident("debug") => flags.debug = true,
+            syn::Meta::Path(path) if path.is_ident("layout") || path.is_ident("output_widths") => {
+                if mode != "compile" {
+                    return syn::Error::new_spanned(
+                        flag,
+                        format!("`{}` only applies in compile mode", quote! {#path}),
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+                if path.is_ident("layout") {
+                    flags.layout = true;
+                } else {
+                    flags.output_widths = true;
+                }
+            }
+            syn::Meta::Path(path) if path.is_ident("signed") => signed = true,
+            syn::Meta::NameValue(name_value) if name_value.path.is_ident("width") => {
+                match parse_width(&name_value.value) {
+                    Ok(value) => width = Some(value),
+                    Err(error) => return error.to_compile_error().into(),
+                }
+            }
+            _ => {
+                return syn::Error::new_spanned(
+                    flag,
+                    "Unknown flag, expected `debug`, `layout`, `output_widths`, `signed` or `width = N`",
+                )
+                .to_compile_error()
+                .into()
+            }
+        }
+    }
+    if signed && width.is_none() {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "`signed` only applies with an explicit `width = N`",
+        )
+        .to_compile_error()
+        .into();
+    }
 
-    // get the type of the first input parameter
-    let type_name = if let FnArg::Typed(PatType { ty, .. }) = &inputs[0] {
-        quote! {#ty}
-    } else {
-        panic!("Expected typed argument");
+    generate_macro(
+        item,
+        &mode.to_string(),
+        flags,
+        width.map(|width| (width, signed)),
+    )
+}
+
+/// Parses the value of a `width = N` flag, which may be any width of at least one bit, e.g.
+/// 24 or 256, since the builder is generic over the width.
+fn parse_width(value: &Expr) -> syn::Result<usize> {
+    let Expr::Lit(syn::ExprLit {
+        lit: Lit::Int(lit_int),
+        ..
+    }) = value
+    else {
+        return Err(syn::Error::new_spanned(
+            value,
+            "Expected an integer width, e.g. `width = 32`",
+        ));
     };
+    let width = lit_int.base10_parse::<usize>()?;
+    if width == 0 {
+        return Err(syn::Error::new_spanned(
+            value,
+            "The width must be at least 1 bit",
+        ));
+    }
+    Ok(width)
+}
 
-    // get the type of the first output parameter
-    let output_type = if let syn::ReturnType::Type(_, ty) = &input_fn.sig.output {
-        quote! {#ty}
-    } else {
-        panic!("Expected typed return type");
+/// Derives `CircuitInput` for a struct of primitive integer and `bool` fields, laying it out
+/// as one integer with the fields concatenated in declaration order, the first field in the
+/// lowest bits. Also derives the conversions to and from `GarbledUint` of the total width, so
+/// that `#[encrypted]` functions can take the struct as a parameter.
+#[proc_macro_derive(CircuitInput)]
+pub fn derive_circuit_input(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as syn::DeriveInput);
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return syn::Error::new_spanned(
+            &input.generics,
+            "`CircuitInput` cannot be derived for generic types",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return syn::Error::new_spanned(
+                name,
+                "`CircuitInput` can only be derived for structs with named fields",
+            )
+            .to_compile_error()
+            .into()
+        }
     };
 
-    // We need to extract each input's identifier
-    let mapped_inputs = inputs.iter().map(|input| {
-        if let FnArg::Typed(PatType { pat, .. }) = input {
-            if let Pat::Ident(pat_ident) = &**pat {
-                let var_name = &pat_ident.ident;
-                quote! {
-                    let #var_name = &context.input(&#var_name.clone().into());
+    let mut layout = Vec::with_capacity(fields.len());
+    for field in fields {
+        let Some((width, signed)) = primitive_type(&field.ty) else {
+            return syn::Error::new_spanned(
+                &field.ty,
+                "Fields of a `CircuitInput` must be primitive integers or bool",
+            )
+            .to_compile_error()
+            .into();
+        };
+        layout.push((field.ident.clone().unwrap(), width, signed));
+    }
+    let width: usize = layout.iter().map(|(_, width, _)| width).sum();
+
+    let descriptors = layout.iter().map(|(field, width, signed)| {
+        let field = field.to_string();
+        quote! { (#field, #width, #signed) }
+    });
+    let encode = layout.iter().map(|(field, width, _)| {
+        quote! { bits.extend(GarbledUint::<#width>::from(value.#field).bits); }
+    });
+    let mut offset = 0;
+    let decode = layout.iter().map(|(field, width, _)| {
+        let start = offset;
+        offset += width;
+        quote! { #field: GarbledUint::<#width>::new(value.bits[#start..#offset].to_vec()).into() }
+    });
+
+    TokenStream::from(quote! {
+        impl CircuitInput for #name {
+            const WIDTH: usize = #width;
+            const FIELDS: &'static [(&'static str, usize, bool)] = &[#(#descriptors),*];
+        }
+
+        impl From<#name> for GarbledUint<#width> {
+            fn from(value: #name) -> Self {
+                let mut bits = Vec::with_capacity(#width);
+                #(#encode)*
+                GarbledUint::new(bits)
+            }
+        }
+
+        impl From<GarbledUint<#width>> for #name {
+            fn from(value: GarbledUint<#width>) -> Self {
+                #name {
+                    #(#decode),*
                 }
-            } else {
-                quote! {}
             }
-        } else {
-            quote! {}
         }
-    });
+    })
+}
+
+/// Generates the macro code based on the mode ("compile", "execute", "build" or "component").
+/// `width` holds the width and signedness from a `width = N` flag, which replace the dispatch
+/// on the name of the parameter type.
+fn generate_macro(
+    item: TokenStream,
+    mode: &str,
+    flags: Flags,
+    width: Option<(usize, bool)>,
+) -> TokenStream {
+    let input_fn = parse_macro_input!(item as ItemFn);
+    let fn_name = &input_fn.sig.ident; // Function name
+    let inputs = &input_fn.sig.inputs; // Function input parameters
+
+    // a circuit has a fixed number of input wires, so slices need a length known up front
+    if let Some(ty) = inputs.iter().find_map(|input| match input {
+        FnArg::Typed(PatType { ty, .. }) if matches!(referent(ty), syn::Type::Slice(_)) => Some(ty),
+        _ => None,
+    }) {
+        return syn::Error::new_spanned(
+            ty,
+            "Slice parameters must have a length fixed at compile time, e.g. `[u8; 16]`",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // get the type of the first input parameter, or its element type for arrays
+    let type_name = match inputs.first() {
+        Some(FnArg::Typed(PatType { ty, .. })) => {
+            let ty = element_type(ty);
+            quote! {#ty}
+        }
+        Some(other) => {
+            return syn::Error::new_spanned(other, "Expected typed argument")
+                .to_compile_error()
+                .into()
+        }
+        None => {
+            return syn::Error::new_spanned(&input_fn.sig, "Expected at least one input argument")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    // the result is decoded from the output wires, so it needs a type
+    if matches!(input_fn.sig.output, syn::ReturnType::Default) {
+        return syn::Error::new_spanned(&input_fn.sig, "Expected typed return type")
+            .to_compile_error()
+            .into();
+    }
+
+    // Collect parameter names dynamically
+    let param_names = match inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(PatType { pat, .. }) => match &**pat {
+                Pat::Ident(pat_ident) => Ok(pat_ident.ident.clone()),
+                other => Err(syn::Error::new_spanned(
+                    other,
+                    "Expected identifier pattern",
+                )),
+            },
+            other => Err(syn::Error::new_spanned(other, "Expected typed argument")),
+        })
+        .collect::<syn::Result<Vec<_>>>()
+    {
+        Ok(param_names) => param_names,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
-    // Extract constants to be added at the top of the function
-    let mut constants = vec![];
-    let transformed_block = modify_body(*input_fn.block, &mut constants);
+    let block = match lower_returns(input_fn.block.stmts.clone()) {
+        Ok(stmts) => syn::Block {
+            stmts,
+            brace_token: input_fn.block.brace_token,
+        },
+        Err(error) => return error.to_compile_error().into(),
+    };
+    // components run at the width of the calling circuit, unknown here
+    let circuit_width = if mode == "component" {
+        None
+    } else {
+        width
+            .map(|(width, _)| width)
+            .or_else(|| literal_width(&input_fn.sig))
+    };
+    // an explicit width takes any type that converts to it as a whole, without fields
+    let record_params = if width.is_none() && mode != "component" {
+        record_params_of(inputs, &input_fn.block)
+    } else {
+        vec![]
+    };
+    // collects the constants to add at the top of the function
+    let mut lowering = Lowering {
+        literal_width: circuit_width,
+        array_params: array_params_of(inputs),
+        record_params: record_params.clone(),
+        integer_params: integer_params_of(inputs),
+        reference_params: reference_params_of(inputs),
+        ..Lowering::default()
+    };
+    let transformed_block = modify_body(block, &mut lowering);
 
     // remove duplicates
     let mut seen = HashSet::new();
-    let constants: Vec<proc_macro2::TokenStream> = constants
+    let constants: Vec<proc_macro2::TokenStream> = lowering
+        .constants
         .into_iter()
         .filter(|item| seen.insert(item.to_string()))
         .collect();
 
-    // Collect parameter names dynamically
-    let param_names: Vec<_> = inputs
+    let param_types: Vec<&syn::Type> = inputs
         .iter()
-        .map(|input| {
-            if let FnArg::Typed(PatType { pat, .. }) = input {
-                if let Pat::Ident(pat_ident) = &**pat {
-                    pat_ident.ident.clone()
-                } else {
-                    panic!("Expected identifier pattern");
-                }
-            } else {
-                panic!("Expected typed argument");
-            }
+        .filter_map(|input| match input {
+            FnArg::Typed(PatType { ty, .. }) => Some(&**ty),
+            FnArg::Receiver(_) => None,
         })
         .collect();
 
-    // Dynamically generate the `generate` function calls using the parameter names
-    let match_arms = quote! {
-        match std::any::type_name::<#type_name>() {
-            "bool" => generate::<1, #type_name>(#(#param_names),*),
-            "u8" => generate::<8, #type_name>(#(#param_names),*),
-            "u16" => generate::<16, #type_name>(#(#param_names),*),
-            "u32" => generate::<32, #type_name>(#(#param_names),*),
-            "u64" => generate::<64, #type_name>(#(#param_names),*),
-            "u128" => generate::<128, #type_name>(#(#param_names),*),
-            _ => panic!("Unsupported type"),
+    if mode == "component" {
+        if width.is_some() {
+            return syn::Error::new_spanned(
+                &input_fn.sig,
+                "Components run at the width of their caller and take no `width`",
+            )
+            .to_compile_error()
+            .into();
         }
-    };
+        return generate_component(
+            &input_fn.sig,
+            flags.debug,
+            &param_names,
+            &param_types,
+            &constants,
+            &transformed_block,
+        );
+    }
 
-    // Set the output type and operation logic based on mode
-    let output_type = if mode == "compile" {
-        quote! {(Circuit, Vec<bool>)}
-    } else {
-        quote! {#output_type}
-    };
+    // parameters of different types, and records, are promoted to the widest one
+    let type_strings: Vec<String> = param_types
+        .iter()
+        .map(|ty| {
+            let ty = element_type(ty);
+            quote! {#ty}.to_string()
+        })
+        .collect();
+    if type_strings.iter().any(|ty| *ty != type_strings[0])
+        || widens_output(&input_fn.sig)
+        || !record_params.is_empty()
+    {
+        if width.is_some() {
+            return syn::Error::new_spanned(
+                &input_fn.sig,
+                "An explicit `width` needs every parameter and the result to share one type",
+            )
+            .to_compile_error()
+            .into();
+        }
+        return generate_mixed_macro(
+            &input_fn.sig,
+            mode,
+            flags,
+            &param_names,
+            &param_types,
+            &record_params,
+            &constants,
+            &transformed_block,
+        );
+    }
 
-    let operation = if mode == "compile" {
+    // We need to extract each input's identifier
+    let mapped_inputs = param_names.iter().zip(&param_types).map(|(var_name, ty)| {
+        let name = var_name.to_string();
+        let value = input_value(var_name, ty);
+        let input = match referent(ty) {
+            syn::Type::Array(array) => {
+                let len = &array.len;
+                quote! {
+                    let #var_name = &context.input_array::<N, { #len }>(#value.map(Into::into));
+                }
+            }
+            _ => quote! {
+                let #var_name = &context.input(&#value.into());
+            },
+        };
         quote! {
-            (context.compile(&output), context.inputs().to_vec())
+            #input
+            context.name_input(#name);
         }
+    });
+
+    // only by-reference parameters are cloned to become inputs
+    let clone_bound = if param_types
+        .iter()
+        .any(|ty| matches!(ty, syn::Type::Reference(_)))
+    {
+        quote! { + Clone }
     } else {
-        quote! {
-            let compiled_circuit = context.compile(&output.into());
-            let result = context.execute::<N>(&compiled_circuit).expect("Execution failed");
-            result.into()
-        }
+        quote! {}
     };
 
-    // Build the function body with circuit context, compile, and execute
-    let expanded = quote! {
-        #[allow(non_camel_case_types, non_snake_case, clippy::builtin_type_shadow, unused_assignments)]
-        fn #fn_name<#type_name>(#inputs) -> #output_type
-        where
-        #type_name: Into<GarbledUint<1>> + From<GarbledUint<1>>
+    // Dynamically generate the `generate` function calls using the parameter names; an
+    // explicit width calls the one instance directly, which works for any type that converts
+    // to and from `GarbledUint` of that width
+    let (type_bounds, match_arms) = if let Some((width, signed)) = width {
+        let width = Literal::usize_unsuffixed(width);
+        (
+            quote! { Into<GarbledUint<#width>> + From<GarbledUint<#width>> },
+            quote! { generate::<#width, #signed, #type_name>(#(#param_names),*) },
+        )
+    } else {
+        (
+            quote! {
+                Into<GarbledUint<1>> + From<GarbledUint<1>>
                 + Into<GarbledUint<8>> + From<GarbledUint<8>>
                 + Into<GarbledUint<16>> + From<GarbledUint<16>>
                 + Into<GarbledUint<32>> + From<GarbledUint<32>>
                 + Into<GarbledUint<64>> + From<GarbledUint<64>>
                 + Into<GarbledUint<128>> + From<GarbledUint<128>>
-                + Clone,
+            },
+            type_name_dispatch(&type_name, &param_names),
+        )
+    };
+
+    // Set the output type and operation logic based on mode
+    let (output_type, operation) = output_operation(
+        mode,
+        flags,
+        &input_fn.sig.output,
+        quote! {N},
+        quote! {
+            let compiled_circuit = context.compile(&output);
+            let result = context.execute::<N>(&compiled_circuit).unwrap_or_else(|error| panic!("Execution failed: {}", error));
+            result.into()
+        },
+    );
+    let allowed_lints = allowed_lints();
+    let body = circuit_body(&constants, &transformed_block, &operation);
+
+    // Build the function body with circuit context, compile, and execute
+    let expanded = quote! {
+        #allowed_lints
+        fn #fn_name<#type_name>(#inputs) -> #output_type
+        where
+            #type_name: #type_bounds #clone_bound,
         {
-            fn generate<const N: usize, #type_name>(#inputs) -> #output_type
+            fn generate<const N: usize, const SIGNED: bool, #type_name>(#inputs) -> #output_type
             where
-                #type_name: Into<GarbledUint<N>> + From<GarbledUint<N>> + Clone,
+                #type_name: Into<GarbledUint<N>> + From<GarbledUint<N>> #clone_bound,
             {
-                let mut context = WRK17CircuitBuilder::default();
+                // signed types select two's complement comparisons
+                let mut context = if SIGNED {
+                    WRK17CircuitBuilder::signed()
+                } else {
+                    WRK17CircuitBuilder::default()
+                };
                 #(#mapped_inputs)*
-                #(#constants)*
-                let const_true = &context.input::<N>(&true.into());
-                let const_false = &context.input::<N>(&false.into());
-
-                // Use the transformed function block (with context.add and if/else replacements)
-                let output = { #transformed_block };
-
-                #operation
+                #body
             }
 
             #match_arms
@@ -144,31 +610,761 @@ fn generate_macro(item: TokenStream, mode: &str) -> TokenStream {
     };
 
     // Print the expanded code to stderr
-    // println!("Generated code:\n{}", expanded);
+    if flags.debug {
+        eprintln!("Generated code:\n{}", expanded);
+    }
 
     TokenStream::from(expanded)
 }
 
+/// Dispatches to the instance of `generate` for the width and signedness of the primitive
+/// type `type_name`, by the name of the type the caller instantiated it with.
+fn type_name_dispatch(
+    type_name: &proc_macro2::TokenStream,
+    param_names: &[syn::Ident],
+) -> proc_macro2::TokenStream {
+    quote! {
+        match std::any::type_name::<#type_name>() {
+            "bool" => generate::<1, false, #type_name>(#(#param_names),*),
+            "u8" => generate::<8, false, #type_name>(#(#param_names),*),
+            "u16" => generate::<16, false, #type_name>(#(#param_names),*),
+            "u32" => generate::<32, false, #type_name>(#(#param_names),*),
+            "u64" => generate::<64, false, #type_name>(#(#param_names),*),
+            "u128" => generate::<128, false, #type_name>(#(#param_names),*),
+            "i8" => generate::<8, true, #type_name>(#(#param_names),*),
+            "i16" => generate::<16, true, #type_name>(#(#param_names),*),
+            "i32" => generate::<32, true, #type_name>(#(#param_names),*),
+            "i64" => generate::<64, true, #type_name>(#(#param_names),*),
+            "i128" => generate::<128, true, #type_name>(#(#param_names),*),
+            other => panic!("Unsupported type: {}", other),
+        }
+    }
+}
+
+/// Generates a component: a function that adds its gates to the caller's circuit context and
+/// returns its output wires instead of compiling and executing a circuit of its own. Calls to
+/// it from other `#[encrypted]` functions are spliced into the caller's circuit.
+fn generate_component(
+    sig: &syn::Signature,
+    debug: bool,
+    param_names: &[syn::Ident],
+    param_types: &[&syn::Type],
+    constants: &[proc_macro2::TokenStream],
+    transformed_block: &syn::Block,
+) -> TokenStream {
+    if let Some(ty) = param_types
+        .iter()
+        .find(|ty| matches!(ty, syn::Type::Array(_)))
+    {
+        return syn::Error::new_spanned(ty, "Array parameters are not supported in components")
+            .to_compile_error()
+            .into();
+    }
+
+    let fn_name = &sig.ident;
+    // integer parameters run at the width of the caller, while a bool may be a single wire
+    let width_checks = param_names
+        .iter()
+        .zip(param_types)
+        .filter_map(|(name, ty)| {
+            if primitive_type(referent(ty))? == (1, false) {
+                return None;
+            }
+            let message = format!(
+                "component `{}` takes `{}` as {{}} bits, found {{}}",
+                fn_name, name
+            );
+            Some(quote! {
+                assert_eq!(#name.len(), N, #message, N, #name.len());
+            })
+        });
+    let expanded = quote! {
+        // `mut` lets nested component calls pass `&mut context` like a top-level circuit does
+        #[allow(unused_assignments, unused_mut, clippy::useless_conversion)]
+        fn #fn_name<const N: usize>(
+            mut context: &mut WRK17CircuitBuilder,
+            #(#param_names: &GateIndexVec),*
+        ) -> GateIndexVec {
+            #(#width_checks)*
+            #(#constants)*
+            let const_true = &context.literal(1, 1);
+            let const_false = &context.literal(0, 1);
+
+            let output = { #transformed_block };
+            output.into()
+        }
+    };
+
+    if debug {
+        eprintln!("Generated code:\n{}", expanded);
+    }
+
+    TokenStream::from(expanded)
+}
+
+/// Returns whether a function over primitive parameters returns a wider primitive integer,
+/// e.g. `fn f(a: u8, b: u8) -> u16`, so that its circuit must work at the output width.
+fn widens_output(sig: &syn::Signature) -> bool {
+    let syn::ReturnType::Type(_, output) = &sig.output else {
+        return false;
+    };
+    let Some((output_width, _)) = primitive_type(output) else {
+        return false;
+    };
+    sig.inputs.iter().all(|input| match input {
+        FnArg::Typed(PatType { ty, .. }) => {
+            primitive_type(element_type(ty)).is_some_and(|(width, _)| width < output_width)
+        }
+        FnArg::Receiver(_) => false,
+    })
+}
+
+/// Returns whether a parameter type may be a record, a named type other than a primitive.
+fn is_record(ty: &syn::Type) -> bool {
+    let ty = referent(ty);
+    matches!(ty, syn::Type::Path(_)) && primitive_type(ty).is_none()
+}
+
+/// Returns the name and type of every record parameter: a parameter of a named type other
+/// than a primitive whose fields the body reads, laid out by its `CircuitInput` impl. Other
+/// such types, e.g. `T` in `fn f(a: T, b: T) -> T`, stay generic.
+fn record_params_of(
+    inputs: &Punctuated<FnArg, syn::Token![,]>,
+    body: &syn::Block,
+) -> Vec<(syn::Ident, syn::Type)> {
+    inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(PatType { pat, ty, .. }) => match &**pat {
+                Pat::Ident(pat_ident)
+                    if is_record(ty) && reads_field(quote! {#body}, &pat_ident.ident) =>
+                {
+                    Some((pat_ident.ident.clone(), referent(ty).clone()))
+                }
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Returns whether `tokens` contain `name.field`, as opposed to a method call `name.f(..)`.
+fn reads_field(tokens: proc_macro2::TokenStream, name: &syn::Ident) -> bool {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    tokens.iter().enumerate().any(|(i, token)| match token {
+        TokenTree::Ident(ident) if ident == name => match &tokens[i + 1..] {
+            [TokenTree::Punct(dot), TokenTree::Ident(_), rest @ ..] if dot.as_char() == '.' => {
+                // arguments or a turbofish follow the name of a method
+                match rest.first() {
+                    Some(TokenTree::Group(group)) => group.delimiter() != Delimiter::Parenthesis,
+                    Some(TokenTree::Punct(colon)) => colon.as_char() != ':',
+                    _ => true,
+                }
+            }
+            _ => false,
+        },
+        TokenTree::Group(group) => reads_field(group.stream(), name),
+        _ => false,
+    })
+}
+
+/// Returns the name of every parameter of a primitive integer type, by value or by reference.
+fn integer_params_of(inputs: &Punctuated<FnArg, syn::Token![,]>) -> Vec<syn::Ident> {
+    inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(PatType { pat, ty, .. }) => match (&**pat, primitive_type(referent(ty))) {
+                (Pat::Ident(pat_ident), Some((width, _))) if width > 1 => {
+                    Some(pat_ident.ident.clone())
+                }
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the name of every parameter passed by reference.
+fn reference_params_of(inputs: &Punctuated<FnArg, syn::Token![,]>) -> Vec<syn::Ident> {
+    inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(PatType { pat, ty, .. }) => match (&**pat, &**ty) {
+                (Pat::Ident(pat_ident), syn::Type::Reference(_)) => Some(pat_ident.ident.clone()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the name and length of every array parameter whose length is a constant.
+fn array_params_of(inputs: &Punctuated<FnArg, syn::Token![,]>) -> Vec<(syn::Ident, u128)> {
+    inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(PatType { pat, ty, .. }) => match (&**pat, referent(ty)) {
+                (Pat::Ident(pat_ident), syn::Type::Array(array)) => {
+                    Some((pat_ident.ident.clone(), eval_const_expr(&array.len)?))
+                }
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Generates a non-generic circuit function for parameters of different primitive types, for
+/// a return type wider than every parameter, or for record parameters. Every input, or every
+/// element of an array input, is widened to the widest of these types before any operation,
+/// and in execute mode the output is resized to the return type before decoding. A record
+/// counts with the width of its widest field, which only its `CircuitInput` impl knows, so
+/// arithmetic on `u8` fields wraps at 8 bits as it would in Rust.
+fn generate_mixed_macro(
+    sig: &syn::Signature,
+    mode: &str,
+    flags: Flags,
+    param_names: &[syn::Ident],
+    param_types: &[&syn::Type],
+    record_params: &[(syn::Ident, syn::Type)],
+    constants: &[proc_macro2::TokenStream],
+    transformed_block: &syn::Block,
+) -> TokenStream {
+    let fn_name = &sig.ident;
+    let inputs = &sig.inputs;
+
+    // records have no primitive width
+    let mut widths = Vec::with_capacity(param_types.len());
+    for (name, ty) in param_names.iter().zip(param_types) {
+        match primitive_type(element_type(ty)) {
+            Some(width) => widths.push(Some(width)),
+            None if record_params.iter().any(|(param, _)| param == name) => widths.push(None),
+            None => {
+                return syn::Error::new_spanned(
+                    ty,
+                    "Parameters of different types must be primitive integers, bool or records",
+                )
+                .to_compile_error()
+                .into()
+            }
+        }
+    }
+
+    // bool parameters are never sign extended, so they mix with either signedness
+    let mut signedness = widths
+        .iter()
+        .zip(param_types)
+        .filter_map(|(width, ty)| Some((width.filter(|(width, _)| *width > 1)?, ty)));
+    let signed = signedness.next().is_some_and(|((_, signed), _)| signed);
+    if let Some((_, ty)) = signedness.find(|((_, other), _)| *other != signed) {
+        return syn::Error::new_spanned(ty, "Cannot mix signed and unsigned parameter types")
+            .to_compile_error()
+            .into();
+    }
+
+    let return_width = match &sig.output {
+        syn::ReturnType::Type(_, ty) => primitive_type(ty).map_or(1, |(width, _)| width),
+        syn::ReturnType::Default => 1,
+    };
+    let width = widths
+        .iter()
+        .flatten()
+        .map(|(width, _)| *width)
+        .chain([return_width])
+        .max()
+        .unwrap_or(1);
+    let records: Vec<&syn::Type> = param_types
+        .iter()
+        .zip(&widths)
+        .filter(|(_, width)| width.is_none())
+        .map(|(ty, _)| referent(ty))
+        .collect();
+    let width = if records.is_empty() {
+        quote! {#width}
+    } else {
+        quote! {{
+            let mut width = #width;
+            #(
+                let fields = <#records as CircuitInput>::FIELDS;
+                let mut i = 0;
+                while i < fields.len() {
+                    if fields[i].1 > width {
+                        width = fields[i].1;
+                    }
+                    i += 1;
+                }
+            )*
+            width
+        }}
+    };
+    let mapped_inputs = param_names.iter().zip(&widths).zip(param_types).map(
+        |((var_name, primitive_width), ty)| {
+            let name = var_name.to_string();
+            let value = input_value(var_name, ty);
+            let Some((input_width, input_signed)) = primitive_width else {
+                return record_input(var_name, ty);
+            };
+            match referent(ty) {
+                syn::Type::Array(array) => {
+                    let len = &array.len;
+                    quote! {
+                        let #var_name = &{
+                            let elements = context
+                                .input_array::<#input_width, { #len }>(#value.map(Into::into));
+                            context.name_input(#name);
+                            elements.map(|wires| context.resize(&wires, N, #input_signed))
+                        };
+                    }
+                }
+                _ => quote! {
+                    let #var_name = &{
+                        let wires = context.input::<#input_width>(&#value.into());
+                        context.name_input(#name);
+                        context.resize(&wires, N, #input_signed)
+                    };
+                },
+            }
+        },
+    );
+
+    let context = if signed {
+        quote! { WRK17CircuitBuilder::signed() }
+    } else {
+        quote! { WRK17CircuitBuilder::default() }
+    };
+
+    // a primitive result is decoded at its own width, and so are the elements of an array
+    let (output_width, element_width) = match &sig.output {
+        syn::ReturnType::Type(_, ty) => (
+            primitive_type(ty).map_or(quote! {N}, |(width, _)| quote! {#width}),
+            primitive_type(element_type(ty)).map_or(quote! {N}, |(width, _)| quote! {#width}),
+        ),
+        syn::ReturnType::Default => (quote! {N}, quote! {N}),
+    };
+    let (output_type, operation) = output_operation(
+        mode,
+        flags,
+        &sig.output,
+        element_width,
+        quote! {
+            let output = context.resize(&output, #output_width, #signed);
+            let compiled_circuit = context.compile(&output);
+            let result = context
+                .execute::<#output_width>(&compiled_circuit)
+                .unwrap_or_else(|error| panic!("Execution failed: {}", error));
+            result.into()
+        },
+    );
+    let allowed_lints = allowed_lints();
+    let body = circuit_body(constants, transformed_block, &operation);
+
+    let expanded = quote! {
+        #allowed_lints
+        fn #fn_name(#inputs) -> #output_type {
+            const N: usize = #width;
+            let mut context = #context;
+            #(#mapped_inputs)*
+            #body
+        }
+    };
+
+    if flags.debug {
+        eprintln!("Generated code:\n{}", expanded);
+    }
+
+    TokenStream::from(expanded)
+}
+
+/// Allocates the inputs of a record parameter at the width of the record, which may exceed
+/// `N`; only its fields, each cut out and resized to `N` bits, take part in operations.
+fn record_input(var_name: &syn::Ident, ty: &syn::Type) -> proc_macro2::TokenStream {
+    let name = var_name.to_string();
+    let value = input_value(var_name, ty);
+    let record = referent(ty);
+    quote! {
+        let #var_name = &{
+            let wires = context.input::<{ <#record as CircuitInput>::WIDTH }>(&#value.into());
+            context.name_input(#name);
+            wires
+        };
+    }
+}
+
+/// Returns the lints to allow on a generated circuit function: the generic path names its
+/// type parameter after the primitive type, e.g. `u8`, and the lowered body assigns and
+/// converts wire groups more than hand-written code would.
+fn allowed_lints() -> proc_macro2::TokenStream {
+    quote! {
+        #[allow(
+            non_camel_case_types,
+            non_snake_case,
+            clippy::builtin_type_shadow,
+            clippy::useless_conversion,
+            unused_assignments
+        )]
+    }
+}
+
+/// Generates the body shared by circuit functions once their inputs are allocated: the
+/// constants, the transformed block computing `output`, and the `operation` on it.
+fn circuit_body(
+    constants: &[proc_macro2::TokenStream],
+    transformed_block: &syn::Block,
+    operation: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    quote! {
+        #(#constants)*
+        let const_true = &context.literal(1, 1);
+        let const_false = &context.literal(0, 1);
+
+        // Use the transformed function block (with context.add and if/else replacements)
+        let output = { #transformed_block };
+
+        #operation
+    }
+}
+
+/// Returns the result type of a circuit function in `mode` and the operation that turns the
+/// `output` of its body into that result: the compiled circuit and its inputs in compile
+/// mode, plus what its flags ask for, the builder and the output wires in build mode, and
+/// otherwise the decoded result of executing the circuit. Tuple results are split into
+/// elements of their declared widths and array results into elements of `element_width`
+/// bits; any other result is decoded by `execute`.
+fn output_operation(
+    mode: &str,
+    flags: Flags,
+    output: &syn::ReturnType,
+    element_width: proc_macro2::TokenStream,
+    execute: proc_macro2::TokenStream,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    // a `bool` result is a single wire, whatever the width of the inputs
+    let (output_wires, returns_array) = match output {
+        syn::ReturnType::Type(_, ty) if primitive_type(ty) == Some((1, false)) => (
+            quote! { let output = context.resize(&output.into(), 1, false); },
+            false,
+        ),
+        syn::ReturnType::Type(_, ty) => (
+            quote! { let output: GateIndexVec = output.into(); },
+            matches!(**ty, syn::Type::Array(_)),
+        ),
+        syn::ReturnType::Default => (quote! { let output: GateIndexVec = output.into(); }, false),
+    };
+
+    if mode == "compile" {
+        let (output_type, repack, extras) = compile_output(flags, output);
+        let operation = quote! {
+            #output_wires
+            #repack
+            (context.compile(&output), context.inputs().to_vec() #extras)
+        };
+        return (output_type, operation);
+    }
+    if mode == "build" {
+        let operation = quote! {
+            #output_wires
+            (context, output)
+        };
+        return (build_output(), operation);
+    }
+
+    let output_type = match output {
+        syn::ReturnType::Type(_, ty) => quote! {#ty},
+        syn::ReturnType::Default => quote! {()},
+    };
+    let operation = if let Some(elements) = tuple_elements(output) {
+        decode_tuple(&elements)
+    } else if returns_array {
+        decode_array(element_width)
+    } else {
+        quote! {
+            #output_wires
+            #execute
+        }
+    };
+    (output_type, operation)
+}
+
+/// Returns the width and signedness of every element when the function returns a non-empty
+/// tuple.
+fn tuple_elements(output: &syn::ReturnType) -> Option<Vec<(proc_macro2::TokenStream, bool)>> {
+    match output {
+        syn::ReturnType::Type(_, ty) => match &**ty {
+            syn::Type::Tuple(tuple) if !tuple.elems.is_empty() => {
+                Some(tuple.elems.iter().map(element_layout).collect())
+            }
+            _ => None,
+        },
+        syn::ReturnType::Default => None,
+    }
+}
+
+/// Returns the width and signedness of a result element of type `ty`: those of a primitive
+/// type, and `N` bits unsigned for any other type.
+fn element_layout(ty: &syn::Type) -> (proc_macro2::TokenStream, bool) {
+    primitive_type(ty).map_or((quote! {N}, false), |(width, signed)| {
+        (quote! {#width}, signed)
+    })
+}
+
+/// Returns the result type of a compile-mode function, the statements that lay out its
+/// output and the extra tuple elements to return, whatever result type it declares. With the
+/// `layout` flag the function also returns the input layout of its circuit, with each entry
+/// named after its parameter. With the `output_widths` flag it then returns the width of
+/// every element of its declared result, and each element is resized to that width in the
+/// circuit's outputs.
+fn compile_output(
+    flags: Flags,
+    output: &syn::ReturnType,
+) -> (
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+) {
+    let mut output_type = vec![quote! {Circuit}, quote! {Vec<bool>}];
+    let mut extras = vec![];
+    let mut repack = quote! {};
+    if flags.layout {
+        output_type.push(quote! {Vec<InputSpec>});
+        extras.push(quote! {context.input_layout().to_vec()});
+    }
+    if flags.output_widths {
+        let (widths, statements) = match output_elements(output) {
+            Ok((elements, statements)) => {
+                let widths = elements.into_iter().map(|(width, _)| width);
+                (quote! {vec![#(#widths),*]}, statements)
+            }
+            Err(error) => (quote! {vec![]}, error.to_compile_error()),
+        };
+        output_type.push(quote! {Vec<usize>});
+        extras.push(widths);
+        repack = statements;
+    }
+    (quote! {(#(#output_type),*)}, repack, quote! {#(, #extras)*})
+}
+
+/// Returns the width and signedness of every element of the declared result, with the
+/// statements that resize the output to them: the elements of a tuple, those of an array of
+/// constant length, or else the result itself. The declared result must be what the body
+/// computes, e.g. `-> (u8, bool)`, rather than the `(Circuit, Vec<bool>)` that compile mode
+/// returns.
+fn output_elements(
+    output: &syn::ReturnType,
+) -> syn::Result<(
+    Vec<(proc_macro2::TokenStream, bool)>,
+    proc_macro2::TokenStream,
+)> {
+    let syn::ReturnType::Type(_, ty) = output else {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "Expected typed return type",
+        ));
+    };
+    match &**ty {
+        syn::Type::Tuple(tuple) if is_compile_result(tuple) => Err(syn::Error::new_spanned(
+            ty,
+            "`output_widths` needs the result the body computes as the return type, e.g. `-> (u8, bool)`",
+        )),
+        syn::Type::Tuple(tuple) if !tuple.elems.is_empty() => {
+            let elements: Vec<_> = tuple.elems.iter().map(element_layout).collect();
+            let statements = repack_elements(&elements);
+            Ok((elements, statements))
+        }
+        syn::Type::Array(array) => {
+            let Some(len) = eval_const_expr(&array.len) else {
+                return Err(syn::Error::new_spanned(
+                    &array.len,
+                    "Array length must be a constant expression",
+                ));
+            };
+            let elements = vec![element_layout(&array.elem); len as usize];
+            let statements = repack_elements(&elements);
+            Ok((elements, statements))
+        }
+        ty => {
+            let (width, signed) = element_layout(ty);
+            let statements = quote! { let output = context.resize(&output, #width, #signed); };
+            Ok((vec![(width, signed)], statements))
+        }
+    }
+}
+
+/// Returns whether a tuple type is the `(Circuit, ...)` result of a compile-mode function.
+fn is_compile_result(tuple: &syn::TypeTuple) -> bool {
+    match tuple.elems.first() {
+        Some(syn::Type::Path(path)) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Circuit"),
+        _ => false,
+    }
+}
+
+/// Resizes the `N`-bit wire group of every element of a tuple or array value to the width of
+/// its element type, extending signed elements by their sign bit, so that the output holds
+/// the elements back to back at their own widths.
+fn repack_elements(elements: &[(proc_macro2::TokenStream, bool)]) -> proc_macro2::TokenStream {
+    let count = elements.len();
+    let indices = 0..count;
+    let (widths, signed): (Vec<_>, Vec<_>) = elements.iter().cloned().unzip();
+    quote! {
+        let groups = context.split(&output, &[N; #count]);
+        let elements = vec![#(context.resize(&groups[#indices], #widths, #signed)),*];
+        let output = context.concat(&elements);
+    }
+}
+
+/// Returns the result type of a build-mode function: the populated builder and the output
+/// wires, for composing with other fragments before compiling.
+fn build_output() -> proc_macro2::TokenStream {
+    quote! {(WRK17CircuitBuilder, GateIndexVec)}
+}
+
+/// Executes the circuit and splits its output into array elements, matching the concatenated
+/// `N`-bit wire groups of an array value. Each element keeps the low `element_width` bits of
+/// its group, which is `N` unless the element type is narrower than the circuit.
+fn decode_array(element_width: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote! {
+        let compiled_circuit = context.compile(&output.into());
+        let bits = context.execute_bits(&compiled_circuit).unwrap_or_else(|error| panic!("Execution failed: {}", error));
+        std::array::from_fn(|i| GarbledUint::<#element_width>::new(bits[i * N..i * N + #element_width].to_vec()).into())
+    }
+}
+
+/// Executes the circuit and splits its output into tuple elements of their declared widths:
+/// the `N`-bit wire group of each element is resized to its width before compiling.
+fn decode_tuple(elements: &[(proc_macro2::TokenStream, bool)]) -> proc_macro2::TokenStream {
+    let repack = repack_elements(elements);
+    let widths = elements.iter().map(|(width, _)| width);
+    quote! {
+        let output: GateIndexVec = output.into();
+        #repack
+        let compiled_circuit = context.compile(&output);
+        let bits = context.execute_bits(&compiled_circuit).unwrap_or_else(|error| panic!("Execution failed: {}", error));
+        let mut bits = bits.into_iter();
+        (#(GarbledUint::<#widths>::new(bits.by_ref().take(#widths).collect()).into(),)*)
+    }
+}
+
+/// Returns the element type of an array type, or the type itself, behind any reference.
+fn element_type(ty: &syn::Type) -> &syn::Type {
+    match referent(ty) {
+        syn::Type::Array(array) => &array.elem,
+        other => other,
+    }
+}
+
+/// Returns the type a reference type points to, or the type itself.
+fn referent(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Reference(reference) => &reference.elem,
+        other => other,
+    }
+}
+
+/// Returns the value of a parameter to convert into circuit inputs: an owned parameter is
+/// consumed, and a by-reference one is dereferenced and cloned.
+fn input_value(var_name: &syn::Ident, ty: &syn::Type) -> proc_macro2::TokenStream {
+    match ty {
+        syn::Type::Reference(_) => quote! { (*#var_name).clone() },
+        _ => quote! { #var_name },
+    }
+}
+
+/// Returns the bit width and signedness of a primitive integer or bool type.
+fn primitive_type(ty: &syn::Type) -> Option<(usize, bool)> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let ident = type_path.path.get_ident()?;
+    let primitive = match ident.to_string().as_str() {
+        "bool" => (1, false),
+        "u8" => (8, false),
+        "u16" => (16, false),
+        "u32" => (32, false),
+        "u64" => (64, false),
+        "u128" => (128, false),
+        "i8" => (8, true),
+        "i16" => (16, true),
+        "i32" => (32, true),
+        "i64" => (64, true),
+        "i128" => (128, true),
+        _ => return None,
+    };
+    Some(primitive)
+}
+
+/// Returns the width the circuit of a function over primitive parameters works at: the widest
+/// parameter or array element type, or the return type if that is wider.
+fn literal_width(sig: &syn::Signature) -> Option<usize> {
+    let mut width = 0;
+    for input in &sig.inputs {
+        let FnArg::Typed(PatType { ty, .. }) = input else {
+            return None;
+        };
+        width = width.max(primitive_type(element_type(ty))?.0);
+    }
+    if let syn::ReturnType::Type(_, ty) = &sig.output {
+        width = width.max(primitive_type(ty).map_or(0, |(width, _)| width));
+    }
+    Some(width)
+}
+
 /// Traverse and transform the function body, replacing binary operators and if/else expressions.
 /// Also collects constants to add to the circuit context.
-fn modify_body(block: syn::Block, constants: &mut Vec<proc_macro2::TokenStream>) -> syn::Block {
-    let stmts = block
-        .stmts
+fn modify_body(block: syn::Block, lowering: &mut Lowering) -> syn::Block {
+    // a local shadowing a parameter hides it until the end of the block
+    let array_params = lowering.array_params.clone();
+    let record_params = lowering.record_params.clone();
+    let integer_params = lowering.integer_params.clone();
+    let reference_params = lowering.reference_params.clone();
+    let stmts = inline_consts(block.stmts)
+        .unwrap_or_else(|error| vec![syn::Stmt::Expr(error_expr(error), None)])
         .into_iter()
-        .map(|stmt| {
+        .flat_map(|stmt| {
             match stmt {
+                // for loops are unrolled into one block per iteration
+                syn::Stmt::Expr(Expr::ForLoop(for_loop), _) => unroll_for_loop(for_loop, lowering),
+                // bounded while loops are unrolled into masked iterations
+                syn::Stmt::Expr(Expr::While(while_loop), _) => {
+                    unroll_while_loop(while_loop, lowering)
+                }
+                // compound assignment statements are lowered as `a = a op b`
+                syn::Stmt::Expr(Expr::Binary(binary), Some(semi))
+                    if compound_op(&binary.op).is_some() =>
+                {
+                    let assign = Expr::Assign(ExprAssign {
+                        attrs: vec![],
+                        left: binary.left.clone(),
+                        eq_token: Default::default(),
+                        right: Box::new(Expr::Binary(binary)),
+                    });
+                    vec![syn::Stmt::Expr(
+                        replace_expressions(assign, lowering),
+                        Some(semi),
+                    )]
+                }
                 syn::Stmt::Expr(expr, semi_opt) => {
-                    syn::Stmt::Expr(replace_expressions(expr, constants), semi_opt)
+                    vec![syn::Stmt::Expr(
+                        replace_expressions(expr, lowering),
+                        semi_opt,
+                    )]
                 }
                 syn::Stmt::Local(mut local) => {
                     if let Some(local_init) = &mut local.init {
                         // Replace the initializer expression
                         //local_init.expr =
-                        //    Box::new(replace_expressions(*local_init.expr.clone(), constants));
+                        //    Box::new(replace_expressions(*local_init.expr.clone(), lowering));
 
-                        let local_expr = replace_expressions(*local_init.expr.clone(), constants);
+                        let local_expr = replace_expressions(*local_init.expr.clone(), lowering);
 
-                        if let syn::Pat::Ident(ref pat_ident) = local.pat {
+                        if let syn::Pat::Tuple(ref pat_tuple) = local.pat {
+                            local_init.expr = Box::new(destructure_tuple(
+                                pat_tuple,
+                                &local_init.expr,
+                                local_expr,
+                            ));
+                        } else if let syn::Pat::Ident(ref pat_ident) = local.pat {
                             if pat_ident.mutability.is_some() {
                                 local_init.expr = Box::new(syn::parse_quote! {
                                     #local_expr.clone()
@@ -180,32 +1376,713 @@ fn modify_body(block: syn::Block, constants: &mut Vec<proc_macro2::TokenStream>)
                             }
                         }
                     }
-                    syn::Stmt::Local(local)
+                    let mut bound = vec![];
+                    collect_bound_idents(&local.pat, &mut bound);
+                    lowering
+                        .array_params
+                        .retain(|(name, _)| !bound.contains(name));
+                    lowering
+                        .record_params
+                        .retain(|(name, _)| !bound.contains(name));
+                    lowering.integer_params.retain(|name| !bound.contains(name));
+                    lowering
+                        .reference_params
+                        .retain(|name| !bound.contains(name));
+                    vec![syn::Stmt::Local(local)]
                 }
 
-                other => other,
+                other => vec![other],
             }
         })
         .collect();
+    lowering.array_params = array_params;
+    lowering.record_params = record_params;
+    lowering.integer_params = integer_params;
+    lowering.reference_params = reference_params;
+
+    syn::Block {
+        stmts,
+        brace_token: syn::token::Brace::default(),
+    }
+}
+
+/// Collects the variables that the pattern of a `let` binds.
+fn collect_bound_idents(pat: &Pat, bound: &mut Vec<syn::Ident>) {
+    match pat {
+        Pat::Ident(pat_ident) => bound.push(pat_ident.ident.clone()),
+        Pat::Type(pat_type) => collect_bound_idents(&pat_type.pat, bound),
+        Pat::Paren(pat_paren) => collect_bound_idents(&pat_paren.pat, bound),
+        Pat::Reference(pat_reference) => collect_bound_idents(&pat_reference.pat, bound),
+        Pat::Tuple(pat_tuple) => {
+            for elem in &pat_tuple.elems {
+                collect_bound_idents(elem, bound);
+            }
+        }
+        Pat::TupleStruct(pat_tuple_struct) => {
+            for elem in &pat_tuple_struct.elems {
+                collect_bound_idents(elem, bound);
+            }
+        }
+        Pat::Slice(pat_slice) => {
+            for elem in &pat_slice.elems {
+                collect_bound_idents(elem, bound);
+            }
+        }
+        Pat::Struct(pat_struct) => {
+            for field in &pat_struct.fields {
+                collect_bound_idents(&field.pat, bound);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Inlines the `const NAME: T = value;` items of a block into the statements that follow
+/// them, so that named constants are lowered like the integer literals they stand for rather
+/// than as inputs. The literals keep the declared type as their suffix, and a value that does
+/// not fit it is an error. A name that is not declared is left alone and fails as it would
+/// in Rust.
+fn inline_consts(stmts: Vec<syn::Stmt>) -> syn::Result<Vec<syn::Stmt>> {
+    let mut inlined = Vec::with_capacity(stmts.len());
+    let mut stmts = stmts.into_iter();
+    while let Some(stmt) = stmts.next() {
+        let syn::Stmt::Item(syn::Item::Const(item_const)) = stmt else {
+            inlined.push(stmt);
+            continue;
+        };
+        let Some(value) = eval_const_expr(&item_const.expr) else {
+            return Err(syn::Error::new_spanned(
+                &item_const.expr,
+                "constants in circuits must be integer literals or arithmetic over them",
+            ));
+        };
+        let ty = &item_const.ty;
+        let size = match quote! {#ty}.to_string().as_str() {
+            "usize" => Some((64, false)),
+            "isize" => Some((64, true)),
+            _ => None,
+        };
+        let Some((width, signed)) = primitive_type(ty).filter(|(width, _)| *width > 1).or(size)
+        else {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "constants in circuits must have a primitive integer type",
+            ));
+        };
+        let bits = if signed { width - 1 } else { width };
+        if bits < 128 && value >> bits != 0 {
+            return Err(syn::Error::new_spanned(
+                &item_const.expr,
+                format!("Constant {} does not fit in `{}`", value, quote! {#ty}),
+            ));
+        }
+        let literal = syn::LitInt::new(
+            &format!("{}{}", value, quote! {#ty}),
+            proc_macro2::Span::call_site(),
+        );
+        let mut rest = syn::Block {
+            brace_token: Default::default(),
+            stmts: stmts.collect(),
+        };
+        Substitute {
+            ident: &item_const.ident,
+            value: &literal,
+            constant: true,
+        }
+        .visit_block_mut(&mut rest);
+        stmts = rest.stmts.into_iter();
+    }
+    Ok(inlined)
+}
+
+/// Replaces the variable or constant `ident` with the literal `value` wherever an expression
+/// refers to it, leaving fields, methods and path segments of the same name alone. A binding
+/// of the same name shadows a variable until the end of its scope. The name of a constant in
+/// a pattern is a constant pattern rather than a binding, so it is replaced as well.
+struct Substitute<'a> {
+    ident: &'a syn::Ident,
+    value: &'a syn::LitInt,
+    constant: bool,
+}
+
+impl Substitute<'_> {
+    /// Returns the literal, spanned at the reference it replaces.
+    fn literal(&self, span: proc_macro2::Span) -> syn::ExprLit {
+        let mut value = self.value.clone();
+        value.set_span(span);
+        syn::ExprLit {
+            attrs: vec![],
+            lit: Lit::Int(value),
+        }
+    }
+
+    /// Returns whether `pat` binds a new variable of the substituted name.
+    fn shadows(&self, pat: &Pat) -> bool {
+        let mut bound = vec![];
+        collect_bound_idents(pat, &mut bound);
+        !self.constant && bound.contains(self.ident)
+    }
+}
+
+impl VisitMut for Substitute<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Path(path) if path.qself.is_none() && path.path.is_ident(self.ident) => {
+                let span = syn::spanned::Spanned::span(&path.path);
+                *expr = Expr::Lit(self.literal(span));
+            }
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_pat_mut(&mut self, pat: &mut Pat) {
+        match pat {
+            Pat::Ident(pat_ident)
+                if self.constant
+                    && pat_ident.ident == *self.ident
+                    && pat_ident.by_ref.is_none()
+                    && pat_ident.mutability.is_none()
+                    && pat_ident.subpat.is_none() =>
+            {
+                *pat = Pat::Lit(self.literal(pat_ident.ident.span()));
+            }
+            _ => visit_mut::visit_pat_mut(self, pat),
+        }
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        for stmt in &mut block.stmts {
+            match stmt {
+                syn::Stmt::Local(local) => {
+                    if let Some(init) = &mut local.init {
+                        self.visit_local_init_mut(init);
+                    }
+                    // the statements after a shadowing binding see the new variable
+                    if self.shadows(&local.pat) {
+                        return;
+                    }
+                    self.visit_pat_mut(&mut local.pat);
+                }
+                // a nested constant may be defined in terms of this one, or shadow it
+                syn::Stmt::Item(syn::Item::Const(item_const)) => {
+                    self.visit_expr_mut(&mut item_const.expr);
+                    if item_const.ident == *self.ident {
+                        return;
+                    }
+                }
+                // other items do not see the variables of the body
+                syn::Stmt::Item(_) => {}
+                stmt => self.visit_stmt_mut(stmt),
+            }
+        }
+    }
+
+    fn visit_expr_closure_mut(&mut self, closure: &mut syn::ExprClosure) {
+        if !closure.inputs.iter().any(|input| self.shadows(input)) {
+            visit_mut::visit_expr_closure_mut(self, closure);
+        }
+    }
+
+    fn visit_expr_for_loop_mut(&mut self, for_loop: &mut ExprForLoop) {
+        self.visit_expr_mut(&mut for_loop.expr);
+        if !self.shadows(&for_loop.pat) {
+            self.visit_block_mut(&mut for_loop.body);
+        }
+    }
+
+    fn visit_arm_mut(&mut self, arm: &mut syn::Arm) {
+        if !self.shadows(&arm.pat) {
+            visit_mut::visit_arm_mut(self, arm);
+        }
+    }
+}
+
+/// Lowers the initializer of `let (a, b, ..) = value;` into one wire group per pattern
+/// element. The value is split into `N`-bit groups, the layout of tuple expressions and of
+/// components returning tuples; a tuple expression of a different arity is a spanned error,
+/// and any other value is checked against the pattern's width when the circuit is built.
+fn destructure_tuple(pat_tuple: &syn::PatTuple, init: &Expr, value: Expr) -> Expr {
+    let arity = pat_tuple.elems.len();
+    if let Expr::Tuple(tuple) = init {
+        if tuple.elems.len() != arity {
+            return error_expr(syn::Error::new_spanned(
+                pat_tuple,
+                format!(
+                    "Cannot destructure a tuple of {} elements into {} bindings",
+                    tuple.elems.len(),
+                    arity
+                ),
+            ));
+        }
+    }
+    if let Some(elem) = pat_tuple
+        .elems
+        .iter()
+        .find(|elem| !matches!(elem, Pat::Ident(_) | Pat::Wild(_)))
+    {
+        return error_expr(syn::Error::new_spanned(
+            elem,
+            "Tuple patterns in circuits may only bind identifiers or `_`",
+        ));
+    }
+
+    let offsets = 0..arity;
+    syn::parse_quote! {{
+        let wires: GateIndexVec = (#value).into();
+        assert_eq!(
+            wires.len(),
+            #arity * N,
+            "cannot destructure {} wires into {} elements of {} bits",
+            wires.len(),
+            #arity,
+            N
+        );
+        (#(GateIndexVec::new(wires.iter().skip(#offsets * N).take(N).copied().collect()),)*)
+    }}
+}
+
+/// Lowers early `return` statements, which a circuit cannot take, into data-dependent
+/// selection. A `return x;` ends its block with the value `x`, and the statements after an
+/// `if` containing a return are moved into both of its branches, so the `if` becomes the
+/// value of the block and is built as a mux over the returned and fall-through values.
+fn lower_returns(stmts: Vec<syn::Stmt>) -> syn::Result<Vec<syn::Stmt>> {
+    let mut lowered = vec![];
+    let mut stmts = stmts.into_iter();
+    while let Some(stmt) = stmts.next() {
+        match stmt {
+            syn::Stmt::Expr(Expr::Return(expr_return), _) => {
+                let Some(value) = expr_return.expr else {
+                    return Err(syn::Error::new_spanned(
+                        expr_return.return_token,
+                        "Return statement must return a value",
+                    ));
+                };
+                // anything after the return is unreachable
+                lowered.push(syn::Stmt::Expr(*value, None));
+                return Ok(lowered);
+            }
+            syn::Stmt::Expr(Expr::If(expr_if), _) if find_return(quote! {#expr_if}).is_some() => {
+                let rest: Vec<syn::Stmt> = stmts.collect();
+                lowered.push(syn::Stmt::Expr(
+                    Expr::If(lower_if_returns(expr_if, &rest)?),
+                    None,
+                ));
+                return Ok(lowered);
+            }
+            other => {
+                if let Some(span) = find_return(quote! {#other}) {
+                    return Err(syn::Error::new(
+                        span,
+                        "Return statement is only supported directly in the function body or in `if` branches",
+                    ));
+                }
+                lowered.push(other);
+            }
+        }
+    }
+    Ok(lowered)
+}
+
+/// Appends the statements that follow `expr_if` to each of its branches, then lowers the
+/// returns within them.
+fn lower_if_returns(mut expr_if: ExprIf, rest: &[syn::Stmt]) -> syn::Result<ExprIf> {
+    fn continue_with(mut stmts: Vec<syn::Stmt>, rest: &[syn::Stmt]) -> syn::Result<Vec<syn::Stmt>> {
+        if !rest.is_empty() {
+            // a trailing expression is now followed by more statements
+            if let Some(syn::Stmt::Expr(_, semi @ None)) = stmts.last_mut() {
+                *semi = Some(Default::default());
+            }
+            stmts.extend(rest.iter().cloned());
+        }
+        lower_returns(stmts)
+    }
+
+    expr_if.then_branch.stmts = continue_with(expr_if.then_branch.stmts, rest)?;
+    let else_expr = match expr_if.else_branch.take() {
+        Some((_, else_expr)) => *else_expr,
+        None => Expr::Block(syn::parse_quote! {{}}),
+    };
+    let else_expr = match else_expr {
+        Expr::If(nested) => Expr::If(lower_if_returns(nested, rest)?),
+        Expr::Block(mut else_block) => {
+            else_block.block.stmts = continue_with(else_block.block.stmts, rest)?;
+            Expr::Block(else_block)
+        }
+        other => other,
+    };
+    expr_if.else_branch = Some((Default::default(), Box::new(else_expr)));
+    Ok(expr_if)
+}
+
+/// Returns the span of the first `return` keyword in `tokens`.
+fn find_return(tokens: proc_macro2::TokenStream) -> Option<proc_macro2::Span> {
+    tokens.into_iter().find_map(|token| match token {
+        TokenTree::Ident(ident) if ident == "return" => Some(ident.span()),
+        TokenTree::Group(group) => find_return(group.stream()),
+        _ => None,
+    })
+}
+
+/// Unrolls a `while` loop annotated with `#[circuit_loop(max = N)]` into `N` iterations. A
+/// circuit cannot stop early, so every iteration is built: a running wire records whether the
+/// condition has held in every iteration so far, and the assignments of an iteration only
+/// take effect while it is set. After `N` iterations the loop stops regardless.
+fn unroll_while_loop(while_loop: ExprWhile, lowering: &mut Lowering) -> Vec<syn::Stmt> {
+    let error = |error: syn::Error| vec![syn::Stmt::Expr(error_expr(error), None)];
+
+    let mut max = None;
+    for attr in &while_loop.attrs {
+        if !attr.path().is_ident("circuit_loop") {
+            return error(syn::Error::new_spanned(
+                attr,
+                "Unsupported attribute on a `while` loop",
+            ));
+        }
+        match attr.parse_args_with(parse_loop_bound) {
+            Ok(bound) => max = Some(bound),
+            Err(err) => return error(err),
+        }
+    }
+    let Some(max) = max else {
+        return error(syn::Error::new_spanned(
+            while_loop.while_token,
+            "`while` loops in circuits need an iteration bound such as `#[circuit_loop(max = 8)]`, since a circuit has a fixed size",
+        ));
+    };
+    if let Expr::Let(expr_let) = &*while_loop.cond {
+        return error(syn::Error::new_spanned(
+            expr_let,
+            "`while let` is not supported in circuits",
+        ));
+    }
+
+    let mut assigned = vec![];
+    collect_assigned_idents(&while_loop.body, &mut assigned);
+    let previous: Vec<_> = assigned
+        .iter()
+        .map(|ident| format_ident!("{}_if_false", ident))
+        .collect();
+    let cond = replace_expressions(*while_loop.cond, lowering);
+    let body = modify_body(while_loop.body, lowering);
+
+    let iteration = quote! {{
+        let cond = #cond;
+        while_running = context.push_and(&while_running, &cond.into());
+        #(let #previous = #assigned.clone();)*
+        #body;
+        #(#assigned = context.mux(&while_running, &#assigned, &#previous);)*
+    }};
+    if max == 0 {
+        return vec![];
+    }
+    let iterations = std::iter::repeat(iteration).take(max);
+    vec![syn::parse_quote! {{
+        let mut while_running = context.one_wire();
+        #(#iterations)*
+    }}]
+}
+
+/// Parses the `max = N` arguments of a `#[circuit_loop]` attribute.
+fn parse_loop_bound(input: syn::parse::ParseStream) -> syn::Result<usize> {
+    let key: syn::Ident = input.parse()?;
+    if key != "max" {
+        return Err(syn::Error::new_spanned(
+            key,
+            "Expected `max = <iterations>`",
+        ));
+    }
+    input.parse::<syn::Token![=]>()?;
+    let bound: syn::LitInt = input.parse()?;
+    bound.base10_parse()
+}
+
+/// Unrolls a `for` loop with constant bounds into a sequence of blocks, one per iteration.
+/// The loop variable is substituted with the iteration's literal value before the body is
+/// transformed, so it can be used both as a circuit constant and as a plain index.
+fn unroll_for_loop(for_loop: ExprForLoop, lowering: &mut Lowering) -> Vec<syn::Stmt> {
+    let range = match &*for_loop.expr {
+        Expr::Range(ExprRange {
+            start: Some(start),
+            end: Some(end),
+            limits,
+            ..
+        }) => eval_const_expr(start)
+            .zip(eval_const_expr(end))
+            .map(|(start, end)| match limits {
+                RangeLimits::HalfOpen(_) => start..end,
+                RangeLimits::Closed(_) => start..end.saturating_add(1),
+            }),
+        _ => None,
+    };
+
+    if range.is_none() {
+        if let Some((array, len)) = lowering.array_param(&for_loop.expr) {
+            return unroll_array_loop(&for_loop, &array, len, lowering);
+        }
+    }
+    let Some(range) = range else {
+        let error = syn::Error::new_spanned(
+            &for_loop.expr,
+            "for loops in circuits require a range with constant integer bounds or an array parameter",
+        );
+        return vec![syn::Stmt::Expr(error_expr(error), None)];
+    };
+
+    let loop_var = match &*for_loop.pat {
+        Pat::Ident(pat_ident) => Some(pat_ident.ident.clone()),
+        _ => None,
+    };
+
+    range
+        .map(|i| {
+            let mut body = for_loop.body.clone();
+            if let Some(ident) = &loop_var {
+                Substitute {
+                    ident,
+                    value: &syn::LitInt::new(&i.to_string(), proc_macro2::Span::call_site()),
+                    constant: false,
+                }
+                .visit_block_mut(&mut body);
+            }
+            let block = replace_expressions(syn::parse_quote! { #body }, lowering);
+            syn::Stmt::Expr(block, Some(syn::token::Semi::default()))
+        })
+        .collect()
+}
+
+/// Unrolls `for x in array` over an array parameter into one block per element, each
+/// binding the loop pattern to the element at a constant index.
+fn unroll_array_loop(
+    for_loop: &ExprForLoop,
+    array: &syn::Ident,
+    len: u128,
+    lowering: &mut Lowering,
+) -> Vec<syn::Stmt> {
+    let pat = &for_loop.pat;
+    let stmts = &for_loop.body.stmts;
+    (0..len)
+        .map(|i| {
+            let index = Literal::u128_unsuffixed(i);
+            let block = replace_expressions(
+                syn::parse_quote! {{
+                    let #pat = #array[#index];
+                    #(#stmts)*
+                }},
+                lowering,
+            );
+            syn::Stmt::Expr(block, Some(syn::token::Semi::default()))
+        })
+        .collect()
+}
+
+/// Lowers a commutative binary operation with a compile-time constant on either side to
+/// `context.<method>(&other, value)`, so the constant never becomes a circuit input.
+fn constant_operand(
+    left: &Expr,
+    right: &Expr,
+    method: &str,
+    lowering: &mut Lowering,
+) -> Option<Expr> {
+    let method = format_ident!("{}", method);
+    let (value, constant, operand) = match (eval_const_expr(left), eval_const_expr(right)) {
+        (Some(_), Some(_)) | (None, None) => return None,
+        (Some(value), None) => (value, left, right),
+        (None, Some(value)) => (value, right, left),
+    };
+    if let Err(error) = lowering.check_literal(value, false, constant) {
+        return Some(error_expr(error));
+    }
+    let operand = replace_expressions(operand.clone(), lowering);
+    Some(syn::parse_quote! {{
+        let operand = #operand;
+        context.#method(&operand.into(), #value)
+    }})
+}
+
+/// Lowers a comparison against a constant zero, in either operand position, to `method`,
+/// which tests the other operand alone.
+fn zero_operand(left: &Expr, right: &Expr, method: &str, lowering: &mut Lowering) -> Option<Expr> {
+    let method = format_ident!("{}", method);
+    let operand = match (eval_const_expr(left), eval_const_expr(right)) {
+        (Some(0), None) => right,
+        (None, Some(0)) => left,
+        _ => return None,
+    };
+    let operand = replace_expressions(operand.clone(), lowering);
+    Some(syn::parse_quote! {{
+        let operand = #operand;
+        context.#method(&operand.into())
+    }})
+}
+
+/// Evaluates an integer literal or a simple arithmetic expression over integer literals.
+fn eval_const_expr(expr: &Expr) -> Option<u128> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(lit_int),
+            ..
+        }) => lit_int.base10_parse::<u128>().ok(),
+        Expr::Paren(expr_paren) => eval_const_expr(&expr_paren.expr),
+        Expr::Group(expr_group) => eval_const_expr(&expr_group.expr),
+        Expr::Binary(ExprBinary {
+            left, op, right, ..
+        }) => {
+            let left = eval_const_expr(left)?;
+            let right = eval_const_expr(right)?;
+            match op {
+                BinOp::Add(_) => left.checked_add(right),
+                BinOp::Sub(_) => left.checked_sub(right),
+                BinOp::Mul(_) => left.checked_mul(right),
+                BinOp::Div(_) => left.checked_div(right),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns `k` when `expr` is a constant `2^k`, so that dividing by it is a rewiring.
+fn power_of_two(expr: &Expr) -> Option<usize> {
+    eval_const_expr(expr)
+        .filter(|value| value.is_power_of_two())
+        .map(|value| value.trailing_zeros() as usize)
+}
+
+/// Returns the binary operator behind a compound assignment operator such as `+=`.
+fn compound_op(op: &BinOp) -> Option<BinOp> {
+    let op = match op {
+        BinOp::AddAssign(t) => BinOp::Add(syn::Token![+](t.spans[0])),
+        BinOp::SubAssign(t) => BinOp::Sub(syn::Token![-](t.spans[0])),
+        BinOp::MulAssign(t) => BinOp::Mul(syn::Token![*](t.spans[0])),
+        BinOp::DivAssign(t) => BinOp::Div(syn::Token![/](t.spans[0])),
+        BinOp::RemAssign(t) => BinOp::Rem(syn::Token![%](t.spans[0])),
+        BinOp::BitAndAssign(t) => BinOp::BitAnd(syn::Token![&](t.spans[0])),
+        BinOp::BitOrAssign(t) => BinOp::BitOr(syn::Token![|](t.spans[0])),
+        BinOp::BitXorAssign(t) => BinOp::BitXor(syn::Token![^](t.spans[0])),
+        BinOp::ShlAssign(t) => BinOp::Shl(syn::Token![<<](t.spans[0])),
+        BinOp::ShrAssign(t) => BinOp::Shr(syn::Token![>>](t.spans[0])),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Collects the variables assigned (directly or through compound assignment) anywhere in `block`.
+fn collect_assigned_idents(block: &syn::Block, assigned: &mut Vec<syn::Ident>) {
+    fn visit(expr: &Expr, assigned: &mut Vec<syn::Ident>) {
+        let target = match expr {
+            Expr::Assign(ExprAssign { left, .. }) => Some(left),
+            Expr::Binary(ExprBinary { left, op, .. }) if compound_op(op).is_some() => Some(left),
+            Expr::Block(ExprBlock { block, .. }) => {
+                collect_assigned_idents(block, assigned);
+                None
+            }
+            Expr::If(ExprIf {
+                then_branch,
+                else_branch,
+                ..
+            }) => {
+                collect_assigned_idents(then_branch, assigned);
+                if let Some((_, else_expr)) = else_branch {
+                    visit(else_expr, assigned);
+                }
+                None
+            }
+            Expr::ForLoop(ExprForLoop { body, .. }) | Expr::While(ExprWhile { body, .. }) => {
+                collect_assigned_idents(body, assigned);
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(Expr::Path(path)) = target.map(|target| &**target) {
+            if let Some(ident) = path.path.get_ident() {
+                if !assigned.contains(ident) {
+                    assigned.push(ident.clone());
+                }
+            }
+        }
+    }
+
+    // variables declared in the block are local to it, and not visible around it
+    let mut declared: Vec<syn::Ident> = vec![];
+    for stmt in &block.stmts {
+        match stmt {
+            syn::Stmt::Local(local) => {
+                if let Pat::Ident(pat_ident) = &local.pat {
+                    declared.push(pat_ident.ident.clone());
+                }
+            }
+            syn::Stmt::Expr(expr, _) => {
+                let mut targets = vec![];
+                visit(expr, &mut targets);
+                for ident in targets {
+                    if !declared.contains(&ident) && !assigned.contains(&ident) {
+                        assigned.push(ident);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Selects between the branch values `if_true` and `if_false` of an `if` expression on
+/// `cond`. The narrower value is zero-extended first, so a branch can be a comparison while
+/// the other is an `N`-bit literal, as in `if c { a == b } else { false }`.
+fn select_branch_value() -> proc_macro2::TokenStream {
+    quote! {{
+        let if_true: GateIndexVec = if_true.into();
+        let if_false: GateIndexVec = if_false.into();
+        let width = if_true.len().max(if_false.len());
+        let if_true = context.resize(&if_true, width, false);
+        let if_false = context.resize(&if_false, width, false);
+        context.mux(&cond.into(), &if_true, &if_false)
+    }}
+}
 
-    syn::Block {
-        stmts,
-        brace_token: syn::token::Brace::default(),
+/// Wraps a `syn::Error` in an expression that expands to a spanned `compile_error!`.
+fn error_expr(error: syn::Error) -> Expr {
+    Expr::Verbatim(error.to_compile_error())
+}
+
+/// Lowers `array[i] = value` for a constant index `i`, storing the value's wire group in the
+/// array. The index keeps its span, so rustc reports an index past the end of the array at
+/// the assignment.
+fn assign_element(expr_index: syn::ExprIndex, value: Expr, lowering: &mut Lowering) -> Expr {
+    let Some(index) = eval_const_expr(&expr_index.index) else {
+        return error_expr(syn::Error::new_spanned(
+            &expr_index.index,
+            "Array index must be a constant expression",
+        ));
+    };
+    let array = &expr_index.expr;
+    let mut index_literal = Literal::u128_unsuffixed(index);
+    index_literal.set_span(syn::spanned::Spanned::span(&expr_index.index));
+    let value = replace_expressions(value, lowering);
+    syn::parse_quote! {
+        #array[#index_literal] = {
+            let element = #value;
+            element.into()
+        }
     }
 }
 
 /// Replaces binary operators and if/else expressions with appropriate context calls.
-fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>) -> Expr {
+fn replace_expressions(expr: Expr, lowering: &mut Lowering) -> Expr {
     match expr {
         // if there is a block, recursively call modify_body
         Expr::Block(ExprBlock { block, .. }) => {
-            let transformed_block = modify_body(block, constants);
+            let transformed_block = modify_body(block, lowering);
             syn::parse_quote! { #transformed_block }
         }
         // implement assignment
         Expr::Assign(ExprAssign { left, right, .. }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left = match *left {
+                Expr::Index(expr_index) => return assign_element(expr_index, *right, lowering),
+                left => left,
+            };
+            let left_expr = replace_expressions(left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
 
             match right_expr {
                 Expr::Reference(ExprReference { .. }) => {
@@ -220,13 +2097,34 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
                 }
             }
         }
+        // compound assignment in expression position evaluates to `a op b`
+        Expr::Binary(ExprBinary {
+            attrs,
+            left,
+            op,
+            right,
+        }) if compound_op(&op).is_some() => replace_expressions(
+            Expr::Binary(ExprBinary {
+                attrs,
+                left,
+                op: compound_op(&op).unwrap(),
+                right,
+            }),
+            lowering,
+        ),
         // return statement
-        Expr::Return(_) => {
-            panic!("Return statement not allowed in circuit macro");
-        }
+        Expr::Return(expr_return) => error_expr(syn::Error::new_spanned(
+            expr_return,
+            "Return statement not allowed in circuit macro",
+        )),
         // parentheses to ensure proper order of operations
         Expr::Paren(expr_paren) => {
-            let inner_expr = replace_expressions(*expr_paren.expr, constants);
+            let inner_expr = replace_expressions(*expr_paren.expr, lowering);
+            syn::parse_quote! { (#inner_expr) }
+        }
+        // invisible grouping around an `$e:expr` fragment substituted by `macro_rules!`
+        Expr::Group(expr_group) => {
+            let inner_expr = replace_expressions(*expr_group.expr, lowering);
             syn::parse_quote! { (#inner_expr) }
         }
         // boolean literal
@@ -237,28 +2135,27 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             let value = lit_bool.value;
             let const_var = format_ident!("const_{}", value as u128);
 
-            if value {
-                constants.push(quote! {
-                    let #const_var = &context.input::<N>(&1_u128.into()).clone();
-                });
-            } else {
-                constants.push(quote! {
-                    let #const_var = &context.input::<N>(&0_u128.into()).clone();
-                });
-            }
+            let value = value as u128;
+            lowering.constants.push(quote! {
+                let #const_var = &context.literal(#value, N);
+            });
             syn::parse_quote! {#const_var}
         }
-        // integer literal - handle as a constant in the circuit context
+        // integer literal - baked into the circuit as constant wires, not as an input
         Expr::Lit(syn::ExprLit {
             lit: Lit::Int(lit_int),
             ..
         }) => {
-            let value = lit_int
-                .base10_parse::<u128>()
-                .expect("Expected an integer literal");
+            let value = match lit_int.base10_parse::<u128>() {
+                Ok(value) => value,
+                Err(error) => return error_expr(error),
+            };
+            if let Err(error) = lowering.check_literal(value, false, &lit_int) {
+                return error_expr(error);
+            }
             let const_var = format_ident!("const_{}", value);
-            constants.push(quote! {
-                let #const_var = &context.input::<N>(&#value.into()).clone();
+            lowering.constants.push(quote! {
+                let #const_var = &context.literal(#value, N);
             });
             syn::parse_quote! {#const_var}
         }
@@ -269,8 +2166,14 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Eq(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            if let Some(expr) = zero_operand(&left, &right, "is_zero", lowering) {
+                return expr;
+            }
+            if let Some(expr) = constant_operand(&left, &right, "eq_constant", lowering) {
+                return expr;
+            }
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
@@ -284,8 +2187,11 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Ne(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            if let Some(expr) = zero_operand(&left, &right, "is_nonzero", lowering) {
+                return expr;
+            }
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
@@ -299,8 +2205,8 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Gt(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
@@ -314,8 +2220,8 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Ge(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
@@ -329,8 +2235,8 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Lt(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
@@ -344,8 +2250,8 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Le(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
@@ -359,25 +2265,17 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Add(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            if let Some(expr) = constant_operand(&left, &right, "add_constant", lowering) {
+                return expr;
+            }
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = &#left_expr;
                 let right = &#right_expr;
                 context.add(left.into(), right.into())
             }}
         }
-        // addition assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::AddAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.add(&#left, &#right)
-            }
-        }
         // subtraction
         Expr::Binary(ExprBinary {
             left,
@@ -385,25 +2283,14 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Sub(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
                 context.sub(&left.into(), &right.into())
             }}
         }
-        // subtraction assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::SubAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.sub(&#left, &#right)
-            }
-        }
         // multiplication
         Expr::Binary(ExprBinary {
             left,
@@ -411,25 +2298,17 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Mul(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            if let Some(expr) = constant_operand(&left, &right, "mul_constant", lowering) {
+                return expr;
+            }
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = &#left_expr;
                 let right = &#right_expr;
                 context.mul(left.into(), right.into())
             }}
         }
-        // multiplication assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::MulAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.mul(&#left, &#right)
-            }
-        }
         // division
         Expr::Binary(ExprBinary {
             left,
@@ -437,25 +2316,26 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Div(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            // a constant divisor is never lowered as a literal when it is a power of two
+            if let Some(value) = eval_const_expr(&right) {
+                if let Err(error) = lowering.check_literal(value, false, &right) {
+                    return error_expr(error);
+                }
+            }
+            let left_expr = replace_expressions(*left, lowering);
+            if let Some(shift) = power_of_two(&right) {
+                return syn::parse_quote! {{
+                    let left = #left_expr;
+                    context.div_pow2(&left.into(), #shift)
+                }};
+            }
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
                 context.div(&left.into(), &right.into())
             }}
         }
-        // division assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::DivAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.div(&#left, &#right)
-            }
-        }
         // modulo
         Expr::Binary(ExprBinary {
             left,
@@ -463,54 +2343,102 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::Rem(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            // a constant divisor is never lowered as a literal when it is a power of two
+            if let Some(value) = eval_const_expr(&right) {
+                if let Err(error) = lowering.check_literal(value, false, &right) {
+                    return error_expr(error);
+                }
+            }
+            let left_expr = replace_expressions(*left, lowering);
+            if let Some(shift) = power_of_two(&right) {
+                return syn::parse_quote! {{
+                    let left = #left_expr;
+                    context.rem_pow2(&left.into(), #shift)
+                }};
+            }
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
                 context.rem(&left.into(), &right.into())
             }}
         }
-        // modulo assignment
+        // left shift, rewired directly when the amount is constant
         Expr::Binary(ExprBinary {
             left,
             right,
-            op: BinOp::RemAssign(_),
+            op: BinOp::Shl(_),
             ..
         }) => {
-            syn::parse_quote! {
-                context.rem(&#left, &#right)
+            let left_expr = replace_expressions(*left, lowering);
+            if let Some(amount) = eval_const_expr(&right) {
+                let amount = amount as usize;
+                syn::parse_quote! {{
+                    let left = #left_expr;
+                    context.fixed_shl(&left.into(), #amount)
+                }}
+            } else {
+                let right_expr = replace_expressions(*right, lowering);
+                syn::parse_quote! {{
+                    let left = #left_expr;
+                    let right = #right_expr;
+                    context.shl_var(&left.into(), &right.into())
+                }}
             }
         }
-        // logical AND
+        // right shift, rewired directly when the amount is constant
         Expr::Binary(ExprBinary {
             left,
             right,
-            op: BinOp::And(_),
+            op: BinOp::Shr(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
-            syn::parse_quote! {{
-                let left = #left_expr;
-                let right = #right_expr;
-                context.land(&left, &right)
-            }}
+            let left_expr = replace_expressions(*left, lowering);
+            if let Some(amount) = eval_const_expr(&right) {
+                let amount = amount as usize;
+                syn::parse_quote! {{
+                    let left = #left_expr;
+                    context.fixed_shr(&left.into(), #amount)
+                }}
+            } else {
+                let right_expr = replace_expressions(*right, lowering);
+                syn::parse_quote! {{
+                    let left = #left_expr;
+                    let right = #right_expr;
+                    context.shr_var(&left.into(), &right.into())
+                }}
+            }
         }
-
-        // logical OR
+        // logical AND and OR - circuits evaluate both sides, so nothing short-circuits
         Expr::Binary(ExprBinary {
             left,
             right,
-            op: BinOp::Or(_),
+            op: op @ (BinOp::And(_) | BinOp::Or(_)),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            if let Some(operand) = [&*left, &*right]
+                .into_iter()
+                .find(|operand| lowering.is_integer(operand))
+            {
+                return error_expr(syn::Error::new_spanned(
+                    operand,
+                    format!("`{}` operands must be bool, found an integer", quote! {#op}),
+                ));
+            }
+            let method = match op {
+                BinOp::And(_) => format_ident!("and_bit"),
+                _ => format_ident!("or_bit"),
+            };
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
-                context.lor(&left, &right)
+                // a bool value may be widened, so any set wire makes it true; a single wire
+                // is reduced without gates
+                let left = context.is_nonzero(&left.into());
+                let right = context.is_nonzero(&right.into());
+                context.#method(left, right)
             }}
         }
 
@@ -521,25 +2449,14 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::BitAnd(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
                 context.and(&left.into(), &right.into())
             }}
         }
-        // bitwise AND assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitAndAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.and(&#left, &#right)
-            }
-        }
 
         // bitwise OR
         Expr::Binary(ExprBinary {
@@ -548,25 +2465,14 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::BitOr(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
                 context.or(&left.into(), &right.into())
             }}
         }
-        // bitwise OR assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitOrAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.or(&#left, &#right)
-            }
-        }
 
         // bitwise XOR
         Expr::Binary(ExprBinary {
@@ -575,25 +2481,14 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             op: BinOp::BitXor(_),
             ..
         }) => {
-            let left_expr = replace_expressions(*left, constants);
-            let right_expr = replace_expressions(*right, constants);
+            let left_expr = replace_expressions(*left, lowering);
+            let right_expr = replace_expressions(*right, lowering);
             syn::parse_quote! {{
                 let left = #left_expr;
                 let right = #right_expr;
                 context.xor(&left.into(), &right.into())
             }}
         }
-        // bitwise XOR assignment
-        Expr::Binary(ExprBinary {
-            left,
-            right,
-            op: BinOp::BitXorAssign(_),
-            ..
-        }) => {
-            syn::parse_quote! {
-                context.xor(&#left, &#right)
-            }
-        }
 
         // bitwise NOT
         Expr::Unary(ExprUnary {
@@ -601,13 +2496,50 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             expr,
             ..
         }) => {
-            let single_expr = replace_expressions(*expr, constants);
+            let single_expr = replace_expressions(*expr, lowering);
             syn::parse_quote! {{
                 let single = #single_expr;
                 context.not(&single.into())
             }}
         }
 
+        // unary minus - constants are negated at macro time, other operands in the circuit
+        Expr::Unary(ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => {
+            if let Some(value) = eval_const_expr(&expr) {
+                if let Err(error) = lowering.check_literal(value, true, &expr) {
+                    return error_expr(error);
+                }
+                let const_var = format_ident!("const_neg_{}", value);
+                let negated = value.wrapping_neg();
+                // above 128 bits the two's complement of a non-zero constant is all ones
+                let sign = (value != 0).then(|| {
+                    quote! {
+                        while wires.len() < N {
+                            let one = context.one_wire();
+                            wires.push(one);
+                        }
+                    }
+                });
+                lowering.constants.push(quote! {
+                    let #const_var = &{
+                        let mut wires = context.literal(#negated, N.min(128));
+                        #sign
+                        wires
+                    };
+                });
+                return syn::parse_quote! {#const_var};
+            }
+            let single_expr = replace_expressions(*expr, lowering);
+            syn::parse_quote! {{
+                let single = #single_expr;
+                context.neg(&single.into())
+            }}
+        }
+
         /*
         Expr::If(ExprIf {
             cond,
@@ -615,12 +2547,12 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
             else_branch,
             ..
         }) => {
-            let cond_expr = replace_expressions(*cond, constants);
-            let then_block = modify_body(then_branch, constants);
+            let cond_expr = replace_expressions(*cond, lowering);
+            let then_block = modify_body(then_branch, lowering);
 
             // If there's an explicit else block, use it; otherwise, continue with remaining expressions
             let else_expr = if let Some((_, else_expr)) = else_branch {
-                replace_expressions(*else_expr, constants)
+                replace_expressions(*else_expr, lowering)
             } else {
                 // Placeholder for remaining function body as the fall-through `else` case
                 //syn::parse_quote! { context.input::<N>(&0u128.into()) }
@@ -653,9 +2585,9 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
                             limits: syn::RangeLimits::Closed(_),
                             ..
                         }) => {
-                            let start_expr = replace_expressions(*start.clone(), constants);
-                            let end_expr = replace_expressions(*end.clone(), constants);
-                            let input_expr = replace_expressions(*expr, constants);
+                            let start_expr = replace_expressions(*start.clone(), lowering);
+                            let end_expr = replace_expressions(*end.clone(), lowering);
+                            let input_expr = replace_expressions(*expr, lowering);
 
                             // Inclusive range with embedded `let` statements for `lhs` and `rhs`
                             syn::parse_quote! {{
@@ -671,9 +2603,9 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
                             limits: syn::RangeLimits::HalfOpen(_),
                             ..
                         }) => {
-                            let start_expr = replace_expressions(*start.clone(), constants);
-                            let end_expr = replace_expressions(*end.clone(), constants);
-                            let input_expr = replace_expressions(*expr, constants);
+                            let start_expr = replace_expressions(*start.clone(), lowering);
+                            let end_expr = replace_expressions(*end.clone(), lowering);
+                            let input_expr = replace_expressions(*expr, lowering);
 
                             // Exclusive range with embedded `let` statements for `lhs` and `rhs`
                             syn::parse_quote! {{
@@ -684,56 +2616,149 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
                         }
                         // Handle single literal pattern, e.g., `if let 5 = n`
                         syn::Pat::Lit(lit) => {
-                            let lit_expr = replace_expressions(Expr::Lit(lit.clone()), constants);
-                            let input_expr = replace_expressions(*expr, constants);
+                            let lit_expr = replace_expressions(Expr::Lit(lit.clone()), lowering);
+                            let input_expr = replace_expressions(*expr, lowering);
 
                             syn::parse_quote! {
                                 context.eq(&#input_expr.into(), &#lit_expr.into())
                             }
                         }
-                        _ => panic!(
-                            "Unsupported pattern in if let: expected a range or literal pattern."
-                        ),
+                        other => error_expr(syn::Error::new_spanned(
+                            other,
+                            "Unsupported pattern in if let: expected a range or literal pattern.",
+                        )),
                     }
                 }
                 ref _other => {
-                    replace_expressions(*cond, constants) // Fallback for non-let conditions
+                    replace_expressions(*cond, lowering) // Fallback for non-let conditions
                 }
             };
 
-            let then_block = modify_body(then_branch, constants);
+            // Without an `else`, every variable assigned in the then-branch keeps the value it
+            // held before the `if` when the condition is false.
+            let Some((_, else_expr)) = else_branch else {
+                let mut assigned = vec![];
+                collect_assigned_idents(&then_branch, &mut assigned);
+                let then_block = modify_body(then_branch, lowering);
+                let previous: Vec<_> = assigned
+                    .iter()
+                    .map(|ident| format_ident!("{}_if_false", ident))
+                    .collect();
 
-            // Check if an `else` branch exists, as it's required.
-            let else_expr = if let Some((_, else_expr)) = else_branch {
-                replace_expressions(*else_expr, constants)
-            } else {
-                panic!("else branch is required for range if let");
+                return syn::parse_quote! {{
+                    let cond = #cond_expr;
+                    #(let #previous = #assigned.clone();)*
+                    #then_block;
+                    #(#assigned = context.mux(&cond.into(), &#assigned, &#previous);)*
+                }};
             };
 
-            // Generate code for conditional execution and chaining
+            let mut assigned = vec![];
+            collect_assigned_idents(&then_branch, &mut assigned);
+            collect_assigned_idents(&syn::parse_quote! {{ #else_expr }}, &mut assigned);
+
+            let mut then_block = modify_body(then_branch, lowering);
+            // an `else if` is a nested `Expr::If` here, so a chain lowers to nested muxes with
+            // each condition built once
+            let else_expr = replace_expressions(*else_expr, lowering);
+
+            if assigned.is_empty() {
+                // Generate code for conditional execution and chaining
+                let select = select_branch_value();
+                return syn::parse_quote! {{
+                    let cond = #cond_expr;
+                    let if_true = #then_block;
+                    let if_false = #else_expr;
+                    #select
+                }};
+            }
+
+            // Both branches are built, so the else-branch must see the variables as they were
+            // before the `if`; each assigned variable then selects its value from either branch.
+            let previous: Vec<_> = assigned
+                .iter()
+                .map(|ident| format_ident!("{}_if_false", ident))
+                .collect();
+            let then_values: Vec<_> = assigned
+                .iter()
+                .map(|ident| format_ident!("{}_if_true", ident))
+                .collect();
+
+            let Some(syn::Stmt::Expr(tail, None)) = then_block.stmts.last_mut() else {
+                // the branches only assign, so there is no value to select
+                return syn::parse_quote! {{
+                    let cond = #cond_expr;
+                    #(let #previous = #assigned.clone();)*
+                    #then_block;
+                    #(let #then_values = #assigned.clone();)*
+                    #(#assigned = #previous;)*
+                    #else_expr;
+                    #(#assigned = context.mux(&cond.into(), &#then_values, &#assigned);)*
+                }};
+            };
+            // the then-branch value is cloned, so it can name one of the assigned variables
+            *tail = syn::parse_quote! { (&#tail).clone() };
+            let select = select_branch_value();
+
             syn::parse_quote! {{
                 let cond = #cond_expr;
+                #(let #previous = #assigned.clone();)*
                 let if_true = #then_block;
+                #(let #then_values = #assigned.clone();)*
+                #(#assigned = #previous;)*
                 let if_false = #else_expr;
-                context.mux(&cond.into(), &if_true, &if_false)
+                #(#assigned = context.mux(&cond.into(), &#then_values, &#assigned);)*
+                #select
             }}
         }
 
         // Support match arms with mux and other operations
-        Expr::Match(ExprMatch { expr, arms, .. }) => {
-            let match_expr = replace_expressions(*expr, constants);
+        Expr::Match(ExprMatch {
+            match_token,
+            expr,
+            arms,
+            ..
+        }) => {
+            let match_expr = replace_expressions(*expr, lowering);
 
             // Define an input variable to use in range proof processing
             let input = syn::Ident::new("input", proc_macro2::Span::call_site());
             let input_binding = quote! { let #input = #match_expr; };
 
+            // Arms are lowered to a mux chain that falls back to the last arm, so it must be
+            // the only wildcard and no arm may carry a guard.
+            let last_arm = arms.len().saturating_sub(1);
+            for (index, arm) in arms.iter().enumerate() {
+                if let Some((if_token, _)) = &arm.guard {
+                    return error_expr(syn::Error::new(
+                        if_token.span,
+                        "Match guards are not supported in circuits",
+                    ));
+                }
+                match (&arm.pat, index == last_arm) {
+                    (syn::Pat::Wild(_), false) => {
+                        return error_expr(syn::Error::new_spanned(
+                            &arm.pat,
+                            "The wildcard pattern `_` must be the last match arm",
+                        ))
+                    }
+                    (syn::Pat::Wild(_), true) | (_, false) => {}
+                    (pat, true) => {
+                        return error_expr(syn::Error::new_spanned(
+                            pat,
+                            "Match expressions in circuits must end with a wildcard `_` arm",
+                        ))
+                    }
+                }
+            }
+
             // Process each arm, building up the conditional chain
             let arm_exprs = arms
                 .into_iter()
                 .rev()
                 .fold(None as Option<Expr>, |acc, arm| {
                     let pat = arm.pat;
-                    let body_expr = replace_expressions(*arm.body, constants);
+                    let body_expr = replace_expressions(*arm.body, lowering);
 
                     // Create conditional expression for each arm, handling ranges
                     let cond_expr = match &pat {
@@ -744,8 +2769,8 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
                             limits: syn::RangeLimits::Closed(_),
                             ..
                         }) => {
-                            let start = replace_expressions(*start.clone(), constants);
-                            let end = replace_expressions(*end.clone(), constants);
+                            let start = replace_expressions(*start.clone(), lowering);
+                            let end = replace_expressions(*end.clone(), lowering);
                             quote! {
                                 let lhs = &context.ge(&#input.into(), &#start.into()).into();
                                 let rhs = &context.le(&#input.into(), &#end.into()).into();
@@ -762,8 +2787,8 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
                             limits: syn::RangeLimits::HalfOpen(_),
                             ..
                         }) => {
-                            let start = replace_expressions(*start.clone(), constants);
-                            let end = replace_expressions(*end.clone(), constants);
+                            let start = replace_expressions(*start.clone(), lowering);
+                            let end = replace_expressions(*end.clone(), lowering);
                             quote! {
                                 let lhs = &context.ge(&#input.into(), &#start.into()).into();
                                 let rhs = &context.lt(&#input.into(), &#end.into()).into();
@@ -776,26 +2801,22 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
                         // Handle single value pattern (e.g., `5`)
                         syn::Pat::Lit(lit) => {
                             let lit_expr =
-                                replace_expressions(syn::Expr::Lit(lit.clone()), constants);
+                                replace_expressions(syn::Expr::Lit(lit.clone()), lowering);
                             quote! {
                                 context.eq(&#input.into(), &#lit_expr.into())
                             }
                         }
 
+                        // Handle named constants bound earlier in the body (e.g., `FIRST_ARM`)
                         syn::Pat::Ident(pat) => {
-                            // Create conditional expression for each arm
-                            let cond_expr = replace_expressions(
-                                syn::parse_quote! { #match_expr == #pat },
-                                constants,
-                            );
-
-                            syn::parse_quote! {{
-                                { #cond_expr }
-                            }}
+                            quote! {
+                                context.eq(&#input.into(), &#pat.into())
+                            }
                         }
-                        // Handle the wildcard pattern `_` as default/fallback case
-                        syn::Pat::Wild(_) => quote! { true },
-                        other => panic!("{:?}: Unsupported pattern in match arm", other),
+                        // The trailing wildcard `_` is the fallback and never needs a condition
+                        syn::Pat::Wild(_) => quote! {},
+                        other => syn::Error::new_spanned(other, "Unsupported pattern in match arm")
+                            .to_compile_error(),
                     };
 
                     // Chain the condition with the body, selecting based on condition
@@ -818,10 +2839,465 @@ fn replace_expressions(expr: Expr, constants: &mut Vec<proc_macro2::TokenStream>
                     #input_binding // Bind `input` at the beginning
                     #result        // Process the chained expressions
                 }},
-                None => panic!("Match expression requires at least one arm"),
+                None => error_expr(syn::Error::new(
+                    match_token.span,
+                    "Match expression requires at least one arm",
+                )),
             }
         }
 
-        other => other,
+        // call to an `#[encrypted(component)]` function, which adds its gates to this context;
+        // the generated call only type-checks against a component's signature
+        Expr::Call(call) if matches!(*call.func, Expr::Path(_)) => {
+            let func = &call.func;
+            if !is_component_path(func) {
+                return error_expr(syn::Error::new_spanned(
+                    func,
+                    "Only calls to `#[encrypted(component)]` functions can be lowered to a circuit",
+                ));
+            }
+            let args: Vec<Expr> = call
+                .args
+                .into_iter()
+                .map(|arg| replace_expressions(arg, lowering))
+                .collect();
+            let arg_names: Vec<syn::Ident> =
+                (0..args.len()).map(|i| format_ident!("arg{}", i)).collect();
+            syn::parse_quote! {{
+                #(let #arg_names = #args;)*
+                #func::<N>(&mut context, #(&#arg_names.into()),*)
+            }}
+        }
+
+        // range check against constant inclusive bounds, a single validity bit
+        Expr::MethodCall(method_call) if method_call.method == "in_range" => {
+            if method_call.args.len() != 2 {
+                return error_expr(syn::Error::new_spanned(
+                    &method_call,
+                    "`in_range` expects a lower and an upper bound",
+                ));
+            }
+            let mut bounds = vec![];
+            for bound in &method_call.args {
+                let Some(value) = eval_const_expr(bound) else {
+                    return error_expr(syn::Error::new_spanned(
+                        bound,
+                        "Range bounds must be constant expressions",
+                    ));
+                };
+                if let Err(error) = lowering.check_literal(value, false, bound) {
+                    return error_expr(error);
+                }
+                bounds.push(value);
+            }
+            let (lo, hi) = (bounds[0], bounds[1]);
+            let receiver = replace_expressions(*method_call.receiver, lowering);
+            syn::parse_quote! {{
+                let receiver = #receiver;
+                context.in_range(&receiver.into(), #lo, #hi)
+            }}
+        }
+
+        // a single bit of the receiver at a constant index, which only rewires its wires
+        Expr::MethodCall(method_call) if method_call.method == "bit" => {
+            let index = match method_call.args.first() {
+                Some(arg) if method_call.args.len() == 1 => eval_const_expr(arg),
+                _ => {
+                    return error_expr(syn::Error::new_spanned(
+                        &method_call,
+                        "`bit` expects a single bit index",
+                    ))
+                }
+            };
+            let Some(index) = index else {
+                return error_expr(syn::Error::new_spanned(
+                    &method_call.args,
+                    "Bit index must be a constant expression",
+                ));
+            };
+            if let Some(width) = lowering.literal_width {
+                if index >= width as u128 {
+                    return error_expr(syn::Error::new_spanned(
+                        &method_call.args,
+                        format!(
+                            "Bit index {} is out of range for the {}-bit width of the circuit",
+                            index, width
+                        ),
+                    ));
+                }
+            }
+            let index = index as usize;
+            let receiver = replace_expressions(*method_call.receiver, lowering);
+            syn::parse_quote! {{
+                let receiver = #receiver;
+                context.bit(&receiver.into(), #index)
+            }}
+        }
+
+        // methods on integer values from `OPERAND_METHODS`, lowered to their builder method
+        Expr::MethodCall(method_call) if operand_method(&method_call.method).is_some() => {
+            let (method, args) = operand_method(&method_call.method).unwrap();
+            let receiver = replace_expressions(*method_call.receiver.clone(), lowering);
+            match args {
+                // wrapping and saturating arithmetic and min/max with a single operand
+                MethodArgs::Operand => {
+                    if method_call.args.len() != 1 {
+                        return error_expr(syn::Error::new_spanned(
+                            &method_call,
+                            format!("`{}` expects a single operand", method_call.method),
+                        ));
+                    }
+                    let operand = replace_expressions(method_call.args[0].clone(), lowering);
+                    syn::parse_quote! {{
+                        let left = #receiver;
+                        let right = #operand;
+                        context.#method(&left.into(), &right.into())
+                    }}
+                }
+                // bit rotation by a constant amount, which only rewires the receiver's wires
+                MethodArgs::Amount => {
+                    let amount = match method_call.args.first() {
+                        Some(arg) if method_call.args.len() == 1 => eval_const_expr(arg),
+                        _ => {
+                            return error_expr(syn::Error::new_spanned(
+                                &method_call,
+                                format!(
+                                    "`{}` expects a single rotation amount",
+                                    method_call.method
+                                ),
+                            ))
+                        }
+                    };
+                    let Some(amount) = amount else {
+                        return error_expr(syn::Error::new_spanned(
+                            &method_call.args,
+                            "Rotation amount must be a constant expression",
+                        ));
+                    };
+                    let amount = amount as usize;
+                    syn::parse_quote! {{
+                        let receiver = #receiver;
+                        context.#method(&receiver.into(), #amount)
+                    }}
+                }
+                // bit counts, zero-extended back to the receiver's width
+                MethodArgs::Count => {
+                    if !method_call.args.is_empty() {
+                        return error_expr(syn::Error::new_spanned(
+                            &method_call.args,
+                            format!("`{}` takes no arguments", method_call.method),
+                        ));
+                    }
+                    syn::parse_quote! {{
+                        let receiver = #receiver;
+                        let receiver: GateIndexVec = receiver.into();
+                        let count = context.#method(&receiver);
+                        context.resize(&count, receiver.len(), false)
+                    }}
+                }
+            }
+        }
+
+        // arithmetic with an overflow bit, laid out like the tuple `(value, overflow)`
+        Expr::MethodCall(method_call)
+            if method_call.method == "checked_add" || method_call.method == "checked_sub" =>
+        {
+            let method = &method_call.method;
+            if method_call.args.len() != 1 {
+                return error_expr(syn::Error::new_spanned(
+                    &method_call,
+                    format!("`{}` expects a single operand", method),
+                ));
+            }
+            let left_expr = replace_expressions(*method_call.receiver.clone(), lowering);
+            let right_expr = replace_expressions(method_call.args[0].clone(), lowering);
+            syn::parse_quote! {{
+                let left = #left_expr;
+                let right = #right_expr;
+                let (value, overflow) = context.#method(&left.into(), &right.into());
+                let mut wires = context.resize(&value, N, false);
+                let overflow = context.resize(&GateIndexVec::new(vec![overflow]), N, false);
+                wires.push_all(&overflow);
+                wires
+            }}
+        }
+
+        // width conversions, e.g. `a.zext::<16>()`, which only rewire the receiver
+        Expr::MethodCall(method_call)
+            if ["zext", "sext", "trunc"]
+                .iter()
+                .any(|method| method_call.method == method) =>
+        {
+            let (Some(width), true) = (&method_call.turbofish, method_call.args.is_empty()) else {
+                return error_expr(syn::Error::new_spanned(
+                    &method_call,
+                    format!(
+                        "`{}` takes the target width and no arguments, e.g. `a.{}::<16>()`",
+                        method_call.method, method_call.method
+                    ),
+                ));
+            };
+            let method = match method_call.method.to_string().as_str() {
+                "zext" => format_ident!("zero_extend"),
+                "sext" => format_ident!("sign_extend"),
+                _ => format_ident!("truncate"),
+            };
+            let receiver = replace_expressions(*method_call.receiver.clone(), lowering);
+            syn::parse_quote! {{
+                let receiver = #receiver;
+                context.#method #width(&receiver.into())
+            }}
+        }
+
+        // integer casts resize the wires; widening extends by the signedness of the source,
+        // which is the circuit's unless the source is itself a cast, e.g. `(a as u8) as u16`
+        Expr::Cast(cast) => {
+            let Some((width, _)) = primitive_type(&cast.ty).filter(|(width, _)| *width > 1) else {
+                return error_expr(syn::Error::new_spanned(
+                    &cast.ty,
+                    "Casts in circuits must target a primitive integer type",
+                ));
+            };
+            let mut source = &*cast.expr;
+            while let Expr::Paren(expr_paren) = source {
+                source = &expr_paren.expr;
+            }
+            let source_signed = match source {
+                Expr::Cast(inner) => match primitive_type(&inner.ty) {
+                    Some((_, signed)) => quote! {#signed},
+                    None => quote! { context.is_signed() },
+                },
+                _ => quote! { context.is_signed() },
+            };
+            let value = replace_expressions(*cast.expr, lowering);
+            syn::parse_quote! {{
+                let value = #value;
+                let signed = #source_signed;
+                context.resize(&value.into(), #width, signed)
+            }}
+        }
+
+        // sum of an array with a balanced adder tree, wrapping at the width of the circuit
+        Expr::MethodCall(method_call) if method_call.method == "sum" => {
+            if !method_call.args.is_empty() {
+                return error_expr(syn::Error::new_spanned(
+                    &method_call.args,
+                    "`sum` takes no arguments",
+                ));
+            }
+            let receiver = replace_expressions(*method_call.receiver.clone(), lowering);
+            syn::parse_quote! {{
+                let receiver = #receiver;
+                context.sum(&receiver[..])
+            }}
+        }
+
+        // absolute value of a signed receiver
+        Expr::MethodCall(method_call) if method_call.method == "abs" => {
+            if !method_call.args.is_empty() {
+                return error_expr(syn::Error::new_spanned(
+                    &method_call.args,
+                    "`abs` takes no arguments",
+                ));
+            }
+            let receiver = replace_expressions(*method_call.receiver.clone(), lowering);
+            syn::parse_quote! {{
+                let receiver = #receiver;
+                context.abs(&receiver.into())
+            }}
+        }
+
+        // array indexing - the index must be a constant, which includes unrolled loop variables
+        Expr::Index(expr_index) => match eval_const_expr(&expr_index.index) {
+            Some(index) => {
+                let array = &expr_index.expr;
+                let index = Literal::u128_unsuffixed(index);
+                syn::parse_quote! { &#array[#index] }
+            }
+            None => error_expr(syn::Error::new_spanned(
+                &expr_index.index,
+                "Array index must be a constant expression",
+            )),
+        },
+
+        // array of one repeated value, e.g. `[0; 3]` for an output array filled by index
+        Expr::Repeat(repeat) => {
+            let value = replace_expressions(*repeat.expr, lowering);
+            let len = &repeat.len;
+            syn::parse_quote! {{
+                let element = #value;
+                let element: GateIndexVec = element.into();
+                [(); #len].map(|_| element.clone())
+            }}
+        }
+
+        // tuple - the element wire groups are concatenated, each padded to `N` bits
+        Expr::Tuple(tuple) => {
+            let elements: Vec<Expr> = tuple
+                .elems
+                .into_iter()
+                .map(|elem| replace_expressions(elem, lowering))
+                .collect();
+            syn::parse_quote! {{
+                let mut wires = GateIndexVec::default();
+                #(
+                    let element = #elements;
+                    let element = context.resize(&element.into(), N, false);
+                    wires.push_all(&element);
+                )*
+                wires
+            }}
+        }
+
+        // field of a record parameter - its bit range, extended to the circuit width
+        Expr::Field(field) if lowering.record_param(&field.base).is_some() => {
+            let record = lowering.record_param(&field.base).unwrap();
+            let base = &field.base;
+            let member = &field.member;
+            let name = match member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            };
+            syn::parse_quote! {{
+                // a field the record does not have is reported here by the compiler
+                let _ = |record: &#record| &record.#member;
+                context.field::<#record>(#base, #name, N)
+            }}
+        }
+
+        // methods on anything but `context` that are not lowered above
+        Expr::MethodCall(method_call) if !is_context(&method_call.receiver) => {
+            error_expr(syn::Error::new_spanned(
+                &method_call.method,
+                format!(
+                    "The method `{}` cannot be lowered to a circuit",
+                    method_call.method
+                ),
+            ))
+        }
+
+        // array expression - each element is lowered to its own wire group
+        Expr::Array(array) => {
+            let elements: Vec<Expr> = array
+                .elems
+                .into_iter()
+                .map(|elem| replace_expressions(elem, lowering))
+                .collect();
+            syn::parse_quote! {
+                [#({
+                    let element = #elements;
+                    let element: GateIndexVec = element.into();
+                    element
+                }),*]
+            }
+        }
+
+        // dereference of a by-reference parameter - its input wires stand for the value
+        Expr::Unary(ExprUnary {
+            op: syn::UnOp::Deref(_),
+            expr,
+            ..
+        }) if lowering.is_reference_param(&expr) => *expr,
+
+        // reference - wire groups are shared by reference already, so only its operand lowers
+        Expr::Reference(reference) => {
+            let inner_expr = replace_expressions(*reference.expr, lowering);
+            syn::parse_quote! { &#inner_expr }
+        }
+
+        // calls into `context` written by hand are emitted unchanged
+        Expr::MethodCall(method_call) => Expr::MethodCall(method_call),
+
+        // leaves, and errors reported while lowering, are emitted unchanged
+        other @ (Expr::Path(_) | Expr::Lit(_) | Expr::Field(_) | Expr::Verbatim(_)) => other,
+        other => error_expr(syn::Error::new_spanned(
+            &other,
+            format!(
+                "{} cannot be lowered to a circuit",
+                unsupported_construct(&other)
+            ),
+        )),
+    }
+}
+
+/// Returns whether `func` may name an `#[encrypted(component)]` function: a single snake case
+/// identifier, unlike associated functions such as `u8::from` and constructors such as `Some`.
+fn is_component_path(func: &Expr) -> bool {
+    let Expr::Path(path) = func else {
+        return false;
+    };
+    path.qself.is_none()
+        && path.path.get_ident().is_some_and(|ident| {
+            ident
+                .to_string()
+                .starts_with(|first: char| first.is_lowercase() || first == '_')
+        })
+}
+
+/// Returns whether `expr` is the circuit builder `context`, whose methods may be called by hand.
+fn is_context(expr: &Expr) -> bool {
+    matches!(expr, Expr::Path(path) if path.path.is_ident("context"))
+}
+
+/// How the arguments of a method in `OPERAND_METHODS` are lowered.
+#[derive(Clone, Copy)]
+enum MethodArgs {
+    /// A single operand, lowered like the receiver, e.g. `a.wrapping_add(b)`.
+    Operand,
+    /// A constant amount, e.g. `a.rotate_left(3)`.
+    Amount,
+    /// No arguments; the result is a count, zero-extended back to the receiver's width.
+    Count,
+}
+
+/// Methods on integer values, the builder methods they lower to and the arguments they take.
+/// A method on anything but `context` that is neither listed here nor lowered by a dedicated
+/// arm of `replace_expressions` is reported as one that cannot be lowered.
+const OPERAND_METHODS: &[(&str, &str, MethodArgs)] = &[
+    ("wrapping_add", "add", MethodArgs::Operand),
+    ("wrapping_sub", "sub", MethodArgs::Operand),
+    ("wrapping_mul", "mul", MethodArgs::Operand),
+    ("saturating_add", "saturating_add", MethodArgs::Operand),
+    ("saturating_sub", "saturating_sub", MethodArgs::Operand),
+    ("min", "min", MethodArgs::Operand),
+    ("max", "max", MethodArgs::Operand),
+    ("rotate_left", "rotate_left", MethodArgs::Amount),
+    ("rotate_right", "rotate_right", MethodArgs::Amount),
+    ("count_ones", "popcount", MethodArgs::Count),
+    ("leading_zeros", "leading_zeros", MethodArgs::Count),
+    ("trailing_zeros", "trailing_zeros", MethodArgs::Count),
+];
+
+/// Returns the builder method that the integer method `method` lowers to, and how its
+/// arguments are lowered.
+fn operand_method(method: &syn::Ident) -> Option<(syn::Ident, MethodArgs)> {
+    OPERAND_METHODS
+        .iter()
+        .find(|(name, _, _)| method == name)
+        .map(|(_, builder_method, args)| (format_ident!("{}", builder_method), *args))
+}
+
+/// Names the construct behind an expression that `replace_expressions` has no lowering for.
+fn unsupported_construct(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Closure(_) => "Closures",
+        Expr::Call(_) => "Calls through an expression",
+        Expr::Unary(_) => "Dereferences of anything but a by-reference parameter",
+        Expr::Macro(_) => "Macro invocations",
+        Expr::Loop(_) => "`loop` expressions",
+        Expr::ForLoop(_) | Expr::While(_) => "Loops in expression position",
+        Expr::Break(_) | Expr::Continue(_) => "`break` and `continue`",
+        Expr::Range(_) => "Ranges outside of `for` loops",
+        Expr::Let(_) => "`let` expressions outside of `if let`",
+        Expr::Struct(_) => "Struct literals",
+        Expr::Try(_) => "The `?` operator",
+        Expr::TryBlock(_) => "`try` blocks",
+        Expr::Async(_) => "`async` blocks",
+        Expr::Await(_) => "`.await`",
+        Expr::Unsafe(_) => "`unsafe` blocks",
+        Expr::Const(_) => "`const` blocks",
+        Expr::Yield(_) => "`yield`",
+        Expr::Infer(_) => "The `_` placeholder",
+        _ => "This expression",
     }
 }
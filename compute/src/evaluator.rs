@@ -5,6 +5,11 @@ use std::fmt::Debug;
 use tandem::states::Evaluator as TandemEvaluator;
 use tandem::Circuit;
 
+/// The evaluator's side of the WRK17 two-party protocol, see [`Garbler`]: it answers every
+/// garbler message and decodes the output from the last one with
+/// [`output`](Evaluator::output).
+///
+/// [`Garbler`]: crate::garbler::Garbler
 pub trait Evaluator {
     fn new(circuit: &Circuit, input: &[bool]) -> Result<Self>
     where
@@ -1,10 +1,12 @@
 use anyhow::Result;
 use once_cell::sync::Lazy;
+use rayon::prelude::*;
 use std::sync::Arc;
-use tandem::Circuit;
+use tandem::{Circuit, Gate, GateIndex};
 
 use crate::evaluator::{Evaluator, GatewayEvaluator};
 use crate::garbler::{Garbler, GatewayGarbler};
+use crate::uint::GarbledUint;
 
 use std::thread::sleep;
 use std::time::Duration;
@@ -21,6 +23,11 @@ pub fn get_executor() -> Arc<dyn Executor + Send + Sync> {
     SINGLETON_EXECUTOR.clone()
 }
 
+/// Runs compiled circuits under the two-party protocol.
+///
+/// The whole circuit, and every garbled table the protocol produces for it, is held in memory
+/// during execution. The `garble-model` feature's `YaoEvaluator::execute_streaming` instead
+/// reads garbled tables from any `Read` source, keeping only the labels of live wires.
 pub trait Executor {
     /// Executes the 2 Party MPC protocol.
     ///
@@ -105,3 +112,653 @@ fn simulate_transfer_time_ms(payload: &[u8], latency: &f64) {
     let transfer_time = bytes_size / latency * 1000.0; // Convert seconds to milliseconds
     sleep(Duration::from_millis(transfer_time as u64))
 }
+
+/// Error returned when a circuit cannot be executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecError {
+    /// The number of input bits does not match the inputs of the circuit.
+    InputLengthMismatch { expected: usize, got: usize },
+    /// A gate or output refers to a wire beyond the end of the circuit.
+    WireOutOfBounds { wire: GateIndex, gates: usize },
+    /// A gate reads a wire that is not assigned before it, i.e. itself or a later gate.
+    UnassignedWire { gate: usize, wire: GateIndex },
+    /// The two-party protocol failed, with the executor's message.
+    Protocol(String),
+    /// A garbling label width that is not a whole number of bytes within the supported range.
+    #[cfg(feature = "garble-model")]
+    UnsupportedSecurity { bits: usize },
+    /// A garbled circuit evaluated with labels of a different width than it was garbled with.
+    #[cfg(feature = "garble-model")]
+    SecurityMismatch { garbled: usize, evaluator: usize },
+    /// Reading garbled tables from a stream or writing outputs to one failed, with the I/O
+    /// error's message.
+    #[cfg(feature = "garble-model")]
+    Stream(String),
+    /// The output bits do not have the width of the circuit's outputs or of the decoded type.
+    OutputWidthMismatch { expected: usize, got: usize },
+}
+
+impl std::fmt::Display for ExecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecError::InputLengthMismatch { expected, got } => {
+                write!(f, "expected {} input bits, got {}", expected, got)
+            }
+            ExecError::WireOutOfBounds { wire, gates } => write!(
+                f,
+                "wire {} is out of range for a circuit of {} gates",
+                wire, gates
+            ),
+            ExecError::UnassignedWire { gate, wire } => write!(
+                f,
+                "gate {} reads wire {}, which is not assigned before it",
+                gate, wire
+            ),
+            ExecError::Protocol(message) => write!(f, "circuit execution failed: {}", message),
+            #[cfg(feature = "garble-model")]
+            ExecError::UnsupportedSecurity { bits } => write!(
+                f,
+                "unsupported label width of {} bits, expected a multiple of 8 from {} to {}",
+                bits,
+                crate::garble::MIN_SECURITY_BITS,
+                crate::garble::DEFAULT_SECURITY_BITS
+            ),
+            #[cfg(feature = "garble-model")]
+            ExecError::SecurityMismatch { garbled, evaluator } => write!(
+                f,
+                "circuit garbled with {}-bit labels, evaluator expects {}-bit labels",
+                garbled, evaluator
+            ),
+            #[cfg(feature = "garble-model")]
+            ExecError::Stream(message) => write!(f, "garbled stream failed: {}", message),
+            ExecError::OutputWidthMismatch { expected, got } => {
+                write!(f, "expected {} output bits, got {}", expected, got)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+impl From<circuit_core::Error> for ExecError {
+    fn from(error: circuit_core::Error) -> Self {
+        match error {
+            circuit_core::Error::InputLengthMismatch { expected, got } => {
+                ExecError::InputLengthMismatch { expected, got }
+            }
+            circuit_core::Error::WireOutOfBounds { wire, gates } => {
+                ExecError::WireOutOfBounds { wire, gates }
+            }
+            circuit_core::Error::UnassignedWire { gate, wire } => {
+                ExecError::UnassignedWire { gate, wire }
+            }
+            // only decoding reports these, and circuits are converted rather than decoded
+            circuit_core::Error::MissingHeader
+            | circuit_core::Error::UnsupportedVersion(_)
+            | circuit_core::Error::MalformedBody
+            | circuit_core::Error::CountMismatch => {
+                unreachable!("circuit_core reports {error:?} only when decoding")
+            }
+        }
+    }
+}
+
+/// The largest total input width [`CircuitExecution::truth_table`] enumerates.
+pub const TRUTH_TABLE_MAX_INPUTS: usize = 16;
+
+/// Every row of a truth table: the input bits and the output bits they produce.
+pub type TruthTable = Vec<(Vec<bool>, Vec<bool>)>;
+
+/// Execution of a compiled [`Circuit`] that returns the raw output wires, for outputs whose
+/// width does not match a primitive type.
+pub trait CircuitExecution {
+    /// Executes the circuit on the singleton executor, returning the value of every output
+    /// wire in order. `inputs` holds the contributor inputs followed by the evaluator inputs,
+    /// and is split between the parties by [`Circuit::contrib_inputs`]. The inputs and wiring
+    /// are checked first, so a malformed call is reported as such rather than as a protocol
+    /// failure. A circuit whose outputs are constant, see
+    /// [`constant_output`](Self::constant_output), returns them without running the protocol.
+    fn execute_bits(&self, inputs: &[bool]) -> std::result::Result<Vec<bool>, ExecError>;
+
+    /// Evaluates the circuit in the clear on every combination of its input bits, as
+    /// [`trace`](Self::trace) does, returning one row per combination in counting order: bit
+    /// `k` of the inputs in row `i` is bit `k` of `i`. The wiring is checked once up front.
+    /// Returns `Ok(None)` when the circuit has more than [`TRUTH_TABLE_MAX_INPUTS`] inputs.
+    fn truth_table(&self) -> std::result::Result<Option<TruthTable>, ExecError>;
+
+    /// Evaluates the circuit in the clear and returns the value of every wire, indexed by the
+    /// gate that drives it, for inspecting intermediate values. `inputs` holds the
+    /// contributor inputs followed by the evaluator inputs, each in allocation order. The
+    /// inputs and wiring are checked as in [`execute_bits`](Self::execute_bits).
+    fn trace(&self, inputs: &[bool]) -> std::result::Result<Vec<bool>, ExecError>;
+
+    /// Evaluates the circuit in the clear on every row of `inputs`, each laid out as for
+    /// [`trace`](Self::trace), and returns the output wires of every row in order, as
+    /// [`execute_bits`](Self::execute_bits) computes them without the protocol. The
+    /// inputs and wiring are checked once up front, and the rows are spread across the rayon
+    /// thread pool, each thread reusing one wire buffer.
+    fn execute_batch(&self, inputs: &[Vec<bool>])
+        -> std::result::Result<Vec<Vec<bool>>, ExecError>;
+
+    /// Returns the number of output wires, the length of the result of
+    /// [`execute_bits`](Self::execute_bits).
+    fn output_width(&self) -> usize;
+
+    /// Decodes the result of [`execute_bits`](Self::execute_bits) to a typed value, e.g.
+    /// `circuit.decode_output::<16, u16>(&bits)`. Both the circuit's outputs and `bits` must be
+    /// `N` bits wide.
+    fn decode_output<const N: usize, T: From<GarbledUint<N>>>(
+        &self,
+        bits: &[bool],
+    ) -> std::result::Result<T, ExecError>;
+
+    /// Returns the value of every output wire when none of them depends on the inputs, e.g.
+    /// for `a & 0`, and `None` otherwise. Constants are propagated in one pass over the gates:
+    /// `XOR(x, x)`, an AND with a constant false operand, and any gate over constant operands
+    /// are constant. The result is computed once, by the wiring check that every execution
+    /// runs, and is `None` for a circuit with invalid wiring.
+    fn constant_output(&self) -> Option<Vec<bool>>;
+}
+
+impl CircuitExecution for Circuit {
+    fn execute_bits(&self, inputs: &[bool]) -> std::result::Result<Vec<bool>, ExecError> {
+        check_input_length(self, inputs)?;
+        // the outputs are known without running the protocol
+        if let Some(outputs) = core_circuit(self)?.constant_output() {
+            return Ok(outputs.to_vec());
+        }
+        let (contrib, eval) = inputs.split_at(self.contrib_inputs());
+        get_executor()
+            .execute(self, contrib, eval)
+            .map_err(|error| ExecError::Protocol(error.to_string()))
+    }
+
+    fn truth_table(&self) -> std::result::Result<Option<TruthTable>, ExecError> {
+        let width = self.contrib_inputs() + self.eval_inputs();
+        if width > TRUTH_TABLE_MAX_INPUTS {
+            return Ok(None);
+        }
+        let circuit = core_circuit(self)?;
+        (0..1_usize << width)
+            .map(|row| {
+                let inputs: Vec<bool> = (0..width).map(|k| (row >> k) & 1 == 1).collect();
+                let outputs = circuit.execute_bits(&inputs)?;
+                Ok((inputs, outputs))
+            })
+            .collect::<std::result::Result<_, _>>()
+            .map(Some)
+    }
+
+    fn trace(&self, inputs: &[bool]) -> std::result::Result<Vec<bool>, ExecError> {
+        Ok(core_circuit(self)?.trace(inputs)?)
+    }
+
+    fn execute_batch(
+        &self,
+        inputs: &[Vec<bool>],
+    ) -> std::result::Result<Vec<Vec<bool>>, ExecError> {
+        for row in inputs {
+            check_input_length(self, row)?;
+        }
+        let circuit = core_circuit(self)?;
+
+        Ok(inputs
+            .par_iter()
+            .map_init(
+                || Vec::with_capacity(self.gates().len()),
+                |wires, row| {
+                    circuit
+                        .trace_into(row, wires)
+                        .expect("the input lengths were checked above");
+                    self.output_gates()
+                        .iter()
+                        .map(|output| wires[*output as usize])
+                        .collect()
+                },
+            )
+            .collect())
+    }
+
+    fn output_width(&self) -> usize {
+        self.output_gates().len()
+    }
+
+    fn decode_output<const N: usize, T: From<GarbledUint<N>>>(
+        &self,
+        bits: &[bool],
+    ) -> std::result::Result<T, ExecError> {
+        for got in [N, bits.len()] {
+            if got != self.output_width() {
+                return Err(ExecError::OutputWidthMismatch {
+                    expected: self.output_width(),
+                    got,
+                });
+            }
+        }
+        Ok(GarbledUint::<N>::from_bits(bits).into())
+    }
+
+    fn constant_output(&self) -> Option<Vec<bool>> {
+        core_circuit(self)
+            .ok()?
+            .constant_output()
+            .map(<[bool]>::to_vec)
+    }
+}
+
+fn check_input_length(circuit: &Circuit, inputs: &[bool]) -> std::result::Result<(), ExecError> {
+    let expected = circuit.contrib_inputs() + circuit.eval_inputs();
+    if inputs.len() != expected {
+        return Err(ExecError::InputLengthMismatch {
+            expected,
+            got: inputs.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that every gate reads only wires assigned before it and that every output is a
+/// wire of the circuit.
+pub(crate) fn check_wiring(circuit: &Circuit) -> std::result::Result<(), ExecError> {
+    core_circuit(circuit).map(drop)
+}
+
+/// Converts a circuit to the `circuit_core` representation, which checks its wiring as in
+/// [`check_wiring`].
+pub(crate) fn core_circuit(
+    circuit: &Circuit,
+) -> std::result::Result<circuit_core::Circuit, ExecError> {
+    let gates = circuit
+        .gates()
+        .iter()
+        .map(|gate| match *gate {
+            Gate::InContrib => circuit_core::Gate::InContrib,
+            Gate::InEval => circuit_core::Gate::InEval,
+            Gate::Xor(a, b) => circuit_core::Gate::Xor(a, b),
+            Gate::And(a, b) => circuit_core::Gate::And(a, b),
+            Gate::Not(a) => circuit_core::Gate::Not(a),
+        })
+        .collect();
+    Ok(circuit_core::Circuit::new(
+        gates,
+        circuit.output_gates().clone(),
+    )?)
+}
+
+/// Converts a circuit checked by `circuit_core` back to a `tandem` circuit.
+pub(crate) fn from_core(circuit: &circuit_core::Circuit) -> Circuit {
+    let gates = circuit
+        .gates()
+        .iter()
+        .map(|gate| match *gate {
+            circuit_core::Gate::InContrib => Gate::InContrib,
+            circuit_core::Gate::InEval => Gate::InEval,
+            circuit_core::Gate::Xor(a, b) => Gate::Xor(a, b),
+            circuit_core::Gate::And(a, b) => Gate::And(a, b),
+            circuit_core::Gate::Not(a) => Gate::Not(a),
+        })
+        .collect();
+    Circuit::new(gates, circuit.output_gates().to_vec())
+}
+
+/// Returns the wires a gate reads.
+pub(crate) fn operands(gate: &Gate) -> [Option<GateIndex>; 2] {
+    match *gate {
+        Gate::InContrib | Gate::InEval => [None, None],
+        Gate::Not(a) => [Some(a), None],
+        Gate::Xor(a, b) | Gate::And(a, b) => [Some(a), Some(b)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::operations::circuits::types::GateIndexVec;
+    use crate::uint::{GarbledBit, GarbledUint16, GarbledUint8};
+
+    #[test]
+    fn test_execute_bits_matches_typed_execute() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0b1100_1010_u8));
+        let b = builder.input(&GarbledUint8::from(0b0110_0111_u8));
+        let sum = builder.add(&a, &b);
+        let circuit = builder.compile(&sum);
+
+        let bits = circuit.execute_bits(builder.inputs()).unwrap();
+        let typed = builder.execute::<8>(&circuit).unwrap();
+        assert_eq!(bits, typed.bits);
+        assert_eq!(u8::from(typed), 0b1100_1010_u8.wrapping_add(0b0110_0111));
+    }
+
+    #[test]
+    fn test_execute_bits_odd_width() {
+        // three output wires, which no primitive type decodes
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0b101_u8));
+        let low = GateIndexVec::new((0..3).map(|i| a[i]).collect());
+        let circuit = builder.compile(&low);
+
+        assert_eq!(
+            circuit.execute_bits(builder.inputs()).unwrap(),
+            vec![true, false, true]
+        );
+    }
+
+    #[test]
+    fn test_execute_bits_input_length_mismatch() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(1_u8));
+        let b = builder.input(&GarbledUint8::from(2_u8));
+        let sum = builder.add(&a, &b);
+        let circuit = builder.compile(&sum);
+
+        let error = circuit.execute_bits(&[true; 8]).unwrap_err();
+        assert!(matches!(
+            error,
+            ExecError::InputLengthMismatch {
+                expected: 16,
+                got: 8
+            }
+        ));
+        assert_eq!(error.to_string(), "expected 16 input bits, got 8");
+    }
+
+    #[test]
+    fn test_execute_bits_evaluator_inputs() {
+        // `c0 & e0` and `c1 ^ e1`, with the evaluator inputs after the contributor inputs
+        let circuit = Circuit::new(
+            vec![
+                Gate::InContrib,
+                Gate::InContrib,
+                Gate::InEval,
+                Gate::InEval,
+                Gate::And(0, 2),
+                Gate::Xor(1, 3),
+            ],
+            vec![4, 5],
+        );
+        assert_eq!(
+            circuit.execute_bits(&[true, false, true, true]).unwrap(),
+            vec![true, true]
+        );
+        assert_eq!(
+            circuit.execute_bits(&[true, true, false, true]).unwrap(),
+            vec![false, false]
+        );
+        assert_eq!(
+            circuit.trace(&[true, true, false, true]).unwrap()[4..],
+            [false, false]
+        );
+    }
+
+    #[test]
+    fn test_execute_bits_malformed_wiring() {
+        let forward = Circuit::new(
+            vec![Gate::InContrib, Gate::Not(2), Gate::InContrib],
+            vec![1],
+        );
+        assert_eq!(
+            forward.execute_bits(&[true, false]).unwrap_err(),
+            ExecError::UnassignedWire { gate: 1, wire: 2 }
+        );
+
+        let dangling = Circuit::new(vec![Gate::InContrib, Gate::Not(0)], vec![7]);
+        assert_eq!(
+            dangling.execute_bits(&[true]).unwrap_err(),
+            ExecError::WireOutOfBounds { wire: 7, gates: 2 }
+        );
+    }
+
+    #[test]
+    fn test_constant_output() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0b1011_0110_u8));
+        let zero = builder.literal(0, 8);
+        let masked = builder.and(&a, &zero);
+        let circuit = builder.compile(&masked);
+
+        assert_eq!(circuit.constant_output(), Some(vec![false; 8]));
+        assert_eq!(
+            circuit.execute_bits(builder.inputs()).unwrap(),
+            vec![false; 8]
+        );
+        assert_eq!(u8::from(builder.execute::<8>(&circuit).unwrap()), 0);
+
+        // constants fold through further gates, but one input-dependent output is enough
+        let ones = builder.literal(0xff, 8);
+        let flipped = builder.xor(&masked, &ones);
+        let circuit = builder.compile(&flipped);
+        assert_eq!(circuit.constant_output(), Some(vec![true; 8]));
+
+        let sum = builder.add(&a, &flipped);
+        let circuit = builder.compile(&sum);
+        assert_eq!(circuit.constant_output(), None);
+        assert_eq!(
+            u8::from(builder.execute::<8>(&circuit).unwrap()),
+            0b1011_0110_u8.wrapping_add(0xff)
+        );
+    }
+
+    #[test]
+    fn test_truth_table_half_adder() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledBit::from(false));
+        let b = builder.input(&GarbledBit::from(false));
+        let sum = builder.add(&a, &b);
+        let carry = builder.and(&a, &b);
+        let mut output = sum;
+        output.push_all(&carry);
+        let circuit = builder.compile(&output);
+
+        let table = circuit.truth_table().unwrap().unwrap();
+        assert_eq!(
+            table,
+            vec![
+                (vec![false, false], vec![false, false]),
+                (vec![true, false], vec![true, false]),
+                (vec![false, true], vec![true, false]),
+                (vec![true, true], vec![false, true]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_full_adder() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledBit::from(false));
+        let b = builder.input(&GarbledBit::from(false));
+        let carry_in = builder.input(&GarbledBit::from(false));
+        let propagate = builder.xor(&a, &b);
+        let generate = builder.and(&a, &b);
+        let sum = builder.xor(&propagate, &carry_in);
+        let carried = builder.and(&propagate, &carry_in);
+        let carry = builder.xor(&generate, &carried);
+        let mut output = sum;
+        output.push_all(&carry);
+        let circuit = builder.compile(&output);
+
+        for row in 0..8 {
+            let inputs: Vec<bool> = (0..3).map(|k| (row >> k) & 1 == 1).collect();
+            let (x, y, z) = (inputs[0], inputs[1], inputs[2]);
+            let wires = circuit.trace(&inputs).unwrap();
+            assert_eq!(wires.len(), circuit.gates().len());
+            assert_eq!(wires[propagate[0] as usize], x ^ y);
+            assert_eq!(wires[generate[0] as usize], x & y);
+            assert_eq!(wires[carried[0] as usize], (x ^ y) & z);
+            assert_eq!(wires[carry[0] as usize], (x as u8 + y as u8 + z as u8) >= 2);
+        }
+    }
+
+    #[test]
+    fn test_trace_matches_execute_bits() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0b0000_1111_u8));
+        let b = builder.input(&GarbledUint8::from(0b0000_0001_u8));
+        let sum = builder.add(&a, &b);
+        let circuit = builder.compile(&sum);
+
+        let wires = circuit.trace(builder.inputs()).unwrap();
+        let outputs: Vec<bool> = sum.iter().map(|wire| wires[*wire as usize]).collect();
+        assert_eq!(outputs, circuit.execute_bits(builder.inputs()).unwrap());
+        // the carry ripples through the low four bits and stops at bit four
+        assert_eq!(
+            outputs,
+            vec![false, false, false, false, true, false, false, false]
+        );
+        assert_eq!(
+            circuit.trace(&[true; 8]).unwrap_err(),
+            ExecError::InputLengthMismatch {
+                expected: 16,
+                got: 8
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_output() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint16::from(40_000_u16));
+        let b = builder.input(&GarbledUint16::from(1_234_u16));
+        let sum = builder.add(&a, &b);
+        let circuit = builder.compile(&sum);
+        assert_eq!(circuit.output_width(), 16);
+
+        let bits = circuit.execute_bits(builder.inputs()).unwrap();
+        let sum: u16 = circuit.decode_output::<16, _>(&bits).unwrap();
+        assert_eq!(sum, 41_234);
+
+        assert_eq!(
+            circuit.decode_output::<8, u8>(&bits[..8]).unwrap_err(),
+            ExecError::OutputWidthMismatch {
+                expected: 16,
+                got: 8
+            }
+        );
+        assert_eq!(
+            circuit.decode_output::<16, u16>(&bits[..15]).unwrap_err(),
+            ExecError::OutputWidthMismatch {
+                expected: 16,
+                got: 15
+            }
+        );
+    }
+
+    #[test]
+    fn test_execute_batch_matches_single_calls() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let b = builder.input(&GarbledUint8::from(0_u8));
+        let product = builder.mul(&a, &b);
+        let output = builder.xor(&product, &a);
+        let circuit = builder.compile(&output);
+
+        let rows: Vec<Vec<bool>> = (0..1000_u32)
+            .map(|row| {
+                let (a, b) = ((row * 37) as u8, (row * 101 + 13) as u8);
+                (0..8)
+                    .map(|i| (a >> i) & 1 == 1)
+                    .chain((0..8).map(|i| (b >> i) & 1 == 1))
+                    .collect()
+            })
+            .collect();
+        let batch = circuit.execute_batch(&rows).unwrap();
+        assert_eq!(batch.len(), rows.len());
+        for (row, outputs) in rows.iter().zip(&batch) {
+            let wires = circuit.trace(row).unwrap();
+            let expected: Vec<bool> = circuit
+                .output_gates()
+                .iter()
+                .map(|output| wires[*output as usize])
+                .collect();
+            assert_eq!(*outputs, expected);
+        }
+        // a few rows through the full protocol as well
+        for (row, outputs) in rows.iter().zip(&batch).step_by(250) {
+            assert_eq!(*outputs, circuit.execute_bits(row).unwrap());
+        }
+
+        assert_eq!(circuit.execute_batch(&[]).unwrap(), Vec::<Vec<bool>>::new());
+        assert_eq!(
+            circuit
+                .execute_batch(&[rows[0].clone(), vec![true; 15]])
+                .unwrap_err(),
+            ExecError::InputLengthMismatch {
+                expected: 16,
+                got: 15
+            }
+        );
+    }
+
+    #[test]
+    fn test_truth_table_too_wide() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let b = builder.input(&GarbledUint8::from(0_u8));
+        let c = builder.input(&GarbledBit::from(false));
+        let c = builder.resize(&c, 8, false);
+        let sum = builder.add(&a, &b);
+        let output = builder.xor(&sum, &c);
+        assert_eq!(builder.compile(&output).truth_table(), Ok(None));
+    }
+
+    #[test]
+    fn test_parties_over_channel() {
+        use crate::evaluator::{Evaluator, GatewayEvaluator};
+        use crate::garbler::{Garbler, GatewayGarbler};
+        use std::sync::mpsc::channel;
+        use std::thread;
+
+        // bit 0 is `c0 & e0`, bit 1 is `c1 ^ e1`, with inputs from both parties
+        let circuit = Circuit::new(
+            vec![
+                Gate::InContrib,
+                Gate::InContrib,
+                Gate::InEval,
+                Gate::InEval,
+                Gate::And(0, 2),
+                Gate::Xor(1, 3),
+            ],
+            vec![4, 5],
+        );
+
+        for (contributor, evaluator) in [
+            ([true, false], [true, true]),
+            ([false, true], [true, false]),
+        ] {
+            let (to_evaluator, from_garbler) = channel::<Vec<u8>>();
+            let (to_garbler, from_evaluator) = channel::<Vec<u8>>();
+
+            let garbler_circuit = circuit.clone();
+            let garbler = thread::spawn(move || {
+                let (mut garbler, message) =
+                    GatewayGarbler::start(&garbler_circuit, &contributor).unwrap();
+                to_evaluator.send(message).unwrap();
+                while !garbler.is_complete() {
+                    let (next, reply) = garbler.next(&from_evaluator.recv().unwrap()).unwrap();
+                    to_evaluator.send(reply).unwrap();
+                    garbler = next;
+                }
+            });
+
+            let mut party = GatewayEvaluator::new(&circuit, &evaluator).unwrap();
+            while !party.is_complete() {
+                let (next, reply) = party.next(&from_garbler.recv().unwrap()).unwrap();
+                to_garbler.send(reply).unwrap();
+                party = next;
+            }
+            let output = party.output(&from_garbler.recv().unwrap()).unwrap();
+            garbler.join().unwrap();
+
+            let expected = get_executor()
+                .execute(&circuit, &contributor, &evaluator)
+                .unwrap();
+            assert_eq!(output, expected);
+            assert_eq!(
+                output,
+                vec![contributor[0] & evaluator[0], contributor[1] ^ evaluator[1]]
+            );
+        }
+    }
+}
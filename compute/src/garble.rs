@@ -0,0 +1,1095 @@
+//! Yao garbling of compiled circuits with free XOR and point-and-permute, for inspecting the
+//! cost of a circuit under classic garbling and for running both roles in-process.
+//!
+//! Every wire carries one of two labels, `zero` for false and `zero ^ delta` for true, where
+//! `delta` is a secret offset shared by all wires. XOR gates XOR their input labels and NOT
+//! gates swap the meaning of a wire's labels, so neither needs a ciphertext.
+//! The low bit of `delta` is set, so the low bits of a wire's two labels differ: the evaluator
+//! uses the low bit of the label it holds, its select bit, to pick a ciphertext row without
+//! learning the value. AND gates are garbled as two half gates, one where the garbler knows an
+//! input and one where the evaluator does, which XOR to the AND of the inputs; each half gate
+//! sends one row, so an AND gate costs [`ROWS_PER_AND`] rows.
+//!
+//! Labels are [`DEFAULT_SECURITY_BITS`] wide unless a [`YaoGarbler`] is configured with
+//! [`YaoGarbler::with_security`]: shorter labels shrink every row, and the hash output is
+//! truncated to match, at the cost of security. A [`YaoEvaluator`] must be configured with the
+//! same width.
+//!
+//! [`InputEncoding::encode`] hands out the labels for known inputs directly. In a two-party
+//! run, [`YaoGarbler::run`] and [`YaoEvaluator::run`] play the two roles over a byte channel,
+//! and the evaluator obtains the labels of its own inputs through an [`ObliviousTransfer`]
+//! supplied by the caller, so the garbler never learns them. This module is a teaching and
+//! cost model behind the `garble-model` feature and only as private as that transfer:
+//! execution between two parties uses the WRK17 protocol from `tandem` behind
+//! [`Garbler`](crate::garbler::Garbler).
+
+use crate::executor::{check_wiring, operands, ExecError};
+use crate::operations::circuits::stats::CircuitStats;
+use rand::{CryptoRng, RngCore};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use tandem::{Circuit, Gate, GateIndex};
+
+/// The label of a wire, which encodes its value without revealing it. Labels narrower than
+/// 128 bits occupy the low bits.
+pub type Label = u128;
+
+/// Ciphertext rows sent for every AND gate.
+pub const ROWS_PER_AND: usize = 2;
+
+/// Size in bytes of a single ciphertext row with labels of the default width.
+pub const ROW_BYTES: usize = std::mem::size_of::<Label>();
+
+/// The default label width in bits, which is also the widest supported.
+pub const DEFAULT_SECURITY_BITS: usize = 128;
+
+/// The narrowest supported label width in bits.
+pub const MIN_SECURITY_BITS: usize = 80;
+
+/// Garbles circuits with labels of a configurable width.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct YaoGarbler {
+    security: Security,
+}
+
+/// Evaluates garbled circuits, checking that they were garbled with the labels it expects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct YaoEvaluator {
+    security: Security,
+}
+
+/// A validated label width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Security {
+    bits: usize,
+}
+
+/// A garbled circuit: the gate topology, the ciphertext rows of its AND gates and the select
+/// bits that decode its outputs.
+#[derive(Debug, Clone)]
+pub struct GarbledCircuit {
+    circuit: Circuit,
+    security: Security,
+    tables: Vec<Label>,
+    decoding: Vec<bool>,
+}
+
+/// A circuit garbled by [`YaoGarbler::garble_streaming`], whose ciphertext rows were written
+/// to a sink instead of being kept: the gate topology, the select bits that decode its
+/// outputs, and the last gate reading every wire.
+#[derive(Debug, Clone)]
+pub struct GarbledStream {
+    circuit: Circuit,
+    security: Security,
+    decoding: Vec<bool>,
+    last_use: Vec<Option<usize>>,
+}
+
+/// The false labels of the input wires and the offset to the true labels, kept by the
+/// garbler to hand out input labels.
+#[derive(Debug, Clone)]
+pub struct InputEncoding {
+    zero_labels: Vec<Label>,
+    delta: Label,
+}
+
+/// Delivers the labels of the evaluator's input wires in a two-party run: the garbler offers
+/// both labels of every wire and the evaluator receives the one selected by each of its input
+/// bits. A secure implementation reveals neither the other label to the evaluator nor the
+/// choices to the garbler; each party holds its own endpoint.
+pub trait ObliviousTransfer {
+    /// Offers the false and the true label of every evaluator input wire, in order.
+    fn send(&mut self, labels: &[[Label; 2]]) -> Result<(), ExecError>;
+
+    /// Returns the label selected by every bit of `choices`, the evaluator inputs in order.
+    fn receive(&mut self, choices: &[bool]) -> Result<Vec<Label>, ExecError>;
+}
+
+/// Garbles `circuit` with labels of the default width drawn from `rng`, returning the garbled
+/// circuit for the evaluator and the input encoding for the garbler. The wiring is checked
+/// first, as [`CircuitExecution::execute_bits`](crate::executor::CircuitExecution::execute_bits)
+/// does.
+pub fn garble(
+    circuit: &Circuit,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Result<(GarbledCircuit, InputEncoding), ExecError> {
+    YaoGarbler::default().garble(circuit, rng)
+}
+
+impl Security {
+    fn new(bits: usize) -> Result<Self, ExecError> {
+        if bits % 8 != 0 || !(MIN_SECURITY_BITS..=DEFAULT_SECURITY_BITS).contains(&bits) {
+            return Err(ExecError::UnsupportedSecurity { bits });
+        }
+        Ok(Security { bits })
+    }
+
+    fn label_bytes(self) -> usize {
+        self.bits / 8
+    }
+
+    fn random_label(self, rng: &mut impl RngCore) -> Label {
+        let mut bytes = [0; ROW_BYTES];
+        rng.fill_bytes(&mut bytes[..self.label_bytes()]);
+        Label::from_le_bytes(bytes)
+    }
+
+    /// Hashes an input label of a half gate under `tweak`, truncated to the label width. The
+    /// garbler and the evaluator must use the same function.
+    fn hash(self, label: Label, tweak: u64) -> Label {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&label.to_le_bytes()[..self.label_bytes()]);
+        hasher.update(&tweak.to_le_bytes());
+        let mut bytes = [0; ROW_BYTES];
+        bytes[..self.label_bytes()]
+            .copy_from_slice(&hasher.finalize().as_bytes()[..self.label_bytes()]);
+        Label::from_le_bytes(bytes)
+    }
+
+    /// Evaluates the half gates of the AND gate at `gate` on its input labels and its two
+    /// ciphertext rows, returning the output label.
+    fn evaluate_and(self, gate: usize, a: Label, b: Label, rows: [Label; ROWS_PER_AND]) -> Label {
+        let (tweak_a, tweak_b) = tweaks(gate);
+        let garbler_out = self.hash(a, tweak_a) ^ rows[0] * (a & 1);
+        let evaluator_out = self.hash(b, tweak_b) ^ (rows[1] ^ a) * (b & 1);
+        garbler_out ^ evaluator_out
+    }
+
+    /// Reads one ciphertext row of the label width.
+    fn read_label(self, source: &mut impl Read) -> Result<Label, ExecError> {
+        let mut bytes = [0; ROW_BYTES];
+        source
+            .read_exact(&mut bytes[..self.label_bytes()])
+            .map_err(stream_error)?;
+        Ok(Label::from_le_bytes(bytes))
+    }
+}
+
+impl Default for Security {
+    fn default() -> Self {
+        Security {
+            bits: DEFAULT_SECURITY_BITS,
+        }
+    }
+}
+
+impl YaoGarbler {
+    /// Returns a garbler whose labels, and so its ciphertext rows, are `bits` wide. `bits`
+    /// must be a multiple of 8 from [`MIN_SECURITY_BITS`] to [`DEFAULT_SECURITY_BITS`].
+    pub fn with_security(bits: usize) -> Result<Self, ExecError> {
+        Ok(YaoGarbler {
+            security: Security::new(bits)?,
+        })
+    }
+
+    /// Returns the label width in bits.
+    pub fn security(&self) -> usize {
+        self.security.bits
+    }
+
+    /// Garbles `circuit` as [`garble`] does, with this garbler's label width.
+    pub fn garble(
+        &self,
+        circuit: &Circuit,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(GarbledCircuit, InputEncoding), ExecError> {
+        let mut tables = vec![];
+        let (decoding, encoding) = self.garble_rows(circuit, rng, |row| {
+            tables.push(row);
+            Ok(())
+        })?;
+        Ok((
+            GarbledCircuit {
+                circuit: circuit.clone(),
+                security: self.security,
+                tables,
+                decoding,
+            },
+            encoding,
+        ))
+    }
+
+    /// Garbles `circuit` like [`Self::garble`], but writes every ciphertext row to `tables`
+    /// as soon as its gate is garbled, in the layout of [`GarbledCircuit::tables_to_bytes`],
+    /// so the tables are never held in memory. The returned [`GarbledStream`] evaluates them
+    /// with [`YaoEvaluator::execute_streaming`].
+    pub fn garble_streaming(
+        &self,
+        circuit: &Circuit,
+        rng: &mut (impl RngCore + CryptoRng),
+        mut tables: impl Write,
+    ) -> Result<(GarbledStream, InputEncoding), ExecError> {
+        let row_bytes = self.security.label_bytes();
+        let (decoding, encoding) = self.garble_rows(circuit, rng, |row| {
+            tables
+                .write_all(&row.to_le_bytes()[..row_bytes])
+                .map_err(stream_error)
+        })?;
+        tables.flush().map_err(stream_error)?;
+        Ok((
+            GarbledStream {
+                circuit: circuit.clone(),
+                security: self.security,
+                decoding,
+                last_use: last_uses(circuit),
+            },
+            encoding,
+        ))
+    }
+
+    /// Runs the garbler's side of a two-party evaluation of `circuit` on the `contributor`
+    /// inputs. The labels of the evaluator inputs are offered through `ot`; then the label
+    /// width as one byte, the labels of the contributor inputs, the output decoding bits
+    /// packed little-endian and the ciphertext rows are written to `channel`, which
+    /// [`YaoEvaluator::run`] reads on the other end.
+    pub fn run(
+        &self,
+        circuit: &Circuit,
+        contributor: &[bool],
+        rng: &mut (impl RngCore + CryptoRng),
+        ot: &mut impl ObliviousTransfer,
+        mut channel: impl Write,
+    ) -> Result<(), ExecError> {
+        if contributor.len() != circuit.contrib_inputs() {
+            return Err(ExecError::InputLengthMismatch {
+                expected: circuit.contrib_inputs(),
+                got: contributor.len(),
+            });
+        }
+        let (garbled, encoding) = self.garble(circuit, rng)?;
+        let (contrib_zero, eval_zero) = encoding.zero_labels.split_at(contributor.len());
+        let offers: Vec<[Label; 2]> = eval_zero
+            .iter()
+            .map(|zero| [*zero, zero ^ encoding.delta])
+            .collect();
+        ot.send(&offers)?;
+
+        let mut message = vec![self.security.bits as u8];
+        for (bit, zero) in contributor.iter().zip(contrib_zero) {
+            let label = if *bit { zero ^ encoding.delta } else { *zero };
+            message.extend_from_slice(&label.to_le_bytes()[..self.security.label_bytes()]);
+        }
+        let mut decoding = vec![0; garbled.decoding.len().div_ceil(8)];
+        for (i, bit) in garbled.decoding.iter().enumerate() {
+            decoding[i / 8] |= u8::from(*bit) << (i % 8);
+        }
+        message.extend(decoding);
+        channel.write_all(&message).map_err(stream_error)?;
+        channel
+            .write_all(&garbled.tables_to_bytes())
+            .map_err(stream_error)?;
+        channel.flush().map_err(stream_error)
+    }
+
+    /// Garbles the gates of `circuit` in order, passing every ciphertext row to `emit`, and
+    /// returns the output decoding bits and the input encoding.
+    fn garble_rows(
+        &self,
+        circuit: &Circuit,
+        rng: &mut (impl RngCore + CryptoRng),
+        mut emit: impl FnMut(Label) -> Result<(), ExecError>,
+    ) -> Result<(Vec<bool>, InputEncoding), ExecError> {
+        check_wiring(circuit)?;
+
+        let security = self.security;
+        let delta = security.random_label(rng) | 1;
+        let mut zero: Vec<Label> = Vec::with_capacity(circuit.gates().len());
+        let mut contrib_labels = vec![];
+        let mut eval_labels = vec![];
+
+        for (index, gate) in circuit.gates().iter().enumerate() {
+            let label = match *gate {
+                Gate::InContrib => {
+                    let label = security.random_label(rng);
+                    contrib_labels.push(label);
+                    label
+                }
+                Gate::InEval => {
+                    let label = security.random_label(rng);
+                    eval_labels.push(label);
+                    label
+                }
+                Gate::Xor(a, b) => zero[a as usize] ^ zero[b as usize],
+                Gate::Not(a) => zero[a as usize] ^ delta,
+                Gate::And(a, b) => {
+                    let (a, b) = (zero[a as usize], zero[b as usize]);
+                    let (select_a, select_b) = (a & 1, b & 1);
+                    let (tweak_a, tweak_b) = tweaks(index);
+                    let (hash_a, hash_b) = (security.hash(a, tweak_a), security.hash(b, tweak_b));
+
+                    // the garbler's half gate, computing `a & select_b`
+                    let garbler_row = hash_a ^ security.hash(a ^ delta, tweak_a) ^ delta * select_b;
+                    let garbler_out = hash_a ^ garbler_row * select_a;
+                    // the evaluator's half gate, computing `a & (b ^ select_b)`
+                    let evaluator_row = hash_b ^ security.hash(b ^ delta, tweak_b) ^ a;
+                    let evaluator_out = hash_b ^ (evaluator_row ^ a) * select_b;
+
+                    emit(garbler_row)?;
+                    emit(evaluator_row)?;
+                    garbler_out ^ evaluator_out
+                }
+            };
+            zero.push(label);
+        }
+
+        let decoding = circuit
+            .output_gates()
+            .iter()
+            .map(|output| zero[*output as usize] & 1 == 1)
+            .collect();
+        contrib_labels.append(&mut eval_labels);
+        Ok((
+            decoding,
+            InputEncoding {
+                zero_labels: contrib_labels,
+                delta,
+            },
+        ))
+    }
+}
+
+impl GarbledCircuit {
+    /// Evaluates the circuit on the labels of its input wires, contributor inputs first, and
+    /// decodes the output labels to the value of every output wire in order. The label width
+    /// is checked by [`YaoEvaluator::evaluate`], the public entry point.
+    fn evaluate(&self, inputs: &[Label]) -> Result<Vec<bool>, ExecError> {
+        let expected = self.circuit.contrib_inputs() + self.circuit.eval_inputs();
+        if inputs.len() != expected {
+            return Err(ExecError::InputLengthMismatch {
+                expected,
+                got: inputs.len(),
+            });
+        }
+
+        let (contrib, eval) = inputs.split_at(self.circuit.contrib_inputs());
+        let (mut contrib, mut eval) = (contrib.iter(), eval.iter());
+        let mut tables = self.tables.chunks_exact(ROWS_PER_AND);
+        let mut labels: Vec<Label> = Vec::with_capacity(self.circuit.gates().len());
+
+        for (index, gate) in self.circuit.gates().iter().enumerate() {
+            let label = match *gate {
+                // the input counts were checked above
+                Gate::InContrib => *contrib.next().unwrap(),
+                Gate::InEval => *eval.next().unwrap(),
+                Gate::Xor(a, b) => labels[a as usize] ^ labels[b as usize],
+                Gate::Not(a) => labels[a as usize],
+                Gate::And(a, b) => {
+                    // one table per AND gate was written while garbling
+                    let table = tables.next().unwrap();
+                    let (a, b) = (labels[a as usize], labels[b as usize]);
+                    self.security
+                        .evaluate_and(index, a, b, [table[0], table[1]])
+                }
+            };
+            labels.push(label);
+        }
+
+        Ok(self
+            .circuit
+            .output_gates()
+            .iter()
+            .zip(&self.decoding)
+            .map(|(output, decoding)| (labels[*output as usize] & 1 == 1) ^ decoding)
+            .collect())
+    }
+
+    /// Returns the number of ciphertext rows, [`ROWS_PER_AND`] for every AND gate.
+    pub fn rows(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Returns the label width in bits the circuit was garbled with.
+    pub fn security(&self) -> usize {
+        self.security.bits
+    }
+
+    /// Returns the size in bytes of a single ciphertext row, one label.
+    pub fn row_bytes(&self) -> usize {
+        self.security.label_bytes()
+    }
+
+    /// Returns the size in bytes of the ciphertexts the garbler sends.
+    pub fn ciphertext_bytes(&self) -> usize {
+        self.tables.len() * self.row_bytes()
+    }
+
+    /// Serializes the ciphertext rows in gate order, as the garbler sends them.
+    pub fn tables_to_bytes(&self) -> Vec<u8> {
+        self.tables
+            .iter()
+            .flat_map(|row| row.to_le_bytes()[..self.row_bytes()].to_vec())
+            .collect()
+    }
+}
+
+impl YaoEvaluator {
+    /// Returns an evaluator for circuits garbled with `bits`-wide labels, with the same
+    /// constraints as [`YaoGarbler::with_security`].
+    pub fn with_security(bits: usize) -> Result<Self, ExecError> {
+        Ok(YaoEvaluator {
+            security: Security::new(bits)?,
+        })
+    }
+
+    /// Returns the label width in bits.
+    pub fn security(&self) -> usize {
+        self.security.bits
+    }
+
+    /// Evaluates `garbled` on the labels of its input wires, contributor inputs first, after
+    /// checking that it was garbled with this evaluator's label width, and decodes the output
+    /// labels to the value of every output wire in order.
+    pub fn evaluate(
+        &self,
+        garbled: &GarbledCircuit,
+        inputs: &[Label],
+    ) -> Result<Vec<bool>, ExecError> {
+        if garbled.security != self.security {
+            return Err(ExecError::SecurityMismatch {
+                garbled: garbled.security.bits,
+                evaluator: self.security.bits,
+            });
+        }
+        garbled.evaluate(inputs)
+    }
+
+    /// Evaluates `garbled` on the labels of its input wires like [`Self::evaluate`], reading
+    /// the ciphertext rows of every AND gate from `tables` as the gate is reached, and writes
+    /// the output bits to `outputs`, packed little-endian into `outputs.div_ceil(8)` bytes.
+    ///
+    /// Only the labels of live wires are kept: a label is dropped once the last gate reading
+    /// it has been evaluated, and the label of a gate nothing reads is never stored. Returns
+    /// the peak number of labels that were resident at once.
+    pub fn execute_streaming(
+        &self,
+        garbled: &GarbledStream,
+        inputs: &[Label],
+        mut tables: impl Read,
+        mut outputs: impl Write,
+    ) -> Result<usize, ExecError> {
+        if garbled.security != self.security {
+            return Err(ExecError::SecurityMismatch {
+                garbled: garbled.security.bits,
+                evaluator: self.security.bits,
+            });
+        }
+        let circuit = &garbled.circuit;
+        let expected = circuit.contrib_inputs() + circuit.eval_inputs();
+        if inputs.len() != expected {
+            return Err(ExecError::InputLengthMismatch {
+                expected,
+                got: inputs.len(),
+            });
+        }
+
+        let (contrib, eval) = inputs.split_at(circuit.contrib_inputs());
+        let (mut contrib, mut eval) = (contrib.iter(), eval.iter());
+        let mut live: HashMap<GateIndex, Label> = HashMap::new();
+        let mut peak = 0;
+        for (index, gate) in circuit.gates().iter().enumerate() {
+            let operands = operands(gate);
+            // the wiring was checked while garbling, so every operand is live
+            let [a, b] = operands.map(|wire| wire.map(|wire| live[&wire]));
+            let label = match *gate {
+                // the input counts were checked above
+                Gate::InContrib => *contrib.next().unwrap(),
+                Gate::InEval => *eval.next().unwrap(),
+                Gate::Xor(_, _) => a.unwrap() ^ b.unwrap(),
+                Gate::Not(_) => a.unwrap(),
+                Gate::And(_, _) => {
+                    let rows = [
+                        self.security.read_label(&mut tables)?,
+                        self.security.read_label(&mut tables)?,
+                    ];
+                    self.security
+                        .evaluate_and(index, a.unwrap(), b.unwrap(), rows)
+                }
+            };
+
+            for wire in operands.into_iter().flatten() {
+                if garbled.last_use[wire as usize] == Some(index) {
+                    live.remove(&wire);
+                }
+            }
+            if garbled.last_use[index].is_some() {
+                live.insert(index as GateIndex, label);
+            }
+            peak = peak.max(live.len());
+        }
+
+        let mut bytes = vec![0; circuit.output_gates().len().div_ceil(8)];
+        for (i, (output, decoding)) in circuit
+            .output_gates()
+            .iter()
+            .zip(&garbled.decoding)
+            .enumerate()
+        {
+            if (live[output] & 1 == 1) ^ decoding {
+                bytes[i / 8] |= 1 << (i % 8);
+            }
+        }
+        outputs.write_all(&bytes).map_err(stream_error)?;
+        outputs.flush().map_err(stream_error)?;
+        Ok(peak)
+    }
+
+    /// Runs the evaluator's side of a two-party evaluation of `circuit` on the `evaluator`
+    /// inputs: receives their labels through `ot`, reads what [`YaoGarbler::run`] writes from
+    /// `channel` and returns the value of every output wire in order. The garbler's label
+    /// width must match this evaluator's.
+    pub fn run(
+        &self,
+        circuit: &Circuit,
+        evaluator: &[bool],
+        ot: &mut impl ObliviousTransfer,
+        mut channel: impl Read,
+    ) -> Result<Vec<bool>, ExecError> {
+        check_wiring(circuit)?;
+        if evaluator.len() != circuit.eval_inputs() {
+            return Err(ExecError::InputLengthMismatch {
+                expected: circuit.eval_inputs(),
+                got: evaluator.len(),
+            });
+        }
+        let eval_labels = ot.receive(evaluator)?;
+        if eval_labels.len() != evaluator.len() {
+            return Err(ExecError::Protocol(format!(
+                "received {} labels for {} evaluator inputs",
+                eval_labels.len(),
+                evaluator.len()
+            )));
+        }
+
+        let mut width = [0];
+        channel.read_exact(&mut width).map_err(stream_error)?;
+        let bits = width[0] as usize;
+        if bits != self.security.bits {
+            return Err(ExecError::SecurityMismatch {
+                garbled: bits,
+                evaluator: self.security.bits,
+            });
+        }
+        let mut labels = (0..circuit.contrib_inputs())
+            .map(|_| self.security.read_label(&mut channel))
+            .collect::<Result<Vec<_>, _>>()?;
+        labels.extend(eval_labels);
+        let outputs = circuit.output_gates().len();
+        let mut decoding = vec![0; outputs.div_ceil(8)];
+        channel.read_exact(&mut decoding).map_err(stream_error)?;
+        let tables = (0..circuit.and_count() * ROWS_PER_AND)
+            .map(|_| self.security.read_label(&mut channel))
+            .collect::<Result<_, _>>()?;
+
+        let garbled = GarbledCircuit {
+            circuit: circuit.clone(),
+            security: self.security,
+            tables,
+            decoding: (0..outputs)
+                .map(|i| (decoding[i / 8] >> (i % 8)) & 1 == 1)
+                .collect(),
+        };
+        garbled.evaluate(&labels)
+    }
+}
+
+impl InputEncoding {
+    /// Returns the offset between the false and the true label of every wire. Its low bit is
+    /// always set.
+    pub fn delta(&self) -> Label {
+        self.delta
+    }
+
+    /// Returns the label of every input wire for the given values, contributor inputs first,
+    /// in the order the circuit's input gates are allocated.
+    pub fn encode(
+        &self,
+        contributor: &[bool],
+        evaluator: &[bool],
+    ) -> Result<Vec<Label>, ExecError> {
+        let got = contributor.len() + evaluator.len();
+        if got != self.zero_labels.len() {
+            return Err(ExecError::InputLengthMismatch {
+                expected: self.zero_labels.len(),
+                got,
+            });
+        }
+        Ok(contributor
+            .iter()
+            .chain(evaluator)
+            .zip(&self.zero_labels)
+            .map(|(bit, zero)| if *bit { zero ^ self.delta } else { *zero })
+            .collect())
+    }
+}
+
+/// Returns the last gate reading every wire, `usize::MAX` for outputs, which are read after
+/// every gate, and `None` for wires nothing reads.
+fn last_uses(circuit: &Circuit) -> Vec<Option<usize>> {
+    let mut last_use = vec![None; circuit.gates().len()];
+    for (index, gate) in circuit.gates().iter().enumerate() {
+        for wire in operands(gate).into_iter().flatten() {
+            last_use[wire as usize] = Some(index);
+        }
+    }
+    for output in circuit.output_gates() {
+        last_use[*output as usize] = Some(usize::MAX);
+    }
+    last_use
+}
+
+fn stream_error(error: io::Error) -> ExecError {
+    ExecError::Stream(error.to_string())
+}
+
+/// Returns the tweaks of the two half gates of the AND gate at `gate`, unique to the gate so
+/// that the same label hashes differently in every gate it feeds.
+fn tweaks(gate: usize) -> (u64, u64) {
+    (2 * gate as u64, 2 * gate as u64 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::get_executor;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::uint::{GarbledUint32, GarbledUint8};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use std::io::Cursor;
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use std::thread;
+
+    fn bits(value: u8) -> Vec<bool> {
+        (0..8).map(|i| (value >> i) & 1 == 1).collect()
+    }
+
+    fn value(bits: &[bool]) -> u8 {
+        bits.iter()
+            .enumerate()
+            .fold(0, |value, (i, bit)| value | ((*bit as u8) << i))
+    }
+
+    #[test]
+    fn test_garbled_adder_matches_plaintext() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let b = builder.input(&GarbledUint8::from(0_u8));
+        let sum = builder.add(&a, &b);
+        let circuit = builder.compile(&sum);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (garbled, encoding) = garble(&circuit, &mut rng).unwrap();
+        for (a, b) in [(0_u8, 0_u8), (1, 1), (200, 100), (0x5a, 0xa5), (255, 255)] {
+            let inputs = [bits(a), bits(b)].concat();
+            let labels = encoding.encode(&inputs, &[]).unwrap();
+            let output = garbled.evaluate(&labels).unwrap();
+            assert_eq!(value(&output), a.wrapping_add(b));
+        }
+    }
+
+    #[test]
+    fn test_xor_gates_are_free() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let b = builder.input(&GarbledUint8::from(0_u8));
+        let c = builder.input(&GarbledUint8::from(0_u8));
+        let ab = builder.xor(&a, &b);
+        let abc = builder.xor(&ab, &c);
+        let output = builder.not(&abc);
+        let circuit = builder.compile(&output);
+        assert_eq!(circuit.and_count(), 0);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (garbled, encoding) = garble(&circuit, &mut rng).unwrap();
+        assert_eq!(garbled.ciphertext_bytes(), 0);
+
+        let inputs = [bits(0x0f), bits(0x33), bits(0x55)].concat();
+        let output = garbled
+            .evaluate(&encoding.encode(&inputs, &[]).unwrap())
+            .unwrap();
+        assert_eq!(value(&output), !(0x0f ^ 0x33 ^ 0x55));
+    }
+
+    #[test]
+    fn test_and_gates_send_two_rows() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let b = builder.input(&GarbledUint8::from(0_u8));
+        let product = builder.and(&a, &b);
+        let circuit = builder.compile(&product);
+        assert_eq!(circuit.and_count(), 8);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (garbled, encoding) = garble(&circuit, &mut rng).unwrap();
+        assert_eq!(ROWS_PER_AND, 2);
+        assert_eq!(garbled.rows(), 8 * 2);
+        assert_eq!(garbled.tables_to_bytes().len(), 8 * 2 * ROW_BYTES);
+        assert_eq!(garbled.ciphertext_bytes(), garbled.tables_to_bytes().len());
+
+        for (a, b) in [(0b1100_1010_u8, 0b1010_0110_u8), (0, 255), (255, 255)] {
+            let inputs = [bits(a), bits(b)].concat();
+            let output = garbled
+                .evaluate(&encoding.encode(&inputs, &[]).unwrap())
+                .unwrap();
+            assert_eq!(value(&output), a & b);
+        }
+    }
+
+    #[test]
+    fn test_half_gates_match_every_input_combination() {
+        let circuit = Circuit::new(
+            vec![
+                Gate::InContrib,
+                Gate::InEval,
+                Gate::And(0, 1),
+                Gate::Not(0),
+                Gate::And(3, 2),
+                Gate::Xor(4, 1),
+            ],
+            vec![2, 4, 5],
+        );
+        for seed in 0..8 {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            let (garbled, encoding) = garble(&circuit, &mut rng).unwrap();
+            for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+                let labels = encoding.encode(&[a], &[b]).unwrap();
+                assert_eq!(
+                    garbled.evaluate(&labels).unwrap(),
+                    vec![a & b, !a & (a & b), (!a & (a & b)) ^ b]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_labels_differ_by_delta() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let circuit = builder.compile(&a);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (_, encoding) = garble(&circuit, &mut rng).unwrap();
+        let delta = encoding.delta();
+        assert_eq!(delta & 1, 1);
+
+        let zeros = encoding.encode(&[false; 8], &[]).unwrap();
+        let ones = encoding.encode(&[true; 8], &[]).unwrap();
+        for (zero, one) in zeros.iter().zip(&ones) {
+            assert_eq!(zero ^ one, delta);
+            assert_ne!(zero & 1, one & 1);
+        }
+    }
+
+    #[test]
+    fn test_label_width() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let b = builder.input(&GarbledUint8::from(0_u8));
+        let product = builder.mul(&a, &b);
+        let circuit = builder.compile(&product);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut garbled_at = |width| {
+            let garbler = YaoGarbler::with_security(width).unwrap();
+            let evaluator = YaoEvaluator::with_security(width).unwrap();
+            let (garbled, encoding) = garbler.garble(&circuit, &mut rng).unwrap();
+            for (a, b) in [(0_u8, 0_u8), (3, 5), (17, 15), (255, 255)] {
+                let labels = encoding.encode(&[bits(a), bits(b)].concat(), &[]).unwrap();
+                let output = evaluator.evaluate(&garbled, &labels).unwrap();
+                assert_eq!(value(&output), a.wrapping_mul(b));
+            }
+            garbled
+        };
+        let standard = garbled_at(DEFAULT_SECURITY_BITS);
+        let legacy = garbled_at(80);
+        assert_eq!(standard.security(), 128);
+        assert_eq!(standard.rows(), legacy.rows());
+        assert_eq!(standard.ciphertext_bytes(), standard.rows() * 16);
+        assert_eq!(legacy.ciphertext_bytes(), legacy.rows() * 10);
+        assert_eq!(legacy.tables_to_bytes().len(), legacy.ciphertext_bytes());
+
+        assert_eq!(
+            YaoEvaluator::default().evaluate(&legacy, &[]).unwrap_err(),
+            ExecError::SecurityMismatch {
+                garbled: 80,
+                evaluator: 128
+            }
+        );
+        for bits in [0, 64, 100, 136] {
+            assert_eq!(
+                YaoGarbler::with_security(bits).unwrap_err(),
+                ExecError::UnsupportedSecurity { bits }
+            );
+        }
+    }
+
+    #[test]
+    fn test_garble_rejects_malformed_inputs() {
+        let forward = Circuit::new(
+            vec![Gate::InContrib, Gate::Not(2), Gate::InContrib],
+            vec![1],
+        );
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        assert_eq!(
+            garble(&forward, &mut rng).unwrap_err(),
+            ExecError::UnassignedWire { gate: 1, wire: 2 }
+        );
+
+        let circuit = Circuit::new(
+            vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)],
+            vec![2],
+        );
+        let (garbled, encoding) = garble(&circuit, &mut rng).unwrap();
+        assert_eq!(
+            encoding.encode(&[true], &[]).unwrap_err(),
+            ExecError::InputLengthMismatch {
+                expected: 2,
+                got: 1
+            }
+        );
+        assert_eq!(
+            garbled.evaluate(&[0]).unwrap_err(),
+            ExecError::InputLengthMismatch {
+                expected: 2,
+                got: 1
+            }
+        );
+        let labels = encoding.encode(&[true], &[true]).unwrap();
+        assert_eq!(garbled.evaluate(&labels).unwrap(), vec![true]);
+    }
+
+    #[test]
+    fn test_execute_streaming_matches_executor() {
+        let (a, b, c) = (0x1234_5678_u32, 0x9abc_def0_u32, 0x0fed_cba9_u32);
+        let mut builder = WRK17CircuitBuilder::default();
+        let a_wires = builder.input(&GarbledUint32::from(a));
+        let b_wires = builder.input(&GarbledUint32::from(b));
+        let c_wires = builder.input(&GarbledUint32::from(c));
+        let product = builder.mul(&a_wires, &b_wires);
+        let sum = builder.add(&product, &c_wires);
+        let difference = builder.sub(&a_wires, &c_wires);
+        let output = builder.xor(&sum, &difference);
+        let circuit = builder.compile(&output);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut tables = vec![];
+        let (garbled, encoding) = YaoGarbler::default()
+            .garble_streaming(&circuit, &mut rng, &mut tables)
+            .unwrap();
+        assert_eq!(tables.len(), circuit.and_count() * ROWS_PER_AND * ROW_BYTES);
+
+        let labels = encoding.encode(builder.inputs(), &[]).unwrap();
+        let mut result = vec![];
+        let peak = YaoEvaluator::default()
+            .execute_streaming(&garbled, &labels, Cursor::new(tables), &mut result)
+            .unwrap();
+
+        let expected = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+        assert_eq!(result, GarbledUint32::new(expected).to_bytes_le());
+        assert_eq!(
+            u32::from_le_bytes(result.try_into().unwrap()),
+            (a.wrapping_mul(b).wrapping_add(c)) ^ a.wrapping_sub(c)
+        );
+        // labels of dead wires are dropped, so only a fraction of the circuit is ever resident
+        assert!(peak < circuit.gates().len() / 2);
+    }
+
+    #[test]
+    fn test_execute_streaming_rejects_bad_input() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let b = builder.input(&GarbledUint8::from(0_u8));
+        let product = builder.and(&a, &b);
+        let circuit = builder.compile(&product);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut tables = vec![];
+        let garbler = YaoGarbler::with_security(80).unwrap();
+        let (garbled, encoding) = garbler
+            .garble_streaming(&circuit, &mut rng, &mut tables)
+            .unwrap();
+        let labels = encoding.encode(&[bits(3), bits(5)].concat(), &[]).unwrap();
+        let evaluator = YaoEvaluator::with_security(80).unwrap();
+
+        // a truncated stream fails instead of producing partial outputs
+        let truncated = Cursor::new(&tables[..tables.len() - 1]);
+        let mut result = vec![];
+        assert!(matches!(
+            evaluator.execute_streaming(&garbled, &labels, truncated, &mut result),
+            Err(ExecError::Stream(_))
+        ));
+        assert!(result.is_empty());
+
+        assert_eq!(
+            evaluator
+                .execute_streaming(&garbled, &labels[1..], Cursor::new(&tables), vec![])
+                .unwrap_err(),
+            ExecError::InputLengthMismatch {
+                expected: 16,
+                got: 15
+            }
+        );
+        assert_eq!(
+            YaoEvaluator::default()
+                .execute_streaming(&garbled, &labels, Cursor::new(&tables), vec![])
+                .unwrap_err(),
+            ExecError::SecurityMismatch {
+                garbled: 80,
+                evaluator: 128
+            }
+        );
+
+        let mut result = vec![];
+        evaluator
+            .execute_streaming(&garbled, &labels, Cursor::new(&tables), &mut result)
+            .unwrap();
+        assert_eq!(result, vec![3 & 5]);
+    }
+
+    /// Oblivious transfer in the clear over in-process channels: the receiver sees both
+    /// offered labels and picks its own. Insecure, but enough to drive the two roles.
+    struct ClearTransfer {
+        offers: Sender<Vec<[Label; 2]>>,
+        offered: Receiver<Vec<[Label; 2]>>,
+    }
+
+    impl ClearTransfer {
+        fn pair() -> (Self, Self) {
+            let (to_evaluator, from_garbler) = channel();
+            let (to_garbler, from_evaluator) = channel();
+            (
+                ClearTransfer {
+                    offers: to_evaluator,
+                    offered: from_evaluator,
+                },
+                ClearTransfer {
+                    offers: to_garbler,
+                    offered: from_garbler,
+                },
+            )
+        }
+    }
+
+    impl ObliviousTransfer for ClearTransfer {
+        fn send(&mut self, labels: &[[Label; 2]]) -> Result<(), ExecError> {
+            self.offers
+                .send(labels.to_vec())
+                .map_err(|error| ExecError::Protocol(error.to_string()))
+        }
+
+        fn receive(&mut self, choices: &[bool]) -> Result<Vec<Label>, ExecError> {
+            let offers = self
+                .offered
+                .recv()
+                .map_err(|error| ExecError::Protocol(error.to_string()))?;
+            Ok(offers
+                .iter()
+                .zip(choices)
+                .map(|(labels, choice)| labels[*choice as usize])
+                .collect())
+        }
+    }
+
+    /// Runs the garbler on a thread and the evaluator on this one, connected by a pipe.
+    fn run_parties(
+        garbler: YaoGarbler,
+        evaluator: YaoEvaluator,
+        circuit: &Circuit,
+        contributor: &[bool],
+        inputs: &[bool],
+    ) -> (Result<(), ExecError>, Result<Vec<bool>, ExecError>) {
+        let (mut garbler_ot, mut evaluator_ot) = ClearTransfer::pair();
+        let (reader, writer) = io::pipe().unwrap();
+        let (circuit_copy, contributor) = (circuit.clone(), contributor.to_vec());
+        let garbler = thread::spawn(move || {
+            let mut rng = ChaCha20Rng::seed_from_u64(7);
+            garbler.run(
+                &circuit_copy,
+                &contributor,
+                &mut rng,
+                &mut garbler_ot,
+                writer,
+            )
+        });
+        let result = evaluator.run(circuit, inputs, &mut evaluator_ot, reader);
+        (garbler.join().unwrap(), result)
+    }
+
+    #[test]
+    fn test_parties_run_over_channel() {
+        // bit i is `c[i] & e[i]`, bit 8 + i is `c[i] ^ e[i]`
+        let mut gates: Vec<Gate> = (0..16)
+            .map(|i| if i < 8 { Gate::InContrib } else { Gate::InEval })
+            .collect();
+        gates.extend((0..8).map(|i| Gate::And(i, 8 + i)));
+        gates.extend((0..8).map(|i| Gate::Xor(i, 8 + i)));
+        let circuit = Circuit::new(gates, (16..32).collect());
+
+        for (a, b) in [(0x5a_u8, 0x3c_u8), (0xff, 0x81), (0, 0xff)] {
+            let (garbled, result) = run_parties(
+                YaoGarbler::with_security(96).unwrap(),
+                YaoEvaluator::with_security(96).unwrap(),
+                &circuit,
+                &bits(a),
+                &bits(b),
+            );
+            garbled.unwrap();
+            let result = result.unwrap();
+            assert_eq!(
+                result,
+                get_executor()
+                    .execute(&circuit, &bits(a), &bits(b))
+                    .unwrap()
+            );
+            assert_eq!((value(&result[..8]), value(&result[8..])), (a & b, a ^ b));
+        }
+    }
+
+    #[test]
+    fn test_parties_reject_bad_input() {
+        let circuit = Circuit::new(
+            vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)],
+            vec![2],
+        );
+
+        let (_, result) = run_parties(
+            YaoGarbler::with_security(80).unwrap(),
+            YaoEvaluator::default(),
+            &circuit,
+            &[true],
+            &[true],
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            ExecError::SecurityMismatch {
+                garbled: 80,
+                evaluator: 128
+            }
+        );
+
+        let (mut garbler_ot, _) = ClearTransfer::pair();
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        assert_eq!(
+            YaoGarbler::default()
+                .run(&circuit, &[], &mut rng, &mut garbler_ot, vec![])
+                .unwrap_err(),
+            ExecError::InputLengthMismatch {
+                expected: 1,
+                got: 0
+            }
+        );
+        let (_, mut evaluator_ot) = ClearTransfer::pair();
+        assert_eq!(
+            YaoEvaluator::default()
+                .run(&circuit, &[true, false], &mut evaluator_ot, io::empty())
+                .unwrap_err(),
+            ExecError::InputLengthMismatch {
+                expected: 1,
+                got: 2
+            }
+        );
+    }
+}
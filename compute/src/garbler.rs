@@ -6,6 +6,19 @@ use rand_chacha::ChaCha20Rng;
 use tandem::states::Contributor;
 use tandem::Circuit;
 
+/// The contributor's side of the WRK17 two-party protocol implemented by `tandem`, which
+/// garbles the circuit and supplies the contributor inputs. The oblivious transfers for the
+/// evaluator's input labels happen inside the protocol messages.
+///
+/// The roles are transport agnostic: [`start`](Garbler::start) and [`next`](Garbler::next)
+/// return the bytes to send to the [`Evaluator`](crate::evaluator::Evaluator), so the parties
+/// can run in different processes over any channel. [`LocalSimulator`] runs both in-process.
+///
+/// All secure execution in this crate goes through this protocol. The `garble` module behind
+/// the `garble-model` feature garbles circuits in-process to study their cost and does not
+/// replace it.
+///
+/// [`LocalSimulator`]: crate::executor::LocalSimulator
 pub trait Garbler {
     fn start(circuit: &Circuit, input: &[bool]) -> Result<(Self, Vec<u8>)>
     where
@@ -1,29 +1,52 @@
 pub mod evaluator;
 pub mod executor;
+#[cfg(feature = "garble-model")]
+pub mod garble;
 pub mod garbler;
 pub mod int;
 pub mod operations;
+pub mod plain;
 pub mod uint;
 
 pub mod prelude {
-    pub use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    pub use crate::operations::circuits::builder::{
+        AdderStrategy, CompileError, GateBudgetExceeded, InputSpec, WRK17CircuitBuilder,
+    };
 
-    pub use crate::executor::get_executor;
+    pub use crate::executor::{get_executor, CircuitExecution, ExecError};
     pub use crate::int::{
         GarbledInt, GarbledInt128, GarbledInt16, GarbledInt256, GarbledInt32, GarbledInt512,
         GarbledInt64, GarbledInt8,
     };
     pub use crate::operations::circuits::types::GateIndexVec;
+    pub use crate::plain::PlainUint;
     pub use crate::uint::{
-        GarbledBoolean, GarbledUint, GarbledUint128, GarbledUint16, GarbledUint2, GarbledUint256,
-        GarbledUint32, GarbledUint4, GarbledUint512, GarbledUint64, GarbledUint8,
+        CircuitInput, GarbledBoolean, GarbledUint, GarbledUint128, GarbledUint16, GarbledUint2,
+        GarbledUint256, GarbledUint32, GarbledUint4, GarbledUint512, GarbledUint64, GarbledUint8,
     };
-    pub use circuit_macro::encrypted;
+    pub use circuit_macro::{encrypted, CircuitInput};
     pub use tandem::{Circuit, Gate};
 
     pub use crate::evaluator::Evaluator;
     pub use crate::evaluator::GatewayEvaluator;
+    #[cfg(feature = "garble-model")]
+    pub use crate::garble::{
+        garble, GarbledCircuit, GarbledStream, InputEncoding, ObliviousTransfer, YaoEvaluator,
+        YaoGarbler,
+    };
     pub use crate::garbler::Garbler;
     pub use crate::garbler::GatewayGarbler;
+    pub use crate::operations::circuits::bristol::{BristolFormat, BristolParseError};
+    pub use crate::operations::circuits::compose::{CircuitCompose, CompositionError};
+    pub use crate::operations::circuits::dot::CircuitDot;
+    pub use crate::operations::circuits::equivalence::CircuitEquivalence;
+    pub use crate::operations::circuits::fingerprint::CircuitFingerprint;
+    #[cfg(feature = "json")]
+    pub use crate::operations::circuits::json::CircuitJson;
+    pub use crate::operations::circuits::optimize::CircuitOptimizer;
+    pub use crate::operations::circuits::parallel::LayeredCircuit;
+    pub use crate::operations::circuits::serialize::CircuitBytes;
+    pub use crate::operations::circuits::stats::CircuitStats;
     pub use crate::operations::circuits::traits::CircuitExecutor;
+    pub use crate::operations::circuits::visit::{CircuitVisit, GateKind, GateView};
 }
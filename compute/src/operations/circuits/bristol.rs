@@ -0,0 +1,406 @@
+use std::fmt;
+use tandem::{Circuit, Gate, GateIndex};
+
+/// Error returned when a Bristol Fashion circuit cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BristolParseError {
+    /// The 1-based line the error was found on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl BristolParseError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        BristolParseError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for BristolParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for BristolParseError {}
+
+/// Conversion of compiled circuits to the [Bristol Fashion] format used by other MPC
+/// frameworks.
+///
+/// Circuits only contain XOR, AND and NOT gates, since muxes, comparisons and arithmetic are
+/// decomposed while building, so every gate maps to a single `XOR`, `AND` or `INV` line.
+/// Contributor inputs are declared as the first input value and evaluator inputs as the
+/// second, each in gate order. Bristol expects the outputs on the last wires, so outputs that
+/// are not already there are copied with a pair of `INV` gates.
+///
+/// When importing, the first input value is contributed by the garbler and any further values
+/// by the evaluator. `EQW` wire copies are accepted as well; constant `EQ` and `MAND` gates
+/// are not.
+///
+/// [Bristol Fashion]: https://nigelsmart.github.io/MPC-Circuits/
+pub trait BristolFormat: Sized {
+    /// Renders the circuit in Bristol Fashion with a single output value.
+    fn to_bristol(&self) -> String;
+
+    /// Parses a Bristol Fashion circuit, reporting malformed lines and references to wires
+    /// that have not been defined yet.
+    fn from_bristol(input: &str) -> Result<Self, BristolParseError>;
+}
+
+impl BristolFormat for Circuit {
+    fn to_bristol(&self) -> String {
+        let gates = self.gates();
+        let mut wires = vec![0; gates.len()];
+        let mut next_wire = 0;
+
+        // input wires come first, contributor before evaluator
+        for party in [Gate::InContrib, Gate::InEval] {
+            for (index, gate) in gates.iter().enumerate() {
+                if *gate == party {
+                    wires[index] = next_wire;
+                    next_wire += 1;
+                }
+            }
+        }
+        let contrib_inputs = self.contrib_inputs();
+        let eval_inputs = self.eval_inputs();
+
+        let mut lines = Vec::new();
+        for (index, gate) in gates.iter().enumerate() {
+            let line = match *gate {
+                Gate::InContrib | Gate::InEval => continue,
+                Gate::Xor(a, b) => {
+                    let (a, b) = (wires[a as usize], wires[b as usize]);
+                    format!("2 1 {a} {b} {next_wire} XOR")
+                }
+                Gate::And(a, b) => {
+                    let (a, b) = (wires[a as usize], wires[b as usize]);
+                    format!("2 1 {a} {b} {next_wire} AND")
+                }
+                Gate::Not(a) => format!("1 1 {} {next_wire} INV", wires[a as usize]),
+            };
+            wires[index] = next_wire;
+            next_wire += 1;
+            lines.push(line);
+        }
+
+        let outputs: Vec<usize> = self
+            .output_gates()
+            .iter()
+            .map(|output| wires[*output as usize])
+            .collect();
+        let first_output = next_wire.checked_sub(outputs.len());
+        let outputs_in_place = first_output.is_some_and(|first| {
+            first >= contrib_inputs + eval_inputs
+                && outputs
+                    .iter()
+                    .enumerate()
+                    .all(|(i, wire)| *wire == first + i)
+        });
+        if !outputs_in_place {
+            let inverted: Vec<usize> = outputs
+                .iter()
+                .map(|wire| {
+                    lines.push(format!("1 1 {wire} {next_wire} INV"));
+                    next_wire += 1;
+                    next_wire - 1
+                })
+                .collect();
+            for wire in inverted {
+                lines.push(format!("1 1 {wire} {next_wire} INV"));
+                next_wire += 1;
+            }
+        }
+
+        let inputs = if eval_inputs == 0 {
+            format!("1 {contrib_inputs}")
+        } else {
+            format!("2 {contrib_inputs} {eval_inputs}")
+        };
+
+        let mut bristol = format!(
+            "{} {next_wire}\n{inputs}\n1 {}\n\n",
+            lines.len(),
+            outputs.len()
+        );
+        for line in lines {
+            bristol.push_str(&line);
+            bristol.push('\n');
+        }
+        bristol
+    }
+
+    fn from_bristol(input: &str) -> Result<Self, BristolParseError> {
+        let mut lines = input
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        let mut header = |name: &str| {
+            let (number, line) = lines.next().ok_or_else(|| {
+                BristolParseError::new(input.lines().count(), format!("missing {name} line"))
+            })?;
+            Ok::<_, BristolParseError>((number, parse_numbers(number, line.split_whitespace())?))
+        };
+
+        let (number, counts) = header("gate count")?;
+        let [gate_count, wire_count] = counts[..] else {
+            return Err(BristolParseError::new(number, "expected `<gates> <wires>`"));
+        };
+        let (input_line, input_values) = header("input")?;
+        let input_widths = value_widths(input_line, &input_values)?;
+        let (output_line, output_values) = header("output")?;
+        let output_widths = value_widths(output_line, &output_values)?;
+
+        let input_wires: usize = input_widths.iter().sum();
+        let output_wires: usize = output_widths.iter().sum();
+        if input_wires + output_wires > wire_count {
+            return Err(BristolParseError::new(
+                output_line,
+                format!("{input_wires} inputs and {output_wires} outputs do not fit in {wire_count} wires"),
+            ));
+        }
+
+        // the first input value belongs to the contributor, the rest to the evaluator
+        let contrib_wires = input_widths.first().copied().unwrap_or(0);
+        let mut gates: Vec<Gate> = (0..input_wires)
+            .map(|wire| {
+                if wire < contrib_wires {
+                    Gate::InContrib
+                } else {
+                    Gate::InEval
+                }
+            })
+            .collect();
+        let mut wires: Vec<Option<GateIndex>> = vec![None; wire_count];
+        for (wire, gate) in wires.iter_mut().take(input_wires).enumerate() {
+            *gate = Some(wire as GateIndex);
+        }
+
+        let mut parsed_gates = 0;
+        for (number, line) in lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (op, operands) = fields.split_last().expect("blank lines are skipped");
+            let operands = parse_numbers(number, operands.iter().copied())?;
+            let (arity, outputs) = match *op {
+                "XOR" | "AND" => (2, 1),
+                "INV" | "EQW" => (1, 1),
+                other => {
+                    return Err(BristolParseError::new(
+                        number,
+                        format!("unsupported gate `{other}`"),
+                    ))
+                }
+            };
+            if operands.len() != 2 + arity + outputs || operands[..2] != [arity, outputs] {
+                return Err(BristolParseError::new(
+                    number,
+                    format!("`{op}` expects {arity} input and {outputs} output wires"),
+                ));
+            }
+
+            let wire_gate = |wire: usize| match wires.get(wire) {
+                None => Err(BristolParseError::new(
+                    number,
+                    format!("wire {wire} is out of range"),
+                )),
+                Some(None) => Err(BristolParseError::new(
+                    number,
+                    format!("wire {wire} is used before it is defined"),
+                )),
+                Some(Some(gate)) => Ok(*gate),
+            };
+            let gate = match *op {
+                "XOR" => Some(Gate::Xor(wire_gate(operands[2])?, wire_gate(operands[3])?)),
+                "AND" => Some(Gate::And(wire_gate(operands[2])?, wire_gate(operands[3])?)),
+                "INV" => Some(Gate::Not(wire_gate(operands[2])?)),
+                _ => None,
+            };
+            // an `EQW` copy aliases its input instead of adding a gate
+            let index = match gate {
+                Some(gate) => {
+                    gates.push(gate);
+                    (gates.len() - 1) as GateIndex
+                }
+                None => wire_gate(operands[2])?,
+            };
+
+            let output = *operands.last().expect("gates have an output wire");
+            match wires.get_mut(output) {
+                None => {
+                    return Err(BristolParseError::new(
+                        number,
+                        format!("wire {output} is out of range"),
+                    ))
+                }
+                Some(Some(_)) => {
+                    return Err(BristolParseError::new(
+                        number,
+                        format!("wire {output} is defined twice"),
+                    ))
+                }
+                Some(wire) => *wire = Some(index),
+            }
+            parsed_gates += 1;
+        }
+
+        let last_line = input.lines().count();
+        if parsed_gates != gate_count {
+            return Err(BristolParseError::new(
+                last_line,
+                format!("expected {gate_count} gates, found {parsed_gates}"),
+            ));
+        }
+
+        let output_gates = (wire_count - output_wires..wire_count)
+            .map(|wire| {
+                wires[wire].ok_or_else(|| {
+                    BristolParseError::new(
+                        last_line,
+                        format!("output wire {wire} is never defined"),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Circuit::new(gates, output_gates))
+    }
+}
+
+/// Parses the fields of a line as wire numbers or counts.
+fn parse_numbers<'a>(
+    number: usize,
+    fields: impl Iterator<Item = &'a str>,
+) -> Result<Vec<usize>, BristolParseError> {
+    fields
+        .map(|field| {
+            field.parse().map_err(|_| {
+                BristolParseError::new(number, format!("expected a number, found `{field}`"))
+            })
+        })
+        .collect()
+}
+
+/// Splits a `<count> <width>...` input or output declaration into its widths.
+fn value_widths(number: usize, values: &[usize]) -> Result<Vec<usize>, BristolParseError> {
+    match values.split_first() {
+        Some((count, widths)) if *count == widths.len() => Ok(widths.to_vec()),
+        _ => Err(BristolParseError::new(
+            number,
+            "expected a value count followed by that many wire widths",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::get_executor;
+
+    #[test]
+    fn test_to_bristol_half_adder() {
+        let circuit = Circuit::new(
+            vec![
+                Gate::InContrib,
+                Gate::InContrib,
+                Gate::Xor(0, 1),
+                Gate::And(0, 1),
+            ],
+            vec![2, 3],
+        );
+
+        assert_eq!(
+            circuit.to_bristol(),
+            "2 4\n1 2\n1 2\n\n2 1 0 1 2 XOR\n2 1 0 1 3 AND\n"
+        );
+    }
+
+    /// A 2-bit adder: the contributor provides `a`, the evaluator `b`, the output is `a + b`.
+    const ADDER: &str = "4 8
+2 2 2
+1 2
+
+2 1 0 2 4 AND
+2 1 1 3 5 XOR
+2 1 0 2 6 XOR
+2 1 5 4 7 XOR
+";
+
+    #[test]
+    fn test_from_bristol_adder() {
+        let circuit = Circuit::from_bristol(ADDER).unwrap();
+        assert_eq!(circuit.contrib_inputs(), 2);
+        assert_eq!(circuit.eval_inputs(), 2);
+
+        for a in 0..4_u8 {
+            for b in 0..4_u8 {
+                let bits = |value: u8| vec![value & 1 == 1, value & 2 == 2];
+                let result = get_executor()
+                    .execute(&circuit, &bits(a), &bits(b))
+                    .unwrap();
+                assert_eq!(result, bits((a + b) % 4), "{a} + {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_bristol_round_trip() {
+        let circuit = Circuit::new(
+            vec![
+                Gate::InContrib,
+                Gate::InContrib,
+                Gate::InEval,
+                Gate::Xor(0, 2),
+                Gate::Not(1),
+                Gate::And(3, 4),
+            ],
+            vec![4, 5],
+        );
+
+        let imported = Circuit::from_bristol(&circuit.to_bristol()).unwrap();
+        assert_eq!(imported.gates(), circuit.gates());
+        assert_eq!(imported.output_gates(), circuit.output_gates());
+    }
+
+    #[test]
+    fn test_from_bristol_errors() {
+        let error = |input: &str| Circuit::from_bristol(input).unwrap_err();
+
+        assert_eq!(
+            error("1 3\n1 2\n1 1\n\n2 1 0 2 2 XOR\n"),
+            BristolParseError::new(5, "wire 2 is used before it is defined")
+        );
+        assert_eq!(
+            error("1 3\n1 2\n1 1\n\n2 1 0 1 2 3 XOR\n"),
+            BristolParseError::new(5, "`XOR` expects 2 input and 1 output wires")
+        );
+        assert_eq!(
+            error("1 3\n1 2\n1 1\n\n2 1 0 1 2 OR\n"),
+            BristolParseError::new(5, "unsupported gate `OR`")
+        );
+        assert_eq!(
+            error("2 3\n1 2\n1 1\n\n2 1 0 1 2 XOR\n"),
+            BristolParseError::new(5, "expected 2 gates, found 1")
+        );
+        assert_eq!(
+            error("1 3\n2 2\n1 1\n"),
+            BristolParseError::new(
+                2,
+                "expected a value count followed by that many wire widths"
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_bristol_copies_outputs_to_last_wires() {
+        // the evaluator input is declared second even though it is the first gate
+        let circuit = Circuit::new(vec![Gate::InEval, Gate::InContrib, Gate::Not(0)], vec![1]);
+
+        assert_eq!(
+            circuit.to_bristol(),
+            "3 5\n2 1 1\n1 1\n\n1 1 1 2 INV\n1 1 0 3 INV\n1 1 3 4 INV\n"
+        );
+    }
+}
@@ -1,26 +1,149 @@
+use crate::executor::{get_executor, operands, CircuitExecution};
 use crate::operations::circuits::traits::CircuitExecutor;
 use crate::operations::circuits::types::GateIndexVec;
+use crate::uint::CircuitInput;
+use crate::uint::GarbledBoolean;
 use crate::uint::GarbledUint;
-use crate::{executor::get_executor, uint::GarbledBoolean};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
+use std::ops::Range;
 use tandem::{Circuit, Gate};
 
 pub type GateIndex = u32;
 
+/// Comparator flags already built for an operand pair, shared by every relation on those
+/// operands.
+#[derive(Clone, Copy)]
+struct Comparison {
+    lt: GateIndex,
+    eq: GateIndex,
+    le: Option<GateIndex>,
+}
+
 #[derive(Default, Clone, Serialize, Deserialize)]
 pub struct WRK17CircuitBuilder {
     inputs: Vec<bool>,
     gates: Vec<Gate>,
-    constant_cache: HashMap<String, GateIndexVec>,
+    zero: Option<GateIndex>,
+    one: Option<GateIndex>,
+    signed: bool,
+    #[serde(skip)]
+    comparisons: HashMap<(GateIndexVec, GateIndexVec, bool), Comparison>,
+    #[serde(default)]
+    and_gates: usize,
+    #[serde(default)]
+    gate_budget: Option<usize>,
+    /// The outermost operation in progress, which a budget overrun is attributed to rather
+    /// than to the helper that pushed the gate.
+    #[serde(skip)]
+    operation: Option<&'static str>,
+    #[serde(skip)]
+    budget_error: Option<GateBudgetExceeded>,
+    #[serde(skip)]
+    input_layout: Vec<InputSpec>,
+    #[serde(skip)]
+    wire_names: BTreeMap<GateIndex, String>,
+    adder: AdderStrategy,
+    assertions: Vec<(GateIndexVec, GateIndexVec)>,
+}
+
+/// How the builder lays out additions, trading AND gates against circuit depth.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AdderStrategy {
+    /// A chain of full adders: one AND gate per bit after the first two, but the carry
+    /// ripples through every bit, so the depth grows linearly with the width.
+    #[default]
+    RippleCarry,
+    /// A Kogge-Stone parallel-prefix adder: about `n log n` AND gates for a depth that grows
+    /// with the logarithm of the width.
+    KoggeStone,
+}
+
+/// Where one input value lies within the contributor inputs of a circuit, so that callers
+/// can assemble the input bits without knowing the order the builder allocated them in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSpec {
+    /// The parameter the value was given for, when known. The false input that
+    /// [`WRK17CircuitBuilder::zero_wire`] allocates in an empty builder is unnamed.
+    pub name: Option<String>,
+    /// The total width in bits. An array is a single entry whose elements share one width,
+    /// `width / len` bits for an array of `len` elements.
+    pub width: usize,
+    /// The positions of the value's bits within the inputs, least significant bit first.
+    pub wires: Range<usize>,
+}
+
+/// Error reported when a builder created with [`WRK17CircuitBuilder::with_gate_budget`] grows
+/// past its AND-gate budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GateBudgetExceeded {
+    /// The outermost builder operation that pushed the circuit over the budget.
+    pub operation: &'static str,
+    /// The number of AND gates once that operation had added its first gate over the budget.
+    pub and_gates: usize,
+    pub budget: usize,
+}
+
+impl std::fmt::Display for GateBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` exceeded the AND-gate budget of {} ({} AND gates)",
+            self.operation, self.budget, self.and_gates
+        )
+    }
+}
+
+impl std::error::Error for GateBudgetExceeded {}
+
+/// Error returned by [`WRK17CircuitBuilder::try_compile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// An operation pushed the circuit past the builder's AND-gate budget.
+    GateBudgetExceeded(GateBudgetExceeded),
+    /// An output refers to a wire the builder has not created.
+    OutputOutOfBounds { wire: GateIndex, gates: usize },
+    /// A gate reads a wire that is not assigned before it, e.g. one pushed with a wire index
+    /// that was never returned by the builder.
+    UnassignedWire { gate: usize, wire: GateIndex },
+    /// The output wire group is empty, so the circuit would compute nothing.
+    EmptyOutput,
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::GateBudgetExceeded(error) => error.fmt(f),
+            CompileError::OutputOutOfBounds { wire, gates } => write!(
+                f,
+                "output wire {} is out of range for a circuit of {} gates",
+                wire, gates
+            ),
+            CompileError::UnassignedWire { gate, wire } => write!(
+                f,
+                "gate {} reads wire {}, which is not assigned before it",
+                gate, wire
+            ),
+            CompileError::EmptyOutput => write!(f, "the circuit has no output wires"),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl From<GateBudgetExceeded> for CompileError {
+    fn from(error: GateBudgetExceeded) -> Self {
+        CompileError::GateBudgetExceeded(error)
+    }
 }
 
 impl Debug for WRK17CircuitBuilder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut debug_struct = f.debug_struct("WRK17CircuitBuilder");
         debug_struct.field("inputs", &self.inputs);
+        debug_struct.field("signed", &self.signed);
 
         // Collect gates into a formatted string with newlines
         let gates_with_newlines: Vec<String> = self
@@ -35,6 +158,87 @@ impl Debug for WRK17CircuitBuilder {
 }
 
 impl WRK17CircuitBuilder {
+    /// Creates a builder whose comparisons treat wire groups as two's complement signed integers.
+    pub fn signed() -> Self {
+        WRK17CircuitBuilder {
+            signed: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    /// Creates a builder that stops a circuit from growing past `max_and_gates` AND gates,
+    /// which dominate the cost of garbled execution. The first operation to exceed the budget
+    /// is recorded: [`Self::try_compile`] returns it and [`Self::compile`] panics with it.
+    pub fn with_gate_budget(max_and_gates: usize) -> Self {
+        WRK17CircuitBuilder {
+            gate_budget: Some(max_and_gates),
+            ..Default::default()
+        }
+    }
+
+    /// Selects the adder that [`add`](CircuitExecutor::add) and every operation built on it
+    /// use, e.g. [`AdderStrategy::KoggeStone`] for wide operands in depth-sensitive circuits.
+    pub fn with_adder(mut self, adder: AdderStrategy) -> Self {
+        self.adder = adder;
+        self
+    }
+
+    pub fn adder(&self) -> AdderStrategy {
+        self.adder
+    }
+
+    /// Returns the budget overrun, if an operation has exceeded the AND-gate budget.
+    pub fn budget_error(&self) -> Option<&GateBudgetExceeded> {
+        self.budget_error.as_ref()
+    }
+
+    /// Clears every gate, input, constant wire, cached comparison, wire name and assertion so
+    /// the builder can build another circuit, keeping the capacity already allocated. The
+    /// configuration chosen when it was created is kept: signedness, gate budget and adder.
+    pub fn reset(&mut self) {
+        // destructured so that a new field has to be considered here
+        let WRK17CircuitBuilder {
+            inputs,
+            gates,
+            zero,
+            one,
+            signed: _,
+            comparisons,
+            and_gates,
+            gate_budget: _,
+            operation,
+            budget_error,
+            input_layout,
+            wire_names,
+            adder: _,
+            assertions,
+        } = self;
+        inputs.clear();
+        gates.clear();
+        *zero = None;
+        *one = None;
+        comparisons.clear();
+        *and_gates = 0;
+        *operation = None;
+        *budget_error = None;
+        input_layout.clear();
+        wire_names.clear();
+        assertions.clear();
+    }
+
+    /// Starts the named operation and returns the operation already in progress, which the
+    /// caller restores to `self.operation` when it is done. Only the outermost operation is
+    /// recorded, so nested operations leave it unchanged.
+    fn enter_operation(&mut self, name: &'static str) -> Option<&'static str> {
+        let outer = self.operation;
+        self.operation = outer.or(Some(name));
+        outer
+    }
+
     pub fn input<const R: usize>(&mut self, input: &GarbledUint<R>) -> GateIndexVec {
         // get the cumulative size of all inputs in input_labels
         //let input_offset = self.input_labels.iter().map(|x| x.len()).sum::<usize>();
@@ -48,19 +252,752 @@ impl WRK17CircuitBuilder {
 
             input_label.push(new_gate_index);
         }
+        self.input_layout.push(InputSpec {
+            name: None,
+            width: R,
+            wires: self.inputs.len() - R..self.inputs.len(),
+        });
         input_label
     }
 
+    /// Adds every element of a fixed-size array as an input, returning one wire group per
+    /// element in the same order. The whole array is a single entry of the input layout.
+    pub fn input_array<const R: usize, const K: usize>(
+        &mut self,
+        values: [GarbledUint<R>; K],
+    ) -> [GateIndexVec; K] {
+        let start = self.inputs.len();
+        let layout_len = self.input_layout.len();
+        let wires = values.map(|value| self.input(&value));
+        self.input_layout.truncate(layout_len);
+        self.input_layout.push(InputSpec {
+            name: None,
+            width: R * K,
+            wires: start..self.inputs.len(),
+        });
+        wires
+    }
+
+    /// Names the most recently added input, e.g. after the parameter it was given for.
+    pub fn name_input(&mut self, name: &str) {
+        if let Some(spec) = self.input_layout.last_mut() {
+            spec.name = Some(name.to_string());
+        }
+    }
+
+    /// Returns the inputs added so far, in order.
+    pub fn input_layout(&self) -> &[InputSpec] {
+        &self.input_layout
+    }
+
+    /// Attaches a debug name to `wires`, e.g. `carry`. A group of several wires names each bit
+    /// after its position, `carry[0]` for the least significant. Naming adds no gates, and a
+    /// wire named twice keeps the later name.
+    pub fn name(&mut self, wires: &GateIndexVec, name: &str) {
+        if wires.len() == 1 {
+            self.wire_names.insert(wires[0], name.to_string());
+            return;
+        }
+        for (i, wire) in wires.iter().enumerate() {
+            self.wire_names.insert(*wire, format!("{}[{}]", name, i));
+        }
+    }
+
+    /// Returns the names attached with [`Self::name`], by wire. Wires of the builder are the
+    /// gates of the compiled circuit, so the names index the result of
+    /// [`CircuitExecution::trace`] and can be rendered with
+    /// [`CircuitDot::to_dot_with_names`](crate::operations::circuits::dot::CircuitDot::to_dot_with_names).
+    pub fn wire_names(&self) -> &BTreeMap<GateIndex, String> {
+        &self.wire_names
+    }
+
+    /// Appends the gates and inputs of another builder, e.g. a fragment returned by an
+    /// `#[encrypted(build)]` function, and returns its `wires` renumbered into this builder.
+    /// The fragment's inputs follow the inputs added so far, so the combined input bits stay
+    /// in allocation order.
+    pub fn merge(&mut self, other: WRK17CircuitBuilder, wires: &GateIndexVec) -> GateIndexVec {
+        let offset = self.gates.len() as GateIndex;
+        let input_offset = self.inputs.len();
+        self.gates
+            .extend(other.gates.into_iter().map(|gate| match gate {
+                Gate::Xor(a, b) => Gate::Xor(a + offset, b + offset),
+                Gate::And(a, b) => Gate::And(a + offset, b + offset),
+                Gate::Not(a) => Gate::Not(a + offset),
+                input => input,
+            }));
+        self.inputs.extend(other.inputs);
+        self.input_layout
+            .extend(other.input_layout.into_iter().map(|spec| InputSpec {
+                wires: spec.wires.start + input_offset..spec.wires.end + input_offset,
+                ..spec
+            }));
+        self.wire_names.extend(
+            other
+                .wire_names
+                .into_iter()
+                .map(|(wire, name)| (wire + offset, name)),
+        );
+
+        self.assertions
+            .extend(other.assertions.into_iter().map(|(left, right)| {
+                let renumber = |wires: GateIndexVec| {
+                    GateIndexVec::new(wires.iter().map(|wire| wire + offset).collect())
+                };
+                (renumber(left), renumber(right))
+            }));
+
+        self.and_gates += other.and_gates;
+        if self.budget_error.is_none() {
+            self.budget_error = other.budget_error;
+        }
+        if let Some(budget) = self.gate_budget {
+            if self.and_gates > budget && self.budget_error.is_none() {
+                self.budget_error = Some(GateBudgetExceeded {
+                    operation: self.operation.unwrap_or("merge"),
+                    and_gates: self.and_gates,
+                    budget,
+                });
+            }
+        }
+
+        GateIndexVec::new(wires.iter().map(|wire| wire + offset).collect())
+    }
+
+    /// Returns a wire that always carries `false`. It is derived as `x ^ x` from the first
+    /// gate, so no input is allocated unless the circuit is still empty. A circuit has no
+    /// constant gates, so in an empty builder the wire is a 1-bit contributor input set to
+    /// `false`, which appears in [`Self::inputs`] and [`Self::input_layout`] like any other.
+    pub fn zero_wire(&mut self) -> GateIndex {
+        if let Some(zero) = self.zero {
+            return zero;
+        }
+        let zero = if self.gates.is_empty() {
+            self.input::<1>(&0u128.into())[0]
+        } else {
+            self.push_xor(&0, &0)
+        };
+        self.zero = Some(zero);
+        zero
+    }
+
+    /// Returns a wire that always carries `true`, the negation of [`Self::zero_wire`].
+    pub fn one_wire(&mut self) -> GateIndex {
+        if let Some(one) = self.one {
+            return one;
+        }
+        let zero = self.zero_wire();
+        let one = self.push_not(&zero);
+        self.one = Some(one);
+        one
+    }
+
+    /// Encodes `value` as `width` constant wires, least significant bit first. Bits above
+    /// `width` are dropped, so the value wraps like an integer cast.
+    pub fn literal(&mut self, value: u128, width: usize) -> GateIndexVec {
+        let mut wires = GateIndexVec::default();
+        for i in 0..width {
+            if i < 128 && (value >> i) & 1 == 1 {
+                wires.push(self.one_wire());
+            } else {
+                wires.push(self.zero_wire());
+            }
+        }
+        wires
+    }
+
+    /// Encodes `value` as `R` constant wires, least significant bit first, without allocating
+    /// an input for it.
     pub fn constant<const R: usize>(&mut self, value: &GarbledUint<R>) -> GateIndexVec {
-        let key = format!("{:x}", value);
-        if let Some(cached) = self.constant_cache.get(&key) {
-            return cached.clone();
+        GateIndexVec::new(
+            value
+                .bits
+                .iter()
+                .map(|bit| {
+                    if *bit {
+                        self.one_wire()
+                    } else {
+                        self.zero_wire()
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Encodes `value` as `N` constant wires, least significant bit first. Unlike [`literal`],
+    /// the value must fit in `N` bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` does not fit in `N` bits.
+    ///
+    /// [`literal`]: WRK17CircuitBuilder::literal
+    pub fn constant_u128<const N: usize>(&mut self, value: u128) -> GateIndexVec {
+        assert!(
+            N >= 128 || value >> N == 0,
+            "constant {value} does not fit in {N} bits"
+        );
+        self.literal(value, N)
+    }
+
+    /// Resizes `a` to `width` bits, truncating high bits or extending with the zero wire, or
+    /// with copies of the most significant bit when `signed` is set.
+    pub fn resize(&mut self, a: &GateIndexVec, width: usize, signed: bool) -> GateIndexVec {
+        let mut resized = GateIndexVec::new(a.iter().take(width).copied().collect());
+        let fill = match a.len() {
+            0 => self.zero_wire(),
+            n if signed => a[n - 1],
+            _ => self.zero_wire(),
+        };
+        while resized.len() < width {
+            resized.push(fill);
+        }
+        resized
+    }
+
+    /// Zero-extends `a` to `M` bits with the constant-false wire. This only rewires, so no
+    /// gates are added beyond the zero wire itself on its first use.
+    pub fn zero_extend<const M: usize>(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        assert!(M >= a.len(), "cannot zero-extend {} bits to {M}", a.len());
+        self.resize(a, M, false)
+    }
+
+    /// Sign-extends `a` to `M` bits with copies of its most significant wire. No gates are
+    /// added.
+    pub fn sign_extend<const M: usize>(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        assert!(M >= a.len(), "cannot sign-extend {} bits to {M}", a.len());
+        self.resize(a, M, true)
+    }
+
+    /// Truncates `a` to its low `M` bits. No gates are added.
+    pub fn truncate<const M: usize>(&self, a: &GateIndexVec) -> GateIndexVec {
+        assert!(M <= a.len(), "cannot truncate {} bits to {M}", a.len());
+        GateIndexVec::new(a.iter().take(M).copied().collect())
+    }
+
+    /// Adds a compile-time constant to `a` without allocating an input for it.
+    pub fn add_constant(&mut self, a: &GateIndexVec, value: u128) -> GateIndexVec {
+        let outer = self.enter_operation("add_constant");
+        let b = self.literal(value, a.len());
+        let output = self.add(a, &b);
+        self.operation = outer;
+        output
+    }
+
+    /// Multiplies `a` by a compile-time constant (wrapping). Only the set bits of `value`
+    /// contribute a shifted copy of `a`, so no AND gates are needed for the partial products.
+    pub fn mul_constant(&mut self, a: &GateIndexVec, value: u128) -> GateIndexVec {
+        let outer = self.enter_operation("mul_constant");
+        let b = self.literal(value, a.len());
+        let output = self.mul(a, &b);
+        self.operation = outer;
+        output
+    }
+
+    /// Compares `a` with a compile-time constant. Each bit of `a` is taken as is where the
+    /// constant has a one and negated where it has a zero, and the result is the AND of those
+    /// bits, so no XOR gates are needed. Bits of `value` above the width of `a` are dropped,
+    /// as for [`Self::literal`].
+    pub fn eq_constant(&mut self, a: &GateIndexVec, value: u128) -> GateIndex {
+        let outer = self.enter_operation("eq_constant");
+        let matches: Vec<GateIndex> = a
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| {
+                if i < 128 && (value >> i) & 1 == 1 {
+                    *wire
+                } else {
+                    self.push_not(wire)
+                }
+            })
+            .collect();
+        let output = match matches.split_first() {
+            Some((first, rest)) => rest
+                .iter()
+                .fold(*first, |all, wire| self.push_and(&all, wire)),
+            None => self.one_wire(),
+        };
+        self.operation = outer;
+        output
+    }
+
+    /// Returns a wire that is set when `lo <= a <= hi`, both bounds inclusive, as the AND of
+    /// two comparisons with constants. The comparisons follow the builder's signedness and bits
+    /// of the bounds above the width of `a` are dropped, as for [`Self::literal`]. An empty
+    /// range, where `lo > hi`, gives a wire that is never set.
+    pub fn in_range(&mut self, a: &GateIndexVec, lo: u128, hi: u128) -> GateIndex {
+        let outer = self.enter_operation("in_range");
+        let lo = self.literal(lo, a.len());
+        let hi = self.literal(hi, a.len());
+        let above = self.ge(a, &lo);
+        let below = self.le(a, &hi);
+        let output = self.push_and(&above, &below);
+        self.operation = outer;
+        output
+    }
+
+    /// Returns a wire that is set when every bit of `a` is clear. The bits are ORed together
+    /// pairwise, so the tree is `log2(a.len())` AND gates deep, and the result negated. An
+    /// empty `a` is zero.
+    pub fn is_zero(&mut self, a: &GateIndexVec) -> GateIndex {
+        let outer = self.enter_operation("is_zero");
+        let any = self.any_set(a);
+        let output = self.push_not(&any);
+        self.operation = outer;
+        output
+    }
+
+    /// Returns a wire that is set when any bit of `a` is set, the negation of [`Self::is_zero`].
+    pub fn is_nonzero(&mut self, a: &GateIndexVec) -> GateIndex {
+        let outer = self.enter_operation("is_nonzero");
+        let output = self.any_set(a);
+        self.operation = outer;
+        output
+    }
+
+    fn any_set(&mut self, a: &GateIndexVec) -> GateIndex {
+        let mut layer: Vec<GateIndex> = a.iter().copied().collect();
+        if layer.is_empty() {
+            return self.zero_wire();
+        }
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => self.push_or(a, b),
+                    _ => pair[0],
+                })
+                .collect();
+        }
+        layer[0]
+    }
+
+    /// Unsigned addition that clamps to the maximum value instead of wrapping: every bit of
+    /// the wrapping sum is ORed with the carry out.
+    pub fn saturating_add(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("saturating_add");
+        let (sum, carry) = self.add_with_carry(a, b);
+        let output = GateIndexVec::new(sum.iter().map(|bit| self.push_or(bit, &carry)).collect());
+        self.operation = outer;
+        output
+    }
+
+    /// Unsigned subtraction that clamps to zero instead of wrapping: every bit of the wrapping
+    /// difference is cleared when the subtraction borrows.
+    pub fn saturating_sub(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("saturating_sub");
+        let (diff, borrow) = self.sub_with_borrow(a, b);
+        let no_borrow = self.push_not(&borrow);
+        let output = GateIndexVec::new(
+            diff.iter()
+                .map(|bit| self.push_and(bit, &no_borrow))
+                .collect(),
+        );
+        self.operation = outer;
+        output
+    }
+
+    /// Wrapping addition that also returns an overflow bit, leaving the caller to decide what
+    /// to do with it. For an unsigned builder the bit is the carry out of the top bit; for a
+    /// [`signed`](Self::signed) one it is set when both operands have the same sign and the
+    /// sum's sign differs, which is the XOR of the carries into and out of the sign bit.
+    pub fn checked_add(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndexVec, GateIndex) {
+        let outer = self.enter_operation("checked_add");
+        let (sum, carry) = self.add_with_carry(a, b);
+        let overflow = match (self.signed, a.len()) {
+            (true, n) if n > 0 => {
+                let from_a = self.push_xor(&sum[n - 1], &a[n - 1]);
+                let from_b = self.push_xor(&sum[n - 1], &b[n - 1]);
+                self.push_and(&from_a, &from_b)
+            }
+            _ => carry,
+        };
+        self.operation = outer;
+        (sum, overflow)
+    }
+
+    /// Wrapping subtraction that also returns an overflow bit: the borrow out of the top bit
+    /// for an unsigned builder, and for a [`signed`](Self::signed) one whether the operands
+    /// have different signs and the difference's sign differs from `a`'s.
+    pub fn checked_sub(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndexVec, GateIndex) {
+        let outer = self.enter_operation("checked_sub");
+        let (diff, borrow) = self.sub_with_borrow(a, b);
+        let overflow = match (self.signed, a.len()) {
+            (true, n) if n > 0 => {
+                let signs = self.push_xor(&a[n - 1], &b[n - 1]);
+                let flipped = self.push_xor(&diff[n - 1], &a[n - 1]);
+                self.push_and(&signs, &flipped)
+            }
+            _ => borrow,
+        };
+        self.operation = outer;
+        (diff, overflow)
+    }
+
+    /// Selects `entries[index]` with a balanced tree of muxes, one level per index bit, so the
+    /// AND depth grows with `log2(K)` rather than with `K` as a chain of muxes would. Any
+    /// index past the end selects the last entry.
+    ///
+    /// Panics if `entries` is empty or the entries differ in width.
+    pub fn select(&mut self, index: &GateIndexVec, entries: &[GateIndexVec]) -> GateIndexVec {
+        assert!(!entries.is_empty(), "select needs at least one entry");
+        let width = entries[0].len();
+        if let Some(entry) = entries.iter().find(|entry| entry.len() != width) {
+            panic!(
+                "select entries must share one width, got {} and {} bits",
+                width,
+                entry.len()
+            );
+        }
+
+        let outer = self.enter_operation("select");
+        let last = &entries[entries.len() - 1];
+        let levels = bit_width(entries.len() - 1);
+
+        // padding with the last entry covers the indices between `K` and `2^levels`
+        let mut layer = entries.to_vec();
+        layer.resize(1 << levels, last.clone());
+        for level in 0..levels {
+            layer = match index.iter().nth(level) {
+                Some(bit) => layer
+                    .chunks(2)
+                    .map(|pair| self.mux(bit, &pair[1], &pair[0]))
+                    .collect(),
+                // a missing index bit is zero
+                None => layer.into_iter().step_by(2).collect(),
+            };
+        }
+        let selected = layer.swap_remove(0);
+
+        // index bits above the tree mean the index is past the end
+        let mut out_of_range: Option<GateIndex> = None;
+        for bit in index.iter().skip(levels) {
+            out_of_range = Some(match out_of_range {
+                Some(out_of_range) => self.push_or(&out_of_range, bit),
+                None => *bit,
+            });
+        }
+        let output = match out_of_range {
+            Some(out_of_range) => self.mux(&out_of_range, last, &selected),
+            None => selected,
+        };
+        self.operation = outer;
+        output
+    }
+
+    /// Selects `if_true` when `s` is set and `if_false` otherwise, element by element, with
+    /// every mux reading the one condition wire. Each bit costs a single AND gate, as with
+    /// [`mux`](CircuitExecutor::mux).
+    ///
+    /// Panics if the arrays differ in length or their elements differ in width.
+    pub fn mux_array(
+        &mut self,
+        s: &GateIndex,
+        if_true: &[GateIndexVec],
+        if_false: &[GateIndexVec],
+    ) -> Vec<GateIndexVec> {
+        assert_eq!(
+            if_true.len(),
+            if_false.len(),
+            "mux_array arrays must have the same length"
+        );
+        if let Some((index, (a, b))) = if_true
+            .iter()
+            .zip(if_false)
+            .enumerate()
+            .find(|(_, (a, b))| a.len() != b.len())
+        {
+            panic!(
+                "mux_array element {} has {} bits if true and {} bits if false",
+                index,
+                a.len(),
+                b.len()
+            );
+        }
+
+        let outer = self.enter_operation("mux_array");
+        let output = if_true
+            .iter()
+            .zip(if_false)
+            .map(|(a, b)| self.mux(s, a, b))
+            .collect();
+        self.operation = outer;
+        output
+    }
+
+    /// Adds `values` with a balanced tree of adders, pairing neighbours level by level, so the
+    /// depth grows with `log2(K)` rather than with `K` as a chain of additions would. The sum
+    /// wraps to the width of the values; [`Self::sum_wide`] widens it so that it cannot.
+    ///
+    /// Panics if `values` is empty or the values differ in width.
+    pub fn sum(&mut self, values: &[GateIndexVec]) -> GateIndexVec {
+        check_sum_operands(values);
+        let outer = self.enter_operation("sum");
+        let output = self.adder_tree(values.to_vec());
+        self.operation = outer;
+        output
+    }
+
+    /// Adds `values` like [`Self::sum`], first extending them by `ceil(log2(K))` bits so the
+    /// sum of `K` values never overflows. Signed builders sign-extend the values.
+    ///
+    /// Panics if `values` is empty or the values differ in width.
+    pub fn sum_wide(&mut self, values: &[GateIndexVec]) -> GateIndexVec {
+        check_sum_operands(values);
+        let width = values[0].len() + bit_width(values.len() - 1);
+        let outer = self.enter_operation("sum_wide");
+        let signed = self.signed;
+        let values = values
+            .iter()
+            .map(|value| self.resize(value, width, signed))
+            .collect();
+        let output = self.adder_tree(values);
+        self.operation = outer;
+        output
+    }
+
+    fn adder_tree(&mut self, mut layer: Vec<GateIndexVec>) -> GateIndexVec {
+        while layer.len() > 1 {
+            // an odd value out is carried up to the next level unchanged
+            layer = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => self.add(a, b),
+                    _ => pair[0].clone(),
+                })
+                .collect();
+        }
+        layer.swap_remove(0)
+    }
+
+    /// Returns the smaller of `a` and `b`: one comparator, shared with any other comparison
+    /// of the same operands, selects between them. Signed builders compare as signed.
+    pub fn min(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("min");
+        let (lt, _) = self.compare(a, b);
+        let output = self.mux(&lt, a, b);
+        self.operation = outer;
+        output
+    }
+
+    /// Returns the larger of `a` and `b`, sharing the comparator like [`Self::min`].
+    pub fn max(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("max");
+        let (lt, _) = self.compare(a, b);
+        let output = self.mux(&lt, b, a);
+        self.operation = outer;
+        output
+    }
+
+    /// Two's complement absolute value, built without a mux as `(a ^ mask) - mask`, where
+    /// `mask` is the sign bit replicated across the width. Like `wrapping_abs`, the minimum
+    /// value has no positive counterpart and wraps to itself, e.g. `abs(i8::MIN) == i8::MIN`.
+    pub fn abs(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("abs");
+        if a.is_empty() {
+            self.operation = outer;
+            return a.clone();
+        }
+        let mask = GateIndexVec::new(vec![a[a.len() - 1]; a.len()]);
+        let flipped = self.xor(a, &mask);
+        let output = self.sub(&flipped, &mask);
+        self.operation = outer;
+        output
+    }
+
+    /// Counts the set bits of `a` with a tree of adders: the bits are summed pairwise, each
+    /// level one bit wider than the last. The result is `ceil(log2(a.len() + 1))` bits wide,
+    /// just enough to represent `a.len()`.
+    pub fn popcount(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("popcount");
+        let mut counts: Vec<GateIndexVec> =
+            a.iter().map(|bit| GateIndexVec::new(vec![*bit])).collect();
+        while counts.len() > 1 {
+            counts = counts
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => {
+                        let (mut sum, carry) = self.add_with_carry(left, right);
+                        sum.push(carry);
+                        sum
+                    }
+                    // an unpaired count is widened to match the next level
+                    _ => self.resize(&pair[0], pair[0].len() + 1, false),
+                })
+                .collect();
+        }
+        let count = counts.pop().unwrap_or_default();
+        let output = self.resize(&count, bit_width(a.len()), false);
+        self.operation = outer;
+        output
+    }
+
+    /// Counts the zero bits above the most significant set bit of `a`. The result is
+    /// `ceil(log2(a.len() + 1))` bits wide, and is `a.len()` when no bit is set.
+    pub fn leading_zeros(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("leading_zeros");
+        let bits: Vec<GateIndex> = a.iter().rev().copied().collect();
+        let output = self.zeros_before_first_set(&bits);
+        self.operation = outer;
+        output
+    }
+
+    /// Counts the zero bits below the least significant set bit of `a`. The result is
+    /// `ceil(log2(a.len() + 1))` bits wide, and is `a.len()` when no bit is set.
+    pub fn trailing_zeros(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("trailing_zeros");
+        let bits: Vec<GateIndex> = a.iter().copied().collect();
+        let output = self.zeros_before_first_set(&bits);
+        self.operation = outer;
+        output
+    }
+
+    /// Priority encoder over `bits`, in order. The first set bit is isolated as a one-hot
+    /// wire, `bit & !seen`, and at most one of those wires is set, so the position is
+    /// assembled from them with XOR gates alone; the all-zero case contributes `bits.len()`.
+    fn zeros_before_first_set(&mut self, bits: &[GateIndex]) -> GateIndexVec {
+        let mut count: Vec<Option<GateIndex>> = vec![None; bit_width(bits.len())];
+        let mut seen: Option<GateIndex> = None;
+        for (position, bit) in bits.iter().enumerate() {
+            let first = match seen {
+                Some(seen) => {
+                    let not_seen = self.push_not(&seen);
+                    self.push_and(bit, &not_seen)
+                }
+                None => *bit,
+            };
+            self.xor_position(&mut count, first, position);
+            // `seen | bit` is `seen ^ first`, as `first` is set exactly when `bit` is new
+            seen = Some(match seen {
+                Some(seen) => self.push_xor(&seen, &first),
+                None => *bit,
+            });
+        }
+        let none = match seen {
+            Some(seen) => self.push_not(&seen),
+            None => self.one_wire(),
+        };
+        self.xor_position(&mut count, none, bits.len());
+
+        GateIndexVec::new(
+            count
+                .into_iter()
+                .map(|bit| bit.unwrap_or_else(|| self.zero_wire()))
+                .collect(),
+        )
+    }
+
+    /// XORs `wire` into every bit of `count` that is set in `position`.
+    fn xor_position(&mut self, count: &mut [Option<GateIndex>], wire: GateIndex, position: usize) {
+        for (k, bit) in count.iter_mut().enumerate() {
+            if (position >> k) & 1 == 1 {
+                *bit = Some(match *bit {
+                    Some(bit) => self.push_xor(&bit, &wire),
+                    None => wire,
+                });
+            }
+        }
+    }
+
+    /// Ripple-carry addition, returning the wrapping sum and the carry out of the top bit.
+    fn add_with_carry(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndexVec, GateIndex) {
+        match self.adder {
+            AdderStrategy::RippleCarry => self.ripple_carry_with_carry(a, b),
+            AdderStrategy::KoggeStone => self.kogge_stone_with_carry(a, b),
+        }
+    }
+
+    /// Wrapping addition with a ripple-carry adder, whatever the builder's [`AdderStrategy`].
+    pub fn ripple_carry_add(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("ripple_carry_add");
+        let output = self.ripple_carry_with_carry(a, b).0;
+        self.operation = outer;
+        output
+    }
+
+    /// Wrapping addition with a Kogge-Stone adder, whatever the builder's [`AdderStrategy`].
+    /// The carries are computed as a parallel prefix over (generate, propagate) pairs, so the
+    /// depth is logarithmic in the width at the cost of more AND gates than [`add`].
+    ///
+    /// [`add`]: CircuitExecutor::add
+    pub fn add_fast(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("add_fast");
+        let output = self.kogge_stone_with_carry(a, b).0;
+        self.operation = outer;
+        output
+    }
+
+    fn ripple_carry_with_carry(
+        &mut self,
+        a: &GateIndexVec,
+        b: &GateIndexVec,
+    ) -> (GateIndexVec, GateIndex) {
+        let mut carry = None;
+        let mut output_indices = GateIndexVec::default();
+        for i in 0..a.len() {
+            let (sum, new_carry) = full_adder(self, a[i], b[i], carry);
+            output_indices.push(sum);
+            carry = new_carry;
+        }
+        let carry = carry.unwrap_or_else(|| self.zero_wire());
+        (output_indices, carry)
+    }
+
+    fn kogge_stone_with_carry(
+        &mut self,
+        a: &GateIndexVec,
+        b: &GateIndexVec,
+    ) -> (GateIndexVec, GateIndex) {
+        let n = a.len();
+        let propagate: Vec<GateIndex> = (0..n).map(|i| self.push_xor(&a[i], &b[i])).collect();
+        let mut generate: Vec<GateIndex> = (0..n).map(|i| self.push_and(&a[i], &b[i])).collect();
+
+        // after the round with distance `d`, position `i` covers bits `i - 2d + 1..=i`; a span
+        // cannot both generate and propagate a carry, so the OR of the combination is an XOR
+        let mut group_propagate = propagate.clone();
+        let mut distance = 1;
+        while distance < n {
+            let mut next_generate = generate.clone();
+            let mut next_propagate = group_propagate.clone();
+            for i in distance..n {
+                let carried = self.push_and(&group_propagate[i], &generate[i - distance]);
+                next_generate[i] = self.push_xor(&generate[i], &carried);
+                // the group propagate is only read again by a later round
+                if 2 * distance < n {
+                    next_propagate[i] =
+                        self.push_and(&group_propagate[i], &group_propagate[i - distance]);
+                }
+            }
+            generate = next_generate;
+            group_propagate = next_propagate;
+            distance *= 2;
+        }
+
+        let mut sum = GateIndexVec::with_capacity(n);
+        for i in 0..n {
+            sum.push(match i {
+                0 => propagate[0],
+                _ => self.push_xor(&propagate[i], &generate[i - 1]),
+            });
         }
+        let carry = match generate.last() {
+            Some(carry) => *carry,
+            None => self.zero_wire(),
+        };
+        (sum, carry)
+    }
 
-        // Convert the value to a GarbledUint using From<Uint> implementation
-        let wire = self.input(value);
-        self.constant_cache.insert(key, wire.clone());
-        wire
+    /// Ripple-borrow subtraction, returning the wrapping difference and the borrow out of the
+    /// top bit.
+    fn sub_with_borrow(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndexVec, GateIndex) {
+        let mut borrow = None;
+        let mut output_indices = GateIndexVec::default();
+        for i in 0..a.len() {
+            let (diff, new_borrow) = full_subtractor(self, &a[i], &b[i], &borrow);
+            output_indices.push(diff);
+            borrow = new_borrow;
+        }
+        let borrow = borrow.unwrap_or_else(|| self.zero_wire());
+        (output_indices, borrow)
     }
 
     pub fn len(&self) -> GateIndex {
@@ -86,6 +1023,16 @@ impl WRK17CircuitBuilder {
     pub fn push_and(&mut self, a: &GateIndex, b: &GateIndex) -> GateIndex {
         let and_index = self.gates.len() as u32;
         self.gates.push(Gate::And(*a, *b));
+        self.and_gates += 1;
+        if let Some(budget) = self.gate_budget {
+            if self.and_gates > budget && self.budget_error.is_none() {
+                self.budget_error = Some(GateBudgetExceeded {
+                    operation: self.operation.unwrap_or("push_and"),
+                    and_gates: self.and_gates,
+                    budget,
+                });
+            }
+        }
         and_index
     }
 
@@ -120,44 +1067,74 @@ impl WRK17CircuitBuilder {
         self.push_not(&xor_gate)
     }
 
+    /// Predicts the output wires of a `mux` over `a` built next: each bit pushes three gates
+    /// and its output is the last of them.
     pub fn mux_lookahead(&mut self, a: &GateIndexVec) -> GateIndexVec {
         // repeat with output_indices
         let mut output = GateIndexVec::default();
-        let mut counter = self.len() + 5 + 1;
+        let mut counter = self.len() + 2;
 
         for _ in 0..a.len() {
             output.push(counter);
-            counter += 6 + 1;
+            counter += 3;
         }
         output
     }
 
-    #[allow(dead_code)]
-    // Add a MUX gate: MUX(a, b, s) = (a & !s) | (b & s)
+    // Add a MUX gate: MUX(a, b, s) = a ^ (s & (a ^ b)), selecting `b` when `s` is set.
+    // With free XOR this costs a single AND gate instead of the three of (a & !s) | (b & s).
     pub fn push_mux(&mut self, s: &GateIndex, a: &GateIndex, b: &GateIndex) -> GateIndex {
-        let not_s = self.push_not(s);
-        let and_a_not_s = self.push_and(a, &not_s);
-        let and_b_s = self.push_and(b, s);
-        self.push_or(&and_a_not_s, &and_b_s)
+        let a_xor_b = self.push_xor(a, b);
+        let select = self.push_and(s, &a_xor_b);
+        self.push_xor(a, &select)
+    }
+
+    /// ANDs two single wires, e.g. from [`GateIndexVec::get`], for gadgets built bit by bit.
+    pub fn and_bit(&mut self, a: GateIndex, b: GateIndex) -> GateIndex {
+        self.push_and(&a, &b)
+    }
+
+    /// XORs two single wires. XOR gates are free with free-XOR garbling.
+    pub fn xor_bit(&mut self, a: GateIndex, b: GateIndex) -> GateIndex {
+        self.push_xor(&a, &b)
     }
 
-    /// Implements the division circuit using a bit-serial long division algorithm.
-    fn div_inner(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndexVec, GateIndexVec) {
+    /// ORs two single wires as `a ^ b ^ (a & b)`, which costs one AND gate.
+    pub fn or_bit(&mut self, a: GateIndex, b: GateIndex) -> GateIndex {
+        self.push_or(&a, &b)
+    }
+
+    /// Negates a single wire.
+    pub fn not_bit(&mut self, a: GateIndex) -> GateIndex {
+        self.push_not(&a)
+    }
+
+    /// Builds an unsigned restoring long division network and returns `(quotient, remainder)`,
+    /// so `div` and `rem` share the same gates when both are needed.
+    ///
+    /// The results match Rust's `/` and `%` for unsigned integers. Division by zero does not
+    /// panic inside a circuit: the remainder never drops below a zero divisor, so every
+    /// quotient bit is set and the result is `(all ones, a)`.
+    pub fn div_rem(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndexVec, GateIndexVec) {
+        let outer = self.enter_operation("div_rem");
         let n = a.len();
 
         // zero out quotient, remainder
-        let mut quotient = GateIndexVec::new(vec![self.constant::<1>(&0u128.into())[0]; n]);
-        let mut remainder = GateIndexVec::new(vec![self.constant::<1>(&0u128.into())[0]; n]);
+        let zero = self.zero_wire();
+        let mut quotient = GateIndexVec::new(vec![zero; n]);
+        let mut remainder = GateIndexVec::new(vec![zero; n]);
 
-        let one_bit_vec = self.constant::<1>(&1u128.into());
-        let zero_bit_vec = self.constant::<1>(&0u128.into());
+        let one_bit_vec = GateIndexVec::new(vec![self.one_wire()]);
+        let zero_bit_vec = GateIndexVec::new(vec![zero]);
 
         // For each bit from MSB down to LSB:
         for i in (0..n).rev() {
             remainder = self.shift_left(&remainder);
             remainder = self.set_lsb(&remainder, a[i]);
 
-            let ge_bit = self.ge(&remainder, b);
+            // the remainder is always compared as unsigned, even in signed circuits
+            let (lt_bit, _) = self.compare_inner(&remainder, b, false);
+            let ge_bit = self.push_not(&lt_bit);
 
             let remainder_sub = self.sub(&remainder, b);
             remainder = self.mux(&ge_bit, &remainder_sub, &remainder);
@@ -167,12 +1144,38 @@ impl WRK17CircuitBuilder {
             quotient = self.set_lsb(&quotient, q_bit);
         }
 
+        self.operation = outer;
         (quotient, remainder)
     }
 
+    /// Divides `a` by `2^shift` by rewiring its bits, with the unsigned semantics of
+    /// [`div_rem`](Self::div_rem) but no gates: the quotient is `a` shifted right, filled with
+    /// the zero wire.
+    pub fn div_pow2(&mut self, a: &GateIndexVec, shift: usize) -> GateIndexVec {
+        let zero = self.zero_wire();
+        GateIndexVec::new(
+            (0..a.len())
+                .map(|i| a.get(i + shift).unwrap_or(zero))
+                .collect(),
+        )
+    }
+
+    /// Reduces `a` modulo `2^shift` by rewiring its bits, with the unsigned semantics of
+    /// [`div_rem`](Self::div_rem) but no gates: the remainder keeps the low `shift` bits of
+    /// `a` and reads the zero wire above them.
+    pub fn rem_pow2(&mut self, a: &GateIndexVec, shift: usize) -> GateIndexVec {
+        let zero = self.zero_wire();
+        GateIndexVec::new(
+            a.iter()
+                .enumerate()
+                .map(|(i, wire)| if i < shift { *wire } else { zero })
+                .collect(),
+        )
+    }
+
     fn shift_left(&mut self, vec: &GateIndexVec) -> GateIndexVec {
         let n = vec.len();
-        let zero = self.constant::<1>(&0u128.into())[0];
+        let zero = self.zero_wire();
         let mut new_vec = GateIndexVec::default();
         new_vec.push(zero);
         for i in 0..(n - 1) {
@@ -189,9 +1192,10 @@ impl WRK17CircuitBuilder {
         new_vec
     }
 
-    fn fixed_shl(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+    /// Shifts left by a constant amount by rewiring bits; vacated bits read the zero wire.
+    pub fn fixed_shl(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
         let n = a.len();
-        let zero = self.constant::<1>(&0u128.into())[0];
+        let zero = self.zero_wire();
         let mut new_vec = GateIndexVec::default();
         for i in 0..n {
             if i < amount {
@@ -203,88 +1207,399 @@ impl WRK17CircuitBuilder {
         new_vec
     }
 
-    fn fixed_shr(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+    /// Shifts right by a constant amount by rewiring bits. Vacated bits read the zero wire,
+    /// or the sign bit when the builder is signed (arithmetic shift).
+    pub fn fixed_shr(&mut self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
         let n = a.len();
-        let zero = self.constant::<1>(&0u128.into())[0];
+        let fill = if self.signed {
+            a[n - 1]
+        } else {
+            self.zero_wire()
+        };
         let mut new_vec = GateIndexVec::default();
         for i in 0..n {
             if i + amount < n {
                 new_vec.push(a[i + amount]);
             } else {
-                new_vec.push(zero);
+                new_vec.push(fill);
             }
         }
         new_vec
     }
 
-    pub fn compile(&self, output_indices: &GateIndexVec) -> Circuit {
-        Circuit::new(self.gates.clone(), output_indices.clone().into())
-    }
-
-    pub fn execute<const N: usize>(&self, circuit: &Circuit) -> anyhow::Result<GarbledUint<N>> {
-        let result = get_executor().execute(circuit, &self.inputs, &[])?;
-        Ok(GarbledUint::new(result))
+    /// Shifts left by an amount known only at run time with a barrel shifter: one layer of
+    /// muxes for each of the low `log2(a.len())` bits of `shift`, shifting by its power of two
+    /// when the bit is set. Any higher bit of `shift` means an amount of at least `a.len()`,
+    /// which clears every bit.
+    pub fn shl_var(&mut self, a: &GateIndexVec, shift: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("shl_var");
+        let zero = self.zero_wire();
+        let output = self.barrel_shift(a, shift, zero, Self::fixed_shl);
+        self.operation = outer;
+        output
     }
 
-    // Simulate the circuit using the provided input values
-    pub fn compile_and_execute<const N: usize>(
-        &self,
-        output_indices: &GateIndexVec,
-    ) -> anyhow::Result<GarbledUint<N>> {
-        let circuit = self.compile(output_indices);
-        let result = get_executor().execute(&circuit, &self.inputs, &[])?;
-        Ok(GarbledUint::new(result))
+    /// Shifts right by an amount known only at run time, as [`Self::shl_var`] does. An amount
+    /// of at least `a.len()` clears every bit, or sets every bit to the sign bit when the
+    /// builder is signed.
+    pub fn shr_var(&mut self, a: &GateIndexVec, shift: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("shr_var");
+        let Some(sign) = a.iter().last().copied() else {
+            self.operation = outer;
+            return a.clone();
+        };
+        let fill = if self.signed { sign } else { self.zero_wire() };
+        let output = self.barrel_shift(a, shift, fill, Self::fixed_shr);
+        self.operation = outer;
+        output
     }
-}
-
-impl CircuitExecutor for WRK17CircuitBuilder {
-    type Type = GateIndex;
-    type TypeVec = GateIndexVec;
 
-    fn xor(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
-        // repeat with output_indices
-        let mut output = GateIndexVec::default();
-        for i in 0..a.len() {
-            let xor = self.push_xor(&a[i], &b[i]);
-            output.push(xor);
+    fn barrel_shift(
+        &mut self,
+        a: &GateIndexVec,
+        shift: &GateIndexVec,
+        fill: GateIndex,
+        layer: fn(&mut Self, &GateIndexVec, usize) -> GateIndexVec,
+    ) -> GateIndexVec {
+        if a.is_empty() {
+            return a.clone();
+        }
+        let layers = bit_width(a.len() - 1).min(shift.len());
+        let mut result = a.clone();
+        for i in 0..layers {
+            let partial = layer(self, &result, 1 << i);
+            result = self.mux(&shift[i], &partial, &result);
         }
-        output
-    }
 
-    fn and(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
-        // repeat with output_indices
-        let mut output = GateIndexVec::default();
-        for i in 0..a.len() {
-            let and = self.push_and(&a[i], &b[i]);
-            output.push(and);
+        let high = GateIndexVec::new(shift.iter().skip(layers).copied().collect());
+        if high.is_empty() {
+            return result;
         }
-        output
+        let overflow = self.any_set(&high);
+        let filled = GateIndexVec::new(vec![fill; a.len()]);
+        self.mux(&overflow, &filled, &result)
     }
 
-    fn land(&mut self, a: &GateIndex, b: &GateIndex) -> GateIndex {
-        // repeat with output_indices
-        let mut output = GateIndexVec::default();
-        let and = self.push_and(a, b);
-        output.push(and);
-        output.into()
+    /// Rotates `a` left by `amount` bits (modulo its width). This only rewires bits, so no
+    /// gates are added.
+    pub fn rotate_left(&self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+        let n = a.len();
+        if n == 0 {
+            return a.clone();
+        }
+        let amount = amount % n;
+        GateIndexVec::new((0..n).map(|i| a[(i + n - amount) % n]).collect())
     }
 
-    fn not(&mut self, a: &GateIndexVec) -> GateIndexVec {
-        let mut output = GateIndexVec::default();
-        for i in 0..a.len() {
-            let not_gate = self.push_not(&a[i]);
-            output.push(not_gate);
+    /// Rotates `a` right by `amount` bits (modulo its width). This only rewires bits, so no
+    /// gates are added.
+    pub fn rotate_right(&self, a: &GateIndexVec, amount: usize) -> GateIndexVec {
+        let n = a.len();
+        if n == 0 {
+            return a.clone();
         }
-        output
+        self.rotate_left(a, n - amount % n)
+    }
+
+    /// Joins wire groups into one wider value, placing each group above the ones before it, so
+    /// the first group holds the least significant bits. This only rewires bits, so no gates
+    /// are added.
+    pub fn concat(&self, parts: &[GateIndexVec]) -> GateIndexVec {
+        let mut wires = GateIndexVec::with_capacity(parts.iter().map(GateIndexVec::len).sum());
+        for part in parts {
+            wires.push_all(part);
+        }
+        wires
+    }
+
+    /// Splits `a` into consecutive groups of the given widths, starting from the least
+    /// significant bit, as the inverse of [`concat`](Self::concat). This only rewires bits, so
+    /// no gates are added.
+    ///
+    /// Panics if the widths do not add up to the width of `a`.
+    pub fn split(&self, a: &GateIndexVec, widths: &[usize]) -> Vec<GateIndexVec> {
+        assert_eq!(
+            widths.iter().sum::<usize>(),
+            a.len(),
+            "cannot split {} bits into groups of {:?} bits",
+            a.len(),
+            widths
+        );
+        let mut start = 0;
+        widths
+            .iter()
+            .map(|width| {
+                let group = GateIndexVec::new(a.as_slice()[start..start + width].to_vec());
+                start += width;
+                group
+            })
+            .collect()
+    }
+
+    /// Returns bit `index` of `a`, counting from the least significant bit. This only rewires
+    /// bits, so no gates are added.
+    ///
+    /// Panics if `index` is not below the width of `a`.
+    pub fn bit(&self, a: &GateIndexVec, index: usize) -> GateIndex {
+        assert!(
+            index < a.len(),
+            "bit {} is out of range for {} bits",
+            index,
+            a.len()
+        );
+        a[index]
+    }
+
+    /// Returns field `name` of a record of type `T` held in the low bits of `record`, resized
+    /// to `width` bits with the field's signedness. The field is cut out with
+    /// [`split`](Self::split), so no gates are added beyond the zero wire for the extension.
+    ///
+    /// Panics if `T` has no field `name` or `record` is narrower than `T`.
+    pub fn field<T: CircuitInput>(
+        &mut self,
+        record: &GateIndexVec,
+        name: &str,
+        width: usize,
+    ) -> GateIndexVec {
+        assert!(
+            record.len() >= T::WIDTH,
+            "a record of {} bits does not fit in {} bits",
+            T::WIDTH,
+            record.len()
+        );
+        let mut offset = 0;
+        for (field, field_width, signed) in T::FIELDS {
+            if *field == name {
+                let parts = self.split(
+                    record,
+                    &[offset, *field_width, record.len() - offset - field_width],
+                );
+                return self.resize(&parts[1], width, *signed);
+            }
+            offset += field_width;
+        }
+        panic!("the record has no field `{}`", name);
+    }
+
+    /// Compares two wire groups from the most significant bit down, returning `(lt, eq)`.
+    /// When `signed` is set the most significant bit is treated as the sign bit, so a set
+    /// bit in `a` against a clear bit in `b` means `a < b`.
+    fn compare_inner(
+        &mut self,
+        a: &GateIndexVec,
+        b: &GateIndexVec,
+        signed: bool,
+    ) -> (GateIndex, GateIndex) {
+        let mut eq_list = vec![0; a.len()];
+        let mut lt_list = vec![0; a.len()];
+
+        let i = a.len() - 1;
+        let eq_i = self.push_xnor(&a[i], &b[i]);
+        eq_list[i] = eq_i;
+
+        let lt_i = if signed {
+            let nt = self.push_not(&b[i]);
+            self.push_and(&a[i], &nt)
+        } else {
+            let nt = self.push_not(&a[i]);
+            self.push_and(&nt, &b[i])
+        };
+        lt_list[i] = lt_i;
+
+        for idx in (0..i).rev() {
+            let xn = self.push_xnor(&a[idx], &b[idx]);
+            let eq_i = self.push_and(&eq_list[idx + 1], &xn);
+            eq_list[idx] = eq_i;
+
+            let nt = self.push_not(&a[idx]);
+            let aa = self.push_and(&nt, &b[idx]);
+            let temp_lt = self.push_and(&eq_list[idx + 1], &aa);
+            lt_list[idx] = self.push_or(&lt_list[idx + 1], &temp_lt);
+        }
+
+        (lt_list[0], eq_list[0])
+    }
+
+    /// Compiles the gates built so far into a circuit with the given output wires, without
+    /// checking the wiring, which only [`Self::try_compile`] reports.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an operation has exceeded the AND-gate budget set by
+    /// [`Self::with_gate_budget`].
+    pub fn compile(&self, output_indices: &GateIndexVec) -> Circuit {
+        if let Some(error) = &self.budget_error {
+            panic!("{}", error);
+        }
+        Circuit::new(self.gates.clone(), output_indices.clone().into())
+    }
+
+    /// Compiles the circuit, or returns why it cannot be without panicking, e.g. for circuits
+    /// built from untrusted descriptions: an operation has exceeded the AND-gate budget set
+    /// by [`Self::with_gate_budget`], a gate reads a wire that is not assigned before it, or
+    /// the output wire group is empty or names a wire that does not exist.
+    pub fn try_compile(&self, output_indices: &GateIndexVec) -> Result<Circuit, CompileError> {
+        if let Some(error) = &self.budget_error {
+            return Err(error.clone().into());
+        }
+        for (gate, operands) in self.gates.iter().map(operands).enumerate() {
+            if let Some(wire) = operands
+                .into_iter()
+                .flatten()
+                .find(|wire| *wire as usize >= gate)
+            {
+                return Err(CompileError::UnassignedWire { gate, wire });
+            }
+        }
+        if output_indices.is_empty() {
+            return Err(CompileError::EmptyOutput);
+        }
+        if let Some(wire) = output_indices
+            .iter()
+            .find(|wire| **wire as usize >= self.gates.len())
+        {
+            return Err(CompileError::OutputOutOfBounds {
+                wire: *wire,
+                gates: self.gates.len(),
+            });
+        }
+        Ok(Circuit::new(
+            self.gates.clone(),
+            output_indices.clone().into(),
+        ))
+    }
+
+    pub fn execute<const N: usize>(&self, circuit: &Circuit) -> anyhow::Result<GarbledUint<N>> {
+        Ok(GarbledUint::new(self.execute_bits(circuit)?))
+    }
+
+    /// Executes the circuit and returns its raw output bits, for outputs that span several values.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if an assertion added with [`Self::assert_eq`] does not hold.
+    pub fn execute_bits(&self, circuit: &Circuit) -> anyhow::Result<Vec<bool>> {
+        self.check_assertions()?;
+        Ok(circuit.execute_bits(&self.inputs)?)
+    }
+
+    /// Asserts that two wire groups carry the same value. The assertion adds no gates and is
+    /// not part of the compiled circuit: in debug builds it is checked on the builder's inputs
+    /// whenever the builder executes a circuit, and in release builds it is ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the wire groups differ in width.
+    pub fn assert_eq(&mut self, left: &GateIndexVec, right: &GateIndexVec) {
+        assert_eq!(
+            left.len(),
+            right.len(),
+            "assert_eq on wire groups of different widths"
+        );
+        self.assertions.push((left.clone(), right.clone()));
+    }
+
+    /// Returns the number of assertions added with [`Self::assert_eq`].
+    pub fn assertions(&self) -> usize {
+        self.assertions.len()
+    }
+
+    /// Evaluates the wires of every assertion in a circuit of their own and panics at the
+    /// first one that does not hold. Does nothing in release builds.
+    fn check_assertions(&self) -> anyhow::Result<()> {
+        if !cfg!(debug_assertions) || self.assertions.is_empty() {
+            return Ok(());
+        }
+        let wires = self
+            .assertions
+            .iter()
+            .flat_map(|(left, right)| left.iter().chain(right.iter()).copied())
+            .collect();
+        let bits = Circuit::new(self.gates.clone(), wires).execute_bits(&self.inputs)?;
+
+        let mut bits = bits.as_slice();
+        for (index, (left, _)) in self.assertions.iter().enumerate() {
+            let (values, rest) = bits.split_at(2 * left.len());
+            let (left, right) = values.split_at(left.len());
+            if left != right {
+                panic!(
+                    "circuit assertion {} failed: left = {}, right = {}",
+                    index,
+                    format_bits(left),
+                    format_bits(right)
+                );
+            }
+            bits = rest;
+        }
+        Ok(())
+    }
+
+    // Simulate the circuit using the provided input values
+    pub fn compile_and_execute<const N: usize>(
+        &self,
+        output_indices: &GateIndexVec,
+    ) -> anyhow::Result<GarbledUint<N>> {
+        self.check_assertions()?;
+        let circuit = self.compile(output_indices);
+        let result = get_executor().execute(&circuit, &self.inputs, &[])?;
+        Ok(GarbledUint::new(result))
+    }
+}
+
+impl CircuitExecutor for WRK17CircuitBuilder {
+    type Type = GateIndex;
+    type TypeVec = GateIndexVec;
+
+    fn xor(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        // repeat with output_indices
+        let mut output = GateIndexVec::default();
+        for i in 0..a.len() {
+            let xor = self.push_xor(&a[i], &b[i]);
+            output.push(xor);
+        }
+        output
+    }
+
+    fn and(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("and");
+        // repeat with output_indices
+        let mut output = GateIndexVec::default();
+        for i in 0..a.len() {
+            let and = self.push_and(&a[i], &b[i]);
+            output.push(and);
+        }
+        self.operation = outer;
+        output
+    }
+
+    fn land(&mut self, a: &GateIndex, b: &GateIndex) -> GateIndex {
+        let outer = self.enter_operation("land");
+        // repeat with output_indices
+        let mut output = GateIndexVec::default();
+        let and = self.push_and(a, b);
+        output.push(and);
+        self.operation = outer;
+        output.into()
+    }
+
+    fn not(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let mut output = GateIndexVec::default();
+        for i in 0..a.len() {
+            let not_gate = self.push_not(&a[i]);
+            output.push(not_gate);
+        }
+        output
     }
 
     fn or(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("or");
         // repeat with output_indices
         let mut output = GateIndexVec::default();
         for i in 0..a.len() {
             let or_gate = self.push_or(&a[i], &b[i]);
             output.push(or_gate);
         }
+        self.operation = outer;
         output
     }
 
@@ -294,116 +1609,145 @@ impl CircuitExecutor for WRK17CircuitBuilder {
     }
 
     fn nand(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("nand");
         // repeat with output_indices
         let mut output = GateIndexVec::default();
         for i in 0..a.len() {
             let nand = self.push_nand(&a[i], &b[i]);
             output.push(nand);
         }
+        self.operation = outer;
         output
     }
 
     fn nor(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("nor");
         // repeat with output_indices
         let mut output = GateIndexVec::default();
         for i in 0..a.len() {
             let nor = self.push_nor(&a[i], &b[i]);
             output.push(nor);
         }
+        self.operation = outer;
         output
     }
 
     fn xnor(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("xnor");
         // repeat with output_indices
         let mut output = GateIndexVec::default();
         for i in 0..a.len() {
             let xnor = self.push_xnor(&a[i], &b[i]);
             output.push(xnor);
         }
+        self.operation = outer;
         output
     }
 
     fn mux(&mut self, s: &GateIndex, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("mux");
         // repeat with output_indices
         let mut output = GateIndexVec::default();
         for i in 0..a.len() {
             let mux = self.push_mux(s, &b[i], &a[i]);
             output.push(mux);
         }
+        self.operation = outer;
         output
     }
 
     fn add(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
-        let mut carry = None;
-        let mut output_indices = GateIndexVec::default();
-        for i in 0..a.len() {
-            let (sum, new_carry) = full_adder(self, a[i], b[i], carry);
-            output_indices.push(sum);
-            carry = new_carry;
-        }
-        output_indices
+        let outer = self.enter_operation("add");
+        let output = self.add_with_carry(a, b).0;
+        self.operation = outer;
+        output
     }
 
     fn sub(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
-        let mut borrow = None;
-        let mut output_indices = GateIndexVec::default();
-        for i in 0..a.len() {
-            let (diff, new_borrow) = full_subtractor(self, &a[i], &b[i], &borrow);
-            output_indices.push(diff);
-            borrow = new_borrow;
-        }
-        output_indices
+        let outer = self.enter_operation("sub");
+        let output = self.sub_with_borrow(a, b).0;
+        self.operation = outer;
+        output
     }
 
-    fn mul(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
-        let mut partial_products: Vec<GateIndexVec> = Vec::with_capacity(a.len());
+    fn neg(&mut self, a: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("neg");
+        let inverted = self.not(a);
+        let output = self.add_constant(&inverted, 1);
+        self.operation = outer;
+        output
+    }
 
-        // Generate partial products
-        for i in 0..a.len() {
-            let shifted_product = partial_product_shift(self, a, b, i);
-            partial_products.push(shifted_product);
-        }
+    /// Wrapping multiplication with the semantics of `wrapping_mul`: a shift-and-add network
+    /// that keeps only the low `a.len()` bits. Bits of `b` that are the builder's constant
+    /// wires are resolved while building, so a constant zero multiplier yields constant-false
+    /// wires and a constant power of two reduces to a shift.
+    fn mul(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
+        let outer = self.enter_operation("mul");
+        let n = a.len();
+        let mut result: Option<GateIndexVec> = None;
 
-        // Sum up all partial products
-        let mut result = partial_products[0].clone();
-        for partial_product in partial_products.iter().take(a.len()).skip(1) {
-            result = self.add(&result, partial_product);
+        for i in 0..n {
+            let partial = if Some(b[i]) == self.zero {
+                continue;
+            } else if Some(b[i]) == self.one {
+                self.fixed_shl(a, i)
+            } else {
+                partial_product_shift(self, a, b, i)
+            };
+
+            result = Some(match result {
+                None => partial,
+                Some(acc) => {
+                    // the low `i` bits of the partial product are zero, so only the high
+                    // bits need an adder; the carry out of bit `n - 1` is discarded
+                    let high_acc = GateIndexVec::new(acc.iter().skip(i).copied().collect());
+                    let high_partial = GateIndexVec::new(partial.iter().skip(i).copied().collect());
+                    let high_sum = self.add(&high_acc, &high_partial);
+
+                    let mut sum = GateIndexVec::new(acc.iter().take(i).copied().collect());
+                    sum.push_all(&high_sum);
+                    sum
+                }
+            });
         }
 
-        result
+        let output = result.unwrap_or_else(|| self.literal(0, n));
+        self.operation = outer;
+        output
     }
 
     fn div(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
-        self.div_inner(a, b).0
+        let outer = self.enter_operation("div");
+        let output = self.div_rem(a, b).0;
+        self.operation = outer;
+        output
     }
 
     fn rem(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndexVec {
-        self.div_inner(a, b).1
+        let outer = self.enter_operation("rem");
+        let output = self.div_rem(a, b).1;
+        self.operation = outer;
+        output
     }
 
     fn shl(&mut self, a: &GateIndexVec, shift: &GateIndexVec) -> GateIndexVec {
-        let mut result = a.clone();
-        let k = shift.len();
-        for i in 0..k {
-            let amount = 1 << i;
-            let partial = self.fixed_shl(&result, amount);
-            result = self.mux(&shift[i], &partial, &result);
-        }
-        result
+        self.shl_var(a, shift)
     }
 
     fn shr(&mut self, a: &GateIndexVec, shift: &GateIndexVec) -> GateIndexVec {
-        let mut result = a.clone();
-        let k = shift.len();
-        for i in 0..k {
-            let amount = 1 << i;
-            let partial = self.fixed_shr(&result, amount);
-            result = self.mux(&shift[i], &partial, &result);
-        }
-        result
+        self.shr_var(a, shift)
     }
 
     fn eq(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
+        let outer = self.enter_operation("eq");
+        // an ordering comparison of the same operands has already built the equality chain
+        let key = (a.clone(), b.clone(), self.signed);
+        if let Some(comparison) = self.comparisons.get(&key) {
+            self.operation = outer;
+            return comparison.eq;
+        }
+
         let mut eq_list = vec![0; a.len()];
 
         let i = a.len() - 1;
@@ -416,59 +1760,71 @@ impl CircuitExecutor for WRK17CircuitBuilder {
             eq_list[idx] = eq_i;
         }
 
+        self.operation = outer;
         eq_list[0]
     }
 
     fn ne(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
+        let outer = self.enter_operation("ne");
         let eq = self.eq(a, b);
-        self.push_not(&eq)
+        let output = self.push_not(&eq);
+        self.operation = outer;
+        output
     }
 
     fn gt(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
-        let (lt, eq) = self.compare(a, b);
-        let or_gate = self.push_or(&lt, &eq);
-        self.push_not(&or_gate)
+        let outer = self.enter_operation("gt");
+        let le = self.le(a, b);
+        let output = self.push_not(&le);
+        self.operation = outer;
+        output
     }
 
     fn ge(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
+        let outer = self.enter_operation("ge");
         let lt = self.lt(a, b);
-        self.push_not(&lt)
+        let output = self.push_not(&lt);
+        self.operation = outer;
+        output
     }
 
     fn lt(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
+        let outer = self.enter_operation("lt");
         let (lt, _eq) = self.compare(a, b);
+        self.operation = outer;
         lt
     }
 
+    /// `a <= b` is `lt | eq` over the shared comparator flags. The OR gate is cached with
+    /// them, so `a > b` on the same operands only adds a NOT.
     fn le(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> GateIndex {
-        let gt = self.gt(a, b);
-        self.push_not(&gt)
+        let outer = self.enter_operation("le");
+        let (lt, eq) = self.compare(a, b);
+        let key = (a.clone(), b.clone(), self.signed);
+        if let Some(le) = self.comparisons[&key].le {
+            self.operation = outer;
+            return le;
+        }
+        let le = self.push_or(&lt, &eq);
+        self.comparisons.get_mut(&key).unwrap().le = Some(le);
+        self.operation = outer;
+        le
     }
 
+    /// Builds the comparator for `a` and `b` once; later comparisons of the same operands
+    /// reuse its `(lt, eq)` wires.
     fn compare(&mut self, a: &GateIndexVec, b: &GateIndexVec) -> (GateIndex, GateIndex) {
-        let mut eq_list = vec![0; a.len()];
-        let mut lt_list = vec![0; a.len()];
-
-        let i = a.len() - 1;
-        let eq_i = self.push_xnor(&a[i], &b[i]);
-        eq_list[i] = eq_i;
-
-        let nt = self.push_not(&a[i]);
-        let lt_i = self.push_and(&nt, &b[i]);
-        lt_list[i] = lt_i;
-
-        for idx in (0..i).rev() {
-            let xn = self.push_xnor(&a[idx], &b[idx]);
-            let eq_i = self.push_and(&eq_list[idx + 1], &xn);
-            eq_list[idx] = eq_i;
-
-            let nt = self.push_not(&a[idx]);
-            let aa = self.push_and(&nt, &b[idx]);
-            let temp_lt = self.push_and(&eq_list[idx + 1], &aa);
-            lt_list[idx] = self.push_or(&lt_list[idx + 1], &temp_lt);
+        let outer = self.enter_operation("compare");
+        let key = (a.clone(), b.clone(), self.signed);
+        if let Some(comparison) = self.comparisons.get(&key) {
+            self.operation = outer;
+            return (comparison.lt, comparison.eq);
         }
-
-        (lt_list[0], eq_list[0])
+        let (lt, eq) = self.compare_inner(a, b, self.signed);
+        self.comparisons
+            .insert(key, Comparison { lt, eq, le: None });
+        self.operation = outer;
+        (lt, eq)
     }
 }
 
@@ -530,6 +1886,23 @@ macro_rules! build_and_execute_shift {
 build_and_execute_shift!(build_and_execute_shl, shl);
 build_and_execute_shift!(build_and_execute_shr, shr);
 
+/// The number of bits needed to represent `n`.
+fn bit_width(n: usize) -> usize {
+    (usize::BITS - n.leading_zeros()) as usize
+}
+
+fn check_sum_operands(values: &[GateIndexVec]) {
+    assert!(!values.is_empty(), "sum needs at least one value");
+    let width = values[0].len();
+    if let Some(value) = values.iter().find(|value| value.len() != width) {
+        panic!(
+            "sum values must share one width, got {} and {} bits",
+            width,
+            value.len()
+        );
+    }
+}
+
 fn full_adder(
     builder: &mut WRK17CircuitBuilder,
     a: GateIndex,
@@ -547,12 +1920,10 @@ fn full_adder(
         xor_ab
     };
 
-    let and_ab = builder.len();
-    builder.gates.push(Gate::And(a, b));
+    let and_ab = builder.push_and(&a, &b);
 
     let new_carry = if let Some(c) = carry {
-        let and_axorb_c = builder.len();
-        builder.gates.push(Gate::And(xor_ab, c));
+        let and_axorb_c = builder.push_and(&xor_ab, &c);
 
         let or_gate = builder.len();
         builder.gates.push(Gate::Xor(and_ab, and_axorb_c));
@@ -610,9 +1981,7 @@ fn partial_product_shift(
     for i in 0..lhs.len() {
         if i < shift {
             // For the lower bits, we push a constant 0.
-            let zero_bit = builder.push_not(&rhs[0]);
-            let and_gate = builder.push_and(&rhs[0], &zero_bit); // Constant 0
-            shifted.push(and_gate);
+            shifted.push(builder.zero_wire());
         } else {
             let lhs_bit = lhs[i - shift];
             let and_gate = builder.push_and(&lhs_bit, &(rhs[shift]));
@@ -643,7 +2012,31 @@ pub(crate) fn build_and_execute_comparator<const N: usize>(
     lhs: &GarbledUint<N>,
     rhs: &GarbledUint<N>,
 ) -> Ordering {
-    let mut builder = WRK17CircuitBuilder::default();
+    execute_comparator(WRK17CircuitBuilder::default(), lhs, rhs)
+}
+
+pub(crate) fn build_and_execute_signed_comparator<const N: usize>(
+    lhs: &GarbledUint<N>,
+    rhs: &GarbledUint<N>,
+) -> Ordering {
+    execute_comparator(WRK17CircuitBuilder::signed(), lhs, rhs)
+}
+
+/// Formats the value of a wire group as a binary literal, most significant bit first.
+fn format_bits(bits: &[bool]) -> String {
+    let digits: String = bits
+        .iter()
+        .rev()
+        .map(|bit| if *bit { '1' } else { '0' })
+        .collect();
+    format!("0b{}", digits)
+}
+
+fn execute_comparator<const N: usize>(
+    mut builder: WRK17CircuitBuilder,
+    lhs: &GarbledUint<N>,
+    rhs: &GarbledUint<N>,
+) -> Ordering {
     let a = builder.input(lhs);
     let b = builder.input(rhs);
 
@@ -717,7 +2110,8 @@ mod tests {
 
     use super::*;
     use crate::uint::{
-        GarbledBit, GarbledUint128, GarbledUint16, GarbledUint32, GarbledUint64, GarbledUint8,
+        GarbledBit, GarbledUint128, GarbledUint16, GarbledUint32, GarbledUint4, GarbledUint64,
+        GarbledUint8,
     };
 
     #[test]
@@ -740,6 +2134,32 @@ mod tests {
         assert_eq!(result_value, 10 % 3);
     }
 
+    #[test]
+    fn test_div_rem_u8_sweep() {
+        for a in (0..=255_u8).step_by(5) {
+            for b in 0..=255_u8 {
+                let mut builder = WRK17CircuitBuilder::default();
+                let lhs = builder.input::<8>(&a.into());
+                let rhs = builder.input::<8>(&b.into());
+
+                // quotient and remainder come out of a single shared network
+                let (quotient, remainder) = builder.div_rem(&lhs, &rhs);
+                let mut output = quotient;
+                output.push_all(&remainder);
+
+                let result = builder.compile_and_execute::<16>(&output).unwrap();
+                let result: u16 = result.into();
+                let (quotient, remainder) = ((result & 0xff) as u8, (result >> 8) as u8);
+
+                if b == 0 {
+                    assert_eq!((quotient, remainder), (u8::MAX, a), "{a} / 0");
+                } else {
+                    assert_eq!((quotient, remainder), (a / b, a % b), "{a} / {b}");
+                }
+            }
+        }
+    }
+
     // Test several u8 (8-bit) cases.
     #[test]
     fn test_div_rem_u8_complex() {
@@ -929,6 +2349,22 @@ mod tests {
         assert_eq!(result, b);
     }
 
+    #[test]
+    fn test_mux_single_and_per_bit() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<32>(&0xdead_beef_u32.into());
+        let b = builder.input::<32>(&0x0123_4567_u32.into());
+        let s = builder.input::<1>(&true.into());
+
+        let predicted = builder.mux_lookahead(&a);
+        let output = builder.mux(&s[0], &a, &b);
+        assert_eq!(output, predicted);
+
+        let circuit = builder.compile(&output);
+        assert_eq!(circuit.and_gates(), 32);
+        assert_eq!(circuit.gates().len(), 65 + 3 * 32);
+    }
+
     #[test]
     fn test_build_and_execute_mux32() {
         let s: GarbledBoolean = true.into();
@@ -965,6 +2401,66 @@ mod tests {
         assert_eq!(result_value, 9 * 3);
     }
 
+    #[test]
+    fn test_mul_wrapping_sweep() {
+        for a in (0..=255_u8).step_by(7) {
+            for b in (0..=255_u8).step_by(11) {
+                let result = build_and_execute_multiplication::<8>(&a.into(), &b.into());
+                assert_eq!(u8::from(result), a.wrapping_mul(b), "{a} * {b}");
+            }
+        }
+
+        for (a, b) in [
+            (0_u16, 9_u16),
+            (300, 7),
+            (65535, 65535),
+            (4097, 33),
+            (256, 256),
+        ] {
+            let result = build_and_execute_multiplication::<16>(&a.into(), &b.into());
+            assert_eq!(u16::from(result), a.wrapping_mul(b), "{a} * {b}");
+        }
+
+        for (a, b) in [
+            (0_u32, 1_u32),
+            (123_456, 789),
+            (u32::MAX, 2),
+            (0x8000_0001, 0xffff_fffe),
+            (65_536, 65_536),
+        ] {
+            let result = build_and_execute_multiplication::<32>(&a.into(), &b.into());
+            assert_eq!(u32::from(result), a.wrapping_mul(b), "{a} * {b}");
+        }
+    }
+
+    #[test]
+    fn test_mul_constant_operands() {
+        let input: GarbledUint8 = 13_u8.into();
+
+        // multiplying by a constant zero yields constant-false wires without any gates
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&input);
+        let zero = builder.literal(0, 8);
+        let gates_before = builder.len();
+        let output = builder.mul(&a, &zero);
+        assert_eq!(builder.len(), gates_before);
+        assert_eq!(output, zero);
+
+        // multiplying by a constant power of two reduces to a shift
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&input);
+        let eight = builder.literal(8, 8);
+        let output = builder.mul(&a, &eight);
+        assert_eq!(output, builder.fixed_shl(&a, 3));
+        assert!(!builder
+            .gates
+            .iter()
+            .any(|gate| matches!(gate, Gate::And(_, _))));
+
+        let result: u8 = builder.compile_and_execute::<8>(&output).unwrap().into();
+        assert_eq!(result, 13 * 8);
+    }
+
     #[test]
     fn test_build_and_execute_shl() {
         let a: GarbledUint8 = 31_u8.into();
@@ -986,6 +2482,54 @@ mod tests {
         assert_eq!(shifted_val, 240 >> 3); // 0b0001_1110 = 30
     }
 
+    #[test]
+    fn test_shift_by_runtime_amount() {
+        for amount in 0..=20_u8 {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(0b1011_0110_u8));
+            let shift = builder.input(&GarbledUint8::from(amount));
+            let left = builder.shl_var(&a, &shift);
+            let right = builder.shr_var(&a, &shift);
+            let output = GateIndexVec::new(left.iter().chain(right.iter()).copied().collect());
+            let result: u16 = builder.compile_and_execute::<16>(&output).unwrap().into();
+
+            let (left, right) = (result as u8, (result >> 8) as u8);
+            if amount < 8 {
+                assert_eq!(left, 0b1011_0110_u8.wrapping_shl(amount.into()));
+                assert_eq!(right, 0b1011_0110_u8.wrapping_shr(amount.into()));
+            } else {
+                assert_eq!((left, right), (0, 0), "shift by {amount}");
+            }
+        }
+
+        // arithmetic shifts fill with the sign bit, also past the width
+        for amount in [0_u8, 3, 7, 8, 200] {
+            let mut builder = WRK17CircuitBuilder::signed();
+            let a = builder.input(&GarbledUint8::from(-100_i8 as u8));
+            let shift = builder.input(&GarbledUint8::from(amount));
+            let output = builder.shr_var(&a, &shift);
+            let result: i8 = builder.compile_and_execute::<8>(&output).unwrap().into();
+            assert_eq!(result, -100_i8 >> amount.min(7));
+        }
+
+        // three layers of one AND gate per bit, and one mux behind the OR of the top bits
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let shift = builder.input(&GarbledUint8::from(0_u8));
+        let output = builder.shl_var(&a, &shift);
+        assert_eq!(builder.compile(&output).and_gates(), 3 * 8 + 4 + 8);
+    }
+
+    #[test]
+    fn test_shift_u128_by_runtime_amount() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint128::from(1_u128));
+        let shift = builder.input(&GarbledUint128::from(100_u128));
+        let output = builder.shl(&a, &shift);
+        let result: u128 = builder.compile_and_execute::<128>(&output).unwrap().into();
+        assert_eq!(result, 1 << 100);
+    }
+
     #[test]
     fn test_eq_true() {
         let a: GarbledUint8 = 42_u8.into();
@@ -1100,6 +2644,25 @@ mod tests {
         assert!(!result_value);
     }
 
+    #[test]
+    fn test_signed_lt() {
+        let a: GarbledUint8 = (-42_i8).into();
+        let b: GarbledUint8 = 7_i8.into();
+
+        let mut builder = WRK17CircuitBuilder::signed();
+        let a = builder.input(&a);
+        let b = builder.input(&b);
+
+        let lt = builder.lt(&a, &b);
+        let gt = builder.gt(&a, &b);
+
+        let result = builder
+            .compile_and_execute::<2>(&vec![lt, gt].into())
+            .expect("Failed to execute signed comparison circuit");
+        assert!(result.bits[0]);
+        assert!(!result.bits[1]);
+    }
+
     #[test]
     fn test_build_and_execute_mixed() {
         fn build_and_execute_mixed<const N: usize>(
@@ -1203,17 +2766,17 @@ mod tests {
     #[test]
     fn test_constant_caching() {
         let mut builder = WRK17CircuitBuilder::default();
+        let var: GarbledUint32 = 7u32.into();
+        builder.input(&var);
 
         // Get constant 1 twice
         let one: GarbledUint32 = 1u32.into();
         let wire1 = builder.constant::<32>(&one);
         let wire2 = builder.constant::<32>(&one);
 
-        // Verify we got the same wire indices
+        // Verify we got the same wire indices, and no input was allocated for them
         assert_eq!(wire1, wire2);
-
-        // Verify the cache size is 1 (not 2)
-        assert_eq!(builder.constant_cache.len(), 1);
+        assert_eq!(builder.inputs().len(), 32);
     }
 
     #[test]
@@ -1241,4 +2804,1000 @@ mod tests {
         // Should be equal
         assert!(bool::from(result));
     }
+
+    #[test]
+    fn test_mul_constant() {
+        for a in [0_u8, 1, 7, 9, 200, 255] {
+            for value in [0_u8, 1, 2, 3, 10, 255] {
+                let mut builder = WRK17CircuitBuilder::default();
+                let input: GarbledUint8 = a.into();
+                let wires = builder.input(&input);
+                let output = builder.mul_constant(&wires, value as u128);
+
+                // the constant never becomes an input
+                assert_eq!(builder.inputs().len(), 8);
+
+                let result: u8 = builder.compile_and_execute::<8>(&output).unwrap().into();
+                assert_eq!(result, a.wrapping_mul(value));
+            }
+        }
+    }
+
+    #[test]
+    fn test_neg() {
+        for value in [0_i8, 1, -1, 42, -100, i8::MAX, i8::MIN] {
+            let mut builder = WRK17CircuitBuilder::signed();
+            let input: GarbledUint8 = value.into();
+            let wires = builder.input(&input);
+            let output = builder.neg(&wires);
+
+            let result: i8 = builder.compile_and_execute::<8>(&output).unwrap().into();
+            assert_eq!(result, value.wrapping_neg());
+        }
+    }
+
+    #[test]
+    fn test_input_array() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let values: [GarbledUint8; 3] = [3_u8.into(), 5_u8.into(), 7_u8.into()];
+        let [a, b, c] = builder.input_array(values);
+        assert_eq!(builder.inputs().len(), 24);
+        assert_eq!(a, GateIndexVec::new((0..8).collect()));
+
+        let sum = builder.add(&a, &b);
+        let sum = builder.add(&sum, &c);
+        let result: u8 = builder.compile_and_execute::<8>(&sum).unwrap().into();
+        assert_eq!(result, 15);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let input: GarbledUint8 = 0x85_u8.into();
+        let wires = builder.input(&input);
+
+        let widened = builder.resize(&wires, 16, false);
+        let result: u16 = builder.compile_and_execute::<16>(&widened).unwrap().into();
+        assert_eq!(result, 0x0085);
+
+        let widened = builder.resize(&wires, 16, true);
+        let result: u16 = builder.compile_and_execute::<16>(&widened).unwrap().into();
+        assert_eq!(result, 0xff85);
+
+        let narrowed = builder.resize(&wires, 4, false);
+        let result: u8 = builder.compile_and_execute::<4>(&narrowed).unwrap().into();
+        assert_eq!(result, 0x5);
+    }
+
+    #[test]
+    fn test_add_constant() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let input: GarbledUint8 = 250_u8.into();
+        let wires = builder.input(&input);
+        let output = builder.add_constant(&wires, 10);
+        assert_eq!(builder.inputs().len(), 8);
+
+        let result: u8 = builder.compile_and_execute::<8>(&output).unwrap().into();
+        assert_eq!(result, 250_u8.wrapping_add(10));
+    }
+
+    #[test]
+    fn test_constant_mask() {
+        use crate::operations::circuits::optimize::CircuitOptimizer;
+
+        let mut builder = WRK17CircuitBuilder::default();
+        let input: GarbledUint8 = 0b1011_0110_u8.into();
+        let wires = builder.input(&input);
+        let mask = builder.constant_u128::<8>(0x0F);
+        let output = builder.and(&wires, &mask);
+        assert_eq!(builder.inputs().len(), 8);
+
+        let mut circuit = builder.compile(&output);
+        let result: u8 = builder.execute::<8>(&circuit).unwrap().into();
+        assert_eq!(result, 0b0000_0110);
+
+        // every AND gate folds against the constant wires
+        circuit.optimize_constants();
+        assert_eq!(circuit.and_gates(), 0);
+        let result: u8 = builder.execute::<8>(&circuit).unwrap().into();
+        assert_eq!(result, 0b0000_0110);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit in 4 bits")]
+    fn test_constant_overflow() {
+        let mut builder = WRK17CircuitBuilder::default();
+        builder.constant_u128::<4>(0x10);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let input: GarbledUint8 = 0b1001_0110_u8.into();
+        let wires = builder.input(&input);
+        let gates = builder.len();
+
+        let left = builder.rotate_left(&wires, 3);
+        let right = builder.rotate_right(&wires, 11);
+        assert_eq!(builder.len(), gates);
+
+        let result: u8 = builder.compile_and_execute::<8>(&left).unwrap().into();
+        assert_eq!(result, 0b1001_0110_u8.rotate_left(3));
+        let result: u8 = builder.compile_and_execute::<8>(&right).unwrap().into();
+        assert_eq!(result, 0b1001_0110_u8.rotate_right(11));
+    }
+
+    #[test]
+    fn test_concat_and_split() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let low = builder.input(&GarbledUint8::from(0x34_u8));
+        let high = builder.input(&GarbledUint8::from(0x12_u8));
+        let gates = builder.len();
+
+        let wide = builder.concat(&[low.clone(), high.clone()]);
+        let parts = builder.split(&wide, &[8, 8]);
+        assert_eq!(builder.len(), gates);
+        assert_eq!(parts, vec![low, high]);
+
+        // the first group is the low byte
+        let result: u16 = builder.compile_and_execute::<16>(&wide).unwrap().into();
+        assert_eq!(result, 0x1234);
+        let result: u8 = builder.compile_and_execute::<8>(&parts[1]).unwrap().into();
+        assert_eq!(result, 0x12);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot split 16 bits into groups of [8, 4] bits")]
+    fn test_split_width_mismatch() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let wires = builder.input(&GarbledUint16::from(0x1234_u16));
+        builder.split(&wires, &[8, 4]);
+    }
+
+    #[test]
+    fn test_bit() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let wires = builder.input(&GarbledUint8::from(0b1000_0100_u8));
+        let gates = builder.len();
+
+        let bits = GateIndexVec::new((0..8).map(|i| builder.bit(&wires, i)).collect());
+        assert_eq!(builder.len(), gates);
+        assert_eq!(bits, wires);
+    }
+
+    #[test]
+    fn test_field() {
+        struct Reading;
+        impl CircuitInput for Reading {
+            const WIDTH: usize = 13;
+            const FIELDS: &'static [(&'static str, usize, bool)] =
+                &[("valid", 1, false), ("delta", 4, true), ("level", 8, false)];
+        }
+
+        // valid = 1, delta = -3, level = 200
+        let mut builder = WRK17CircuitBuilder::default();
+        let record = builder.input(&GarbledUint16::from(1_u16 | (0b1101 << 1) | (200 << 5)));
+        let valid = builder.field::<Reading>(&record, "valid", 1);
+        let delta = builder.field::<Reading>(&record, "delta", 8);
+        let level = builder.field::<Reading>(&record, "level", 8);
+        assert_eq!(delta.as_slice()[..4], record.as_slice()[1..5]);
+
+        let output = builder.concat(&[valid, delta, level]);
+        let bits = builder.execute_bits(&builder.compile(&output)).unwrap();
+        assert!(bits[0]);
+        assert_eq!(u8::from(GarbledUint8::new(bits[1..9].to_vec())) as i8, -3);
+        assert_eq!(u8::from(GarbledUint8::new(bits[9..].to_vec())), 200);
+    }
+
+    #[test]
+    fn test_comparisons_share_comparator() {
+        let mut single = WRK17CircuitBuilder::default();
+        let a = single.input(&GarbledUint8::from(23_u8));
+        let b = single.input(&GarbledUint8::from(42_u8));
+        let lt = single.lt(&a, &b);
+        let single_ands = single.compile(&lt.into()).and_gates();
+
+        for (x, y) in [(23_u8, 42_u8), (42, 23), (42, 42)] {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(x));
+            let b = builder.input(&GarbledUint8::from(y));
+            let relations = vec![
+                builder.gt(&a, &b),
+                builder.ge(&a, &b),
+                builder.lt(&a, &b),
+                builder.le(&a, &b),
+                builder.eq(&a, &b),
+            ];
+            let circuit = builder.compile(&relations.into());
+
+            // one comparator plus a single OR gate for `<=` and `>`
+            assert_eq!(circuit.and_gates(), single_ands + 1);
+            assert!(circuit.and_gates() < 4 * single_ands);
+
+            let result = builder.execute_bits(&circuit).unwrap();
+            assert_eq!(result, vec![x > y, x >= y, x < y, x <= y, x == y]);
+
+            // `min` and `max` reuse the comparator and add a one-AND-per-bit mux each
+            let min = builder.min(&a, &b);
+            let max = builder.max(&a, &b);
+            let circuit = builder.compile(&max);
+            assert_eq!(circuit.and_gates(), single_ands + 1 + 2 * 8);
+            let result = GarbledUint8::new(builder.execute_bits(&circuit).unwrap());
+            assert_eq!(u8::from(result), x.max(y));
+
+            let result: u8 = builder.compile_and_execute::<8>(&min).unwrap().into();
+            assert_eq!(result, x.min(y));
+        }
+    }
+
+    #[test]
+    fn test_saturating_u8_sweep() {
+        let values = [0_u8, 1, 2, 100, 127, 128, 200, 254, 255];
+        for x in values {
+            for y in values {
+                let mut builder = WRK17CircuitBuilder::default();
+                let a = builder.input(&GarbledUint8::from(x));
+                let b = builder.input(&GarbledUint8::from(y));
+                let sum = builder.saturating_add(&a, &b);
+                let diff = builder.saturating_sub(&a, &b);
+
+                let result: u8 = builder.compile_and_execute::<8>(&sum).unwrap().into();
+                assert_eq!(result, x.saturating_add(y), "{x} + {y}");
+                let result: u8 = builder.compile_and_execute::<8>(&diff).unwrap().into();
+                assert_eq!(result, x.saturating_sub(y), "{x} - {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_gate_budget() {
+        let mut builder = WRK17CircuitBuilder::with_gate_budget(20);
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let b = builder.input(&GarbledUint8::from(5_u8));
+
+        // an 8-bit adder needs 15 AND gates, well within the budget
+        let sum = builder.add(&a, &b);
+        assert!(builder.budget_error().is_none());
+        assert!(builder.try_compile(&sum).is_ok());
+
+        // the multiplier's internal adders push it over, but `mul` is the operation reported
+        let product = builder.mul(&sum, &b);
+        let error = builder.try_compile(&product).unwrap_err();
+        assert_eq!(
+            error,
+            CompileError::GateBudgetExceeded(GateBudgetExceeded {
+                operation: "mul",
+                and_gates: 21,
+                budget: 20,
+            })
+        );
+        assert_eq!(
+            error.to_string(),
+            "`mul` exceeded the AND-gate budget of 20 (21 AND gates)"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "`and` exceeded the AND-gate budget of 4 (5 AND gates)")]
+    fn test_gate_budget_compile_panics() {
+        let mut builder = WRK17CircuitBuilder::with_gate_budget(4);
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let b = builder.input(&GarbledUint8::from(5_u8));
+        let output = builder.and(&a, &b);
+
+        assert!(builder.try_compile(&output).is_err());
+        builder.compile(&output);
+    }
+
+    fn execute_count(builder: &WRK17CircuitBuilder, count: &GateIndexVec) -> u32 {
+        let circuit = builder.compile(count);
+        let bits = builder.execute_bits(&circuit).unwrap();
+        bits.iter()
+            .rev()
+            .fold(0, |value, bit| (value << 1) | *bit as u32)
+    }
+
+    #[test]
+    fn test_popcount_u8_sweep() {
+        for x in 0..=u8::MAX {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(x));
+            let count = builder.popcount(&a);
+
+            // popcount of a u8 may be 8, which needs 4 bits
+            assert_eq!(count.len(), 4);
+            assert_eq!(execute_count(&builder, &count), x.count_ones(), "{x:#010b}");
+        }
+    }
+
+    #[test]
+    fn test_popcount_u32() {
+        for x in [0_u32, 1, 0x8000_0001, 0xdead_beef, 0x7fff_ffff, u32::MAX] {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint32::from(x));
+            let count = builder.popcount(&a);
+
+            assert_eq!(count.len(), 6);
+            assert_eq!(execute_count(&builder, &count), x.count_ones(), "{x:#x}");
+        }
+    }
+
+    #[test]
+    fn test_leading_trailing_zeros_u8_sweep() {
+        for x in 0..=u8::MAX {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(x));
+            let leading = builder.leading_zeros(&a);
+            let trailing = builder.trailing_zeros(&a);
+
+            assert_eq!(leading.len(), 4);
+            assert_eq!(trailing.len(), 4);
+            assert_eq!(
+                execute_count(&builder, &leading),
+                x.leading_zeros(),
+                "{x:#010b}"
+            );
+            assert_eq!(
+                execute_count(&builder, &trailing),
+                x.trailing_zeros(),
+                "{x:#010b}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_zero_counts_of_zero_are_width() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint16::from(0_u16));
+        let leading = builder.leading_zeros(&a);
+        let trailing = builder.trailing_zeros(&a);
+
+        assert_eq!(execute_count(&builder, &leading), 16);
+        assert_eq!(execute_count(&builder, &trailing), 16);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut builder =
+            WRK17CircuitBuilder::with_gate_budget(100).with_adder(AdderStrategy::KoggeStone);
+        let a = builder.input(&GarbledUint8::from(200_u8));
+        let b = builder.input(&GarbledUint8::from(100_u8));
+        let sum = builder.add(&a, &b);
+        builder.lt(&a, &b);
+        builder.name(&sum, "sum");
+        builder.assert_eq(&a, &a);
+        builder.mul(&a, &b);
+        assert!(builder.budget_error().is_some());
+        let capacity = builder.gates.capacity();
+
+        builder.reset();
+        assert!(builder.is_empty());
+        assert!(builder.inputs().is_empty());
+        assert!(builder.input_layout().is_empty());
+        assert!(builder.wire_names().is_empty());
+        assert_eq!(builder.assertions(), 0);
+        assert!(builder.budget_error().is_none());
+        assert_eq!(builder.adder(), AdderStrategy::KoggeStone);
+        assert_eq!(builder.gates.capacity(), capacity);
+
+        // the second circuit is built exactly as on a fresh builder, constants included
+        let build = |builder: &mut WRK17CircuitBuilder| {
+            let a = builder.input(&GarbledUint8::from(6_u8));
+            let b = builder.input(&GarbledUint8::from(6_u8));
+            let five = builder.literal(5, 8);
+            let sum = builder.add(&a, &five);
+            let eq = builder.eq(&a, &b);
+            let mut output = sum;
+            output.push(eq);
+            output
+        };
+        let output = build(&mut builder);
+        let mut fresh =
+            WRK17CircuitBuilder::with_gate_budget(100).with_adder(AdderStrategy::KoggeStone);
+        let fresh_output = build(&mut fresh);
+        assert_eq!(output, fresh_output);
+        assert_eq!(builder.gates, fresh.gates);
+        assert_eq!(builder.inputs(), fresh.inputs());
+
+        let result: u16 = builder.compile_and_execute::<9>(&output).unwrap().into();
+        assert_eq!(result, 11 | 1 << 8);
+    }
+
+    #[test]
+    fn test_input_layout() {
+        let mut builder = WRK17CircuitBuilder::default();
+        builder.input(&GarbledUint8::from(1_u8));
+        builder.name_input("a");
+        builder.input_array([GarbledUint16::from(2_u16), GarbledUint16::from(3_u16)]);
+        builder.input(&GarbledUint8::from(4_u8));
+
+        assert_eq!(
+            builder.input_layout(),
+            [
+                InputSpec {
+                    name: Some("a".to_string()),
+                    width: 8,
+                    wires: 0..8,
+                },
+                InputSpec {
+                    name: None,
+                    width: 32,
+                    wires: 8..40,
+                },
+                InputSpec {
+                    name: None,
+                    width: 8,
+                    wires: 40..48,
+                },
+            ]
+        );
+
+        // a constant in an empty builder is backed by a false input
+        let mut builder = WRK17CircuitBuilder::default();
+        builder.literal(7, 8);
+        builder.input(&GarbledUint8::from(4_u8));
+        builder.name_input("a");
+        assert!(!builder.inputs()[0]);
+        assert_eq!(
+            builder.input_layout(),
+            [
+                InputSpec {
+                    name: None,
+                    width: 1,
+                    wires: 0..1,
+                },
+                InputSpec {
+                    name: Some("a".to_string()),
+                    width: 8,
+                    wires: 1..9,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wire_names() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0b0000_1111_u8));
+        let b = builder.input(&GarbledUint8::from(0b0000_0001_u8));
+        let (sum, carry) = builder.add_with_carry(&a, &b);
+        let gates = builder.len();
+        builder.name(&GateIndexVec::from(carry), "carry");
+        builder.name(&sum, "sum");
+        assert_eq!(builder.len(), gates);
+        assert_eq!(builder.wire_names()[&carry], "carry");
+        assert_eq!(builder.wire_names()[&sum[4]], "sum[4]");
+
+        let circuit = builder.compile(&sum);
+        let wires = circuit.trace(builder.inputs()).unwrap();
+        let named: Vec<(&str, bool)> = builder
+            .wire_names()
+            .iter()
+            .filter(|(_, name)| name.starts_with("sum"))
+            .map(|(wire, name)| (name.as_str(), wires[*wire as usize]))
+            .collect();
+        assert!(named.contains(&("sum[4]", true)));
+        assert!(named.contains(&("sum[0]", false)));
+
+        // names follow their wires into a merged builder
+        let mut outer = WRK17CircuitBuilder::default();
+        outer.input(&GarbledUint8::from(0_u8));
+        let merged = outer.merge(builder, &sum);
+        assert_eq!(outer.wire_names()[&merged[4]], "sum[4]");
+    }
+
+    #[test]
+    fn test_abs_i8_sweep() {
+        for value in i8::MIN..=i8::MAX {
+            let mut builder = WRK17CircuitBuilder::signed();
+            let input: GarbledUint8 = value.into();
+            let wires = builder.input(&input);
+            let output = builder.abs(&wires);
+
+            let result: i8 = builder.compile_and_execute::<8>(&output).unwrap().into();
+            assert_eq!(result, value.wrapping_abs(), "abs({value})");
+        }
+    }
+
+    #[test]
+    fn test_min_max_signed() {
+        for (x, y) in [(-3_i8, 2_i8), (2, -3), (i8::MIN, i8::MAX), (-7, -7)] {
+            let mut builder = WRK17CircuitBuilder::signed();
+            let a = builder.input(&GarbledUint8::from(x));
+            let b = builder.input(&GarbledUint8::from(y));
+            let min = builder.min(&a, &b);
+            let max = builder.max(&a, &b);
+
+            let result: i8 = builder.compile_and_execute::<8>(&min).unwrap().into();
+            assert_eq!(result, x.min(y));
+            let result: i8 = builder.compile_and_execute::<8>(&max).unwrap().into();
+            assert_eq!(result, x.max(y));
+        }
+    }
+
+    #[test]
+    fn test_width_conversions_are_rewiring() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0b1010_0101_u8));
+        let zero = builder.zero_wire();
+        let gates = builder.len();
+
+        let extended = builder.zero_extend::<16>(&a);
+        let signed = builder.sign_extend::<16>(&a);
+        let truncated = builder.truncate::<4>(&a);
+        assert_eq!(builder.len(), gates);
+
+        // the upper half is the constant-false wire
+        assert_eq!(extended.len(), 16);
+        assert!((8..16).all(|i| extended[i] == zero));
+        assert!((8..16).all(|i| signed[i] == a[7]));
+        assert_eq!(truncated, GateIndexVec::new((0..4).map(|i| a[i]).collect()));
+
+        let result: u16 = builder.compile_and_execute::<16>(&extended).unwrap().into();
+        assert_eq!(result, 0b1010_0101);
+        let result: u16 = builder.compile_and_execute::<16>(&signed).unwrap().into();
+        assert_eq!(result, 0xffa5);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot truncate 8 bits to 16")]
+    fn test_truncate_wider_panics() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(1_u8));
+        builder.truncate::<16>(&a);
+    }
+
+    #[test]
+    fn test_select_among_constants() {
+        let table = [11_u128, 22, 33, 44];
+        for index in [0_u8, 1, 2, 3, 4, 200] {
+            let mut builder = WRK17CircuitBuilder::default();
+            let index_wires = builder.input(&GarbledUint8::from(index));
+            let entries: Vec<GateIndexVec> = table
+                .iter()
+                .map(|value| builder.literal(*value, 8))
+                .collect();
+            let selected = builder.select(&index_wires, &entries);
+
+            let result: u8 = builder.compile_and_execute::<8>(&selected).unwrap().into();
+            // indices past the end select the last entry
+            let expected = table[(index as usize).min(table.len() - 1)];
+            assert_eq!(result as u128, expected, "index {index}");
+        }
+    }
+
+    #[test]
+    fn test_select_uneven_table() {
+        for index in 0_u8..8 {
+            let mut builder = WRK17CircuitBuilder::default();
+            let index_wires = builder.input(&GarbledUint4::from(index));
+            let entries: Vec<GateIndexVec> = (0..5).map(|i| builder.literal(i * 10, 8)).collect();
+            let selected = builder.select(&index_wires, &entries);
+
+            let result: u8 = builder.compile_and_execute::<8>(&selected).unwrap().into();
+            assert_eq!(result, index.min(4) * 10, "index {index}");
+        }
+    }
+
+    #[test]
+    fn test_sum_tree() {
+        use crate::operations::circuits::stats::CircuitStats;
+
+        let values = [200_u8, 17, 255, 3, 99, 128, 64, 1];
+        let mut tree = WRK17CircuitBuilder::default();
+        let wires: Vec<GateIndexVec> = values
+            .iter()
+            .map(|value| tree.input(&GarbledUint8::from(*value)))
+            .collect();
+        let wrapped = tree.sum(&wires);
+        let result: u8 = tree.compile_and_execute::<8>(&wrapped).unwrap().into();
+        let expected = values
+            .iter()
+            .fold(0_u8, |sum, value| sum.wrapping_add(*value));
+        assert_eq!(result, expected);
+
+        let wide = tree.sum_wide(&wires);
+        assert_eq!(wide.len(), 11);
+        let result: u16 = tree.compile_and_execute::<11>(&wide).unwrap().into();
+        assert_eq!(
+            result,
+            values.iter().map(|value| *value as u16).sum::<u16>()
+        );
+
+        // the same sum as a chain of additions
+        let mut chain = WRK17CircuitBuilder::default();
+        let wires: Vec<GateIndexVec> = values
+            .iter()
+            .map(|value| chain.input(&GarbledUint8::from(*value)))
+            .collect();
+        let mut linear = wires[0].clone();
+        for value in &wires[1..] {
+            linear = chain.add(&linear, value);
+        }
+        let result: u8 = chain.compile_and_execute::<8>(&linear).unwrap().into();
+        assert_eq!(result, expected);
+        assert!(tree.compile(&wrapped).depth() < chain.compile(&linear).depth());
+    }
+
+    #[test]
+    fn test_sum_wide_signed() {
+        let values = [-128_i8, -128, -128, 127, -1];
+        let mut builder = WRK17CircuitBuilder::signed();
+        let wires: Vec<GateIndexVec> = values
+            .iter()
+            .map(|value| builder.input(&GarbledUint8::from(*value as u8)))
+            .collect();
+        let wide = builder.sum_wide(&wires);
+        assert_eq!(wide.len(), 11);
+        let bits = builder.execute_bits(&builder.compile(&wide)).unwrap();
+        let result = bits
+            .iter()
+            .enumerate()
+            .fold(0_i16, |sum, (i, bit)| sum | ((*bit as i16) << i));
+        // sign-extend the 11-bit result
+        let result = (result << 5) >> 5;
+        assert_eq!(
+            result,
+            values.iter().map(|value| *value as i16).sum::<i16>()
+        );
+    }
+
+    #[test]
+    fn test_mux_array_swaps_pair() {
+        for swap in [false, true] {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(0x12_u8));
+            let b = builder.input(&GarbledUint8::from(0xab_u8));
+            let condition = builder.input(&GarbledBit::from(swap));
+            let ands = builder.and_gates;
+            let pair = builder.mux_array(&condition[0], &[b.clone(), a.clone()], &[a, b]);
+            assert_eq!(builder.and_gates, ands + 16);
+
+            let mut output = pair[0].clone();
+            output.push_all(&pair[1]);
+            let result: u16 = builder.compile_and_execute::<16>(&output).unwrap().into();
+            let expected = if swap { (0xab, 0x12) } else { (0x12, 0xab) };
+            assert_eq!(((result & 0xff) as u8, (result >> 8) as u8), expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "mux_array element 1 has 8 bits if true and 16 bits if false")]
+    fn test_mux_array_width_mismatch_panics() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let condition = builder.input(&GarbledBit::from(true));
+        let if_true = [builder.literal(1, 8), builder.literal(2, 8)];
+        let if_false = [builder.literal(3, 8), builder.literal(4, 16)];
+        builder.mux_array(&condition[0], &if_true, &if_false);
+    }
+
+    #[test]
+    #[should_panic(expected = "select entries must share one width, got 8 and 16 bits")]
+    fn test_select_width_mismatch_panics() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let index = builder.input(&GarbledUint8::from(0_u8));
+        let entries = [builder.literal(1, 8), builder.literal(2, 16)];
+        builder.select(&index, &entries);
+    }
+
+    #[test]
+    fn test_compile_output_out_of_bounds() {
+        let mut builder = WRK17CircuitBuilder::default();
+        builder.input(&GarbledUint8::from(3_u8));
+
+        assert_eq!(
+            builder
+                .try_compile(&GateIndexVec::new(vec![0, 8]))
+                .unwrap_err(),
+            CompileError::OutputOutOfBounds { wire: 8, gates: 8 }
+        );
+    }
+
+    #[test]
+    fn test_add_fast_depth() {
+        use crate::operations::circuits::stats::CircuitStats;
+
+        let depth_of = |adder: AdderStrategy| {
+            let mut builder = WRK17CircuitBuilder::default().with_adder(adder);
+            let a = builder.input(&GarbledUint64::from(0_u64));
+            let b = builder.input(&GarbledUint64::from(0_u64));
+            let sum = builder.add(&a, &b);
+            builder.compile(&sum).depth()
+        };
+        let ripple = depth_of(AdderStrategy::RippleCarry);
+        let kogge_stone = depth_of(AdderStrategy::KoggeStone);
+        // the carry passes through two gates per bit, or two per prefix round
+        assert!(ripple >= 2 * 63, "ripple-carry depth {}", ripple);
+        assert!(
+            kogge_stone <= 2 * 6 + 2,
+            "Kogge-Stone depth {}",
+            kogge_stone
+        );
+
+        for (x, y) in [
+            (0_u64, 0_u64),
+            (1, u64::MAX),
+            (0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210),
+            (u64::MAX, u64::MAX),
+        ] {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint64::from(x));
+            let b = builder.input(&GarbledUint64::from(y));
+            let fast = builder.add_fast(&a, &b);
+            let ripple = builder.ripple_carry_add(&a, &b);
+            let result: GarbledUint64 = builder.execute(&builder.compile(&fast)).unwrap();
+            assert_eq!(u64::from(result), x.wrapping_add(y));
+            let result: GarbledUint64 = builder.execute(&builder.compile(&ripple)).unwrap();
+            assert_eq!(u64::from(result), x.wrapping_add(y));
+        }
+
+        // operations built on the adder follow the strategy
+        let mut builder = WRK17CircuitBuilder::default().with_adder(AdderStrategy::KoggeStone);
+        let a = builder.input(&GarbledUint8::from(250_u8));
+        let b = builder.input(&GarbledUint8::from(10_u8));
+        let sum = builder.saturating_add(&a, &b);
+        let result: GarbledUint8 = builder.execute(&builder.compile(&sum)).unwrap();
+        assert_eq!(u8::from(result), 255);
+    }
+
+    #[test]
+    fn test_eq_constant() {
+        for value in 0..16_u8 {
+            for constant in [0_u128, 3, 9, 15] {
+                let mut builder = WRK17CircuitBuilder::default();
+                let a = builder.input(&GarbledUint4::from(value));
+                let output = builder.eq_constant(&a, constant);
+                let result: bool = builder
+                    .compile_and_execute::<1>(&GateIndexVec::from(output))
+                    .unwrap()
+                    .into();
+                assert_eq!(result, value as u128 == constant);
+            }
+        }
+
+        // no XOR gates, against one XNOR per bit for a general comparison
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let gates = builder.len();
+        builder.eq_constant(&a, 3);
+        let constant_gates = builder.len() - gates;
+        let b = builder.input(&GarbledUint8::from(3_u8));
+        let gates = builder.len();
+        builder.eq(&a, &b);
+        assert!(constant_gates < builder.len() - gates);
+    }
+
+    #[test]
+    fn test_is_zero() {
+        for value in (0..=255_u8).step_by(7).chain([0, 1, 128, 255]) {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(value));
+            let zero = builder.is_zero(&a);
+            let nonzero = builder.is_nonzero(&a);
+            let result: GarbledUint<2> = builder
+                .compile_and_execute(&GateIndexVec::new(vec![zero, nonzero]))
+                .unwrap();
+            let result: u8 = result.into();
+            assert_eq!(result & 1 == 1, value == 0, "is_zero({value})");
+            assert_eq!(result >> 1 == 1, value != 0, "is_nonzero({value})");
+        }
+
+        // one AND gate per OR, so a u8 costs seven
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let output = builder.is_zero(&a);
+        let circuit = builder.compile(&GateIndexVec::from(output));
+        assert_eq!(circuit.and_gates(), 7);
+    }
+
+    #[test]
+    fn test_in_range() {
+        for (lo, hi) in [(10, 200), (0, 255), (42, 42), (200, 10)] {
+            for value in 0..=255_u8 {
+                let mut builder = WRK17CircuitBuilder::default();
+                let a = builder.input(&GarbledUint8::from(value));
+                let valid = builder.in_range(&a, lo, hi);
+                let result: bool = builder
+                    .compile_and_execute::<1>(&GateIndexVec::from(valid))
+                    .unwrap()
+                    .into();
+                let expected = (lo..=hi).contains(&(value as u128));
+                assert_eq!(result, expected, "{lo} <= {value} <= {hi}");
+            }
+        }
+
+        // signed builders compare two's complement values
+        let mut builder = WRK17CircuitBuilder::signed();
+        let a = builder.input(&GarbledUint8::from(-3_i8));
+        let valid = builder.in_range(&a, (-5_i8) as u8 as u128, 5);
+        let result: bool = builder
+            .compile_and_execute::<1>(&GateIndexVec::from(valid))
+            .unwrap()
+            .into();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_compile_dangling_wire() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let dangling = builder.push_and(&a[0], &42);
+
+        let error = builder
+            .try_compile(&GateIndexVec::new(vec![dangling]))
+            .unwrap_err();
+        assert_eq!(error, CompileError::UnassignedWire { gate: 8, wire: 42 });
+        assert_eq!(
+            error.to_string(),
+            "gate 8 reads wire 42, which is not assigned before it"
+        );
+
+        let mut builder = WRK17CircuitBuilder::default();
+        builder.input(&GarbledUint8::from(3_u8));
+        assert_eq!(
+            builder.try_compile(&GateIndexVec::default()).unwrap_err(),
+            CompileError::EmptyOutput
+        );
+        // `compile` does not validate, so it does not panic either
+        let circuit = builder.compile(&GateIndexVec::default());
+        assert!(circuit.output_gates().is_empty());
+    }
+
+    #[test]
+    fn test_bit_level_full_adder() {
+        for bits in 0..8_u8 {
+            let (a, b, c) = (bits & 1 == 1, bits & 2 == 2, bits & 4 == 4);
+            let mut builder = WRK17CircuitBuilder::default();
+            let a_wire = builder.input(&GarbledBit::from(a)).get(0).unwrap();
+            let b_wire = builder.input(&GarbledBit::from(b)).get(0).unwrap();
+            let c_wire = builder.input(&GarbledBit::from(c)).get(0).unwrap();
+
+            let half = builder.xor_bit(a_wire, b_wire);
+            let sum = builder.xor_bit(half, c_wire);
+            let generate = builder.and_bit(a_wire, b_wire);
+            let propagate = builder.and_bit(half, c_wire);
+            let carry = builder.or_bit(generate, propagate);
+            let no_carry = builder.not_bit(carry);
+
+            let output = GateIndexVec::new(vec![sum, carry, no_carry]);
+            let result = builder.execute_bits(&builder.compile(&output)).unwrap();
+            let total = a as u8 + b as u8 + c as u8;
+            assert_eq!(result, [total & 1 == 1, total >= 2, total < 2]);
+        }
+    }
+
+    #[test]
+    fn test_merge_fragments() {
+        let mut left = WRK17CircuitBuilder::default();
+        let a = left.input(&GarbledUint8::from(3_u8));
+        let b = left.input(&GarbledUint8::from(4_u8));
+        let sum = left.add(&a, &b);
+        let left_ands = left.compile(&sum).and_gates();
+
+        let mut right = WRK17CircuitBuilder::default();
+        let c = right.input(&GarbledUint8::from(5_u8));
+        let d = right.input(&GarbledUint8::from(6_u8));
+        let product = right.mul(&c, &d);
+        let right_ands = right.compile(&product).and_gates();
+
+        let product = left.merge(right, &product);
+        assert_eq!(
+            left.input_layout()
+                .iter()
+                .map(|spec| spec.wires.clone())
+                .collect::<Vec<_>>(),
+            vec![0..8, 8..16, 16..24, 24..32]
+        );
+        let output = left.xor(&sum, &product);
+        let circuit = left.compile(&output);
+        assert_eq!(circuit.and_gates(), left_ands + right_ands);
+
+        let result: u8 = left.execute::<8>(&circuit).unwrap().into();
+        assert_eq!(result, 7 ^ 30);
+    }
+
+    #[test]
+    fn test_checked_u8_sweep() {
+        for x in (0..=255_u8).step_by(15) {
+            for y in (0..=255_u8).step_by(17) {
+                let mut builder = WRK17CircuitBuilder::default();
+                let a = builder.input(&GarbledUint8::from(x));
+                let b = builder.input(&GarbledUint8::from(y));
+                for (expected, checked) in [
+                    (x.overflowing_add(y), builder.checked_add(&a, &b)),
+                    (x.overflowing_sub(y), builder.checked_sub(&a, &b)),
+                ] {
+                    let (value, overflow) = checked;
+                    let mut output = value;
+                    output.push(overflow);
+                    let circuit = builder.compile(&output);
+                    let bits = builder.execute_bits(&circuit).unwrap();
+                    let result: u8 = GarbledUint8::new(bits[..8].to_vec()).into();
+                    assert_eq!((result, bits[8]), expected, "{x}, {y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_checked_i8_sweep() {
+        for x in (-128..=127_i8).step_by(15) {
+            for y in (-128..=127_i8).step_by(17) {
+                let mut builder = WRK17CircuitBuilder::signed();
+                let a = builder.input(&GarbledUint8::from(x));
+                let b = builder.input(&GarbledUint8::from(y));
+                for (expected, checked) in [
+                    (x.overflowing_add(y), builder.checked_add(&a, &b)),
+                    (x.overflowing_sub(y), builder.checked_sub(&a, &b)),
+                ] {
+                    let (value, overflow) = checked;
+                    let mut output = value;
+                    output.push(overflow);
+                    let circuit = builder.compile(&output);
+                    let bits = builder.execute_bits(&circuit).unwrap();
+                    let result: i8 = GarbledUint8::new(bits[..8].to_vec()).into();
+                    assert_eq!((result, bits[8]), expected, "{x}, {y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pow2_div_rem() {
+        for shift in 0..=9 {
+            let mut builder = WRK17CircuitBuilder::default();
+            let a = builder.input(&GarbledUint8::from(0b1011_0110_u8));
+            let ands = builder.and_gates;
+            let quotient = builder.div_pow2(&a, shift);
+            let remainder = builder.rem_pow2(&a, shift);
+            assert_eq!(builder.and_gates, ands);
+
+            let divisor = 1_u16 << shift;
+            let result: u8 = builder.compile_and_execute::<8>(&quotient).unwrap().into();
+            assert_eq!(result as u16, 0b1011_0110 / divisor, "/ 2^{shift}");
+            let result: u8 = builder.compile_and_execute::<8>(&remainder).unwrap().into();
+            assert_eq!(result as u16, 0b1011_0110 % divisor, "% 2^{shift}");
+        }
+    }
+
+    #[test]
+    fn test_assert_eq_adds_no_gates() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(12_u8));
+        let b = builder.input(&GarbledUint8::from(30_u8));
+        let sum = builder.add(&a, &b);
+        let gates = builder.gates.len();
+
+        let expected = builder.input(&GarbledUint8::from(42_u8));
+        let gates_with_expected = builder.gates.len();
+        builder.assert_eq(&sum, &expected);
+        assert_eq!(builder.gates.len(), gates_with_expected);
+        assert_eq!(gates_with_expected, gates + 8);
+        assert_eq!(builder.assertions(), 1);
+
+        let circuit = builder.compile(&sum);
+        assert_eq!(circuit.output_gates().len(), 8);
+        let result: u8 = builder.execute::<8>(&circuit).unwrap().into();
+        assert_eq!(result, 42);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "circuit assertion 1 failed: left = 0b00101010, right = 0b00101011")]
+    fn test_assert_eq_catches_wrong_intermediate() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(12_u8));
+        let b = builder.input(&GarbledUint8::from(30_u8));
+        let sum = builder.add(&a, &b);
+        let double = builder.add(&sum, &sum);
+        builder.assert_eq(&a, &a);
+        // a deliberately wrong intermediate, off by one
+        let wrong = builder.sub(&double, &sum);
+        let one = builder.input(&GarbledUint8::from(1_u8));
+        let wrong = builder.add(&wrong, &one);
+        builder.assert_eq(&sum, &wrong);
+
+        let circuit = builder.compile(&double);
+        let _ = builder.execute::<8>(&circuit);
+    }
 }
@@ -0,0 +1,124 @@
+use std::fmt;
+use tandem::{Circuit, Gate, GateIndex};
+
+/// Error returned when two circuits cannot be chained.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompositionError {
+    /// The first circuit produces a different number of output wires than the second one
+    /// consumes as inputs.
+    WidthMismatch { outputs: usize, inputs: usize },
+}
+
+impl fmt::Display for CompositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompositionError::WidthMismatch { outputs, inputs } => write!(
+                f,
+                "cannot feed {} output wires into a circuit with {} input wires",
+                outputs, inputs
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompositionError {}
+
+/// Composition of compiled circuits into pipelines.
+pub trait CircuitCompose: Sized {
+    /// Chains `a` into `b`: the output wires of `a` replace the input wires of `b`, in order,
+    /// and the result has the inputs of `a` and the outputs of `b`. The gates of `b` are
+    /// renumbered to follow those of `a`.
+    fn compose(a: &Self, b: &Self) -> Result<Self, CompositionError>;
+}
+
+impl CircuitCompose for Circuit {
+    fn compose(a: &Circuit, b: &Circuit) -> Result<Circuit, CompositionError> {
+        let outputs = a.output_gates();
+        let inputs = b.contrib_inputs() + b.eval_inputs();
+        if outputs.len() != inputs {
+            return Err(CompositionError::WidthMismatch {
+                outputs: outputs.len(),
+                inputs,
+            });
+        }
+
+        let mut gates: Vec<Gate> = a
+            .gates()
+            .iter()
+            .map(|gate| match *gate {
+                Gate::InContrib => Gate::InContrib,
+                Gate::InEval => Gate::InEval,
+                Gate::Xor(x, y) => Gate::Xor(x, y),
+                Gate::And(x, y) => Gate::And(x, y),
+                Gate::Not(x) => Gate::Not(x),
+            })
+            .collect();
+
+        // the inputs of `b` are wired to the outputs of `a` in gate order
+        let mut remap: Vec<GateIndex> = Vec::with_capacity(b.gates().len());
+        let mut next_output = outputs.iter();
+        for gate in b.gates() {
+            let gate = match *gate {
+                Gate::InContrib | Gate::InEval => {
+                    remap.push(*next_output.next().unwrap());
+                    continue;
+                }
+                Gate::Xor(x, y) => Gate::Xor(remap[x as usize], remap[y as usize]),
+                Gate::And(x, y) => Gate::And(remap[x as usize], remap[y as usize]),
+                Gate::Not(x) => Gate::Not(remap[x as usize]),
+            };
+            remap.push(gates.len() as GateIndex);
+            gates.push(gate);
+        }
+
+        let outputs = b
+            .output_gates()
+            .iter()
+            .map(|output| remap[*output as usize])
+            .collect();
+        Ok(Circuit::new(gates, outputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::get_executor;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::uint::{GarbledUint16, GarbledUint8};
+
+    fn add_one() -> Circuit {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let output = builder.add_constant(&a, 1);
+        builder.compile(&output)
+    }
+
+    #[test]
+    fn test_compose_add_one_twice() {
+        let add_two = Circuit::compose(&add_one(), &add_one()).unwrap();
+        assert_eq!(add_two.contrib_inputs(), 8);
+        assert_eq!(add_two.output_gates().len(), 8);
+
+        for value in [0_u8, 41, 254, 255] {
+            let inputs = GarbledUint8::from(value).bits;
+            let result = get_executor().execute(&add_two, &inputs, &[]).unwrap();
+            assert_eq!(u8::from(GarbledUint8::new(result)), value.wrapping_add(2));
+        }
+    }
+
+    #[test]
+    fn test_compose_width_mismatch() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint16::from(0_u16));
+        let wide = builder.compile(&a);
+
+        assert_eq!(
+            Circuit::compose(&add_one(), &wide).unwrap_err(),
+            CompositionError::WidthMismatch {
+                outputs: 8,
+                inputs: 16,
+            }
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use tandem::{Circuit, Gate, GateIndex};
+
+/// Rendering of compiled circuits as [Graphviz] digraphs, for inspecting generated circuits.
+///
+/// Input wires, gates and output wires become nodes and every wire dependency an edge. AND
+/// gates, which dominate the cost of garbled execution, are filled red and the free XOR and
+/// NOT gates in lighter colours. The constant wires the builder derives without inputs
+/// (`x ^ x` and its negation) are drawn as separate `0` and `1` source nodes.
+///
+/// [Graphviz]: https://graphviz.org/
+pub trait CircuitDot {
+    /// Renders the circuit in the DOT language, e.g. for `dot -Tsvg`.
+    fn to_dot(&self) -> String;
+
+    /// Renders the circuit as [`to_dot`](Self::to_dot) does, with the name of every named
+    /// wire above its node's label, e.g. the names from
+    /// [`WRK17CircuitBuilder::wire_names`](crate::operations::circuits::builder::WRK17CircuitBuilder::wire_names).
+    fn to_dot_with_names(&self, names: &BTreeMap<GateIndex, String>) -> String;
+}
+
+impl CircuitDot for Circuit {
+    fn to_dot(&self) -> String {
+        self.to_dot_with_names(&BTreeMap::new())
+    }
+
+    fn to_dot_with_names(&self, names: &BTreeMap<GateIndex, String>) -> String {
+        let gates = self.gates();
+        let mut constant: Vec<Option<bool>> = Vec::with_capacity(gates.len());
+        let mut dot = String::from("digraph circuit {\n    rankdir=LR;\n");
+
+        let (mut contrib, mut eval) = (0, 0);
+        for (index, gate) in gates.iter().enumerate() {
+            let (value, label, attributes, operands) = match *gate {
+                Gate::InContrib => {
+                    contrib += 1;
+                    let label = format!("contrib {}", contrib - 1);
+                    (None, label, "shape=circle", vec![])
+                }
+                Gate::InEval => {
+                    eval += 1;
+                    let label = format!("eval {}", eval - 1);
+                    (None, label, "shape=circle", vec![])
+                }
+                Gate::Xor(a, b) if a == b => constant_node(false),
+                Gate::Xor(a, b) => (
+                    None,
+                    "XOR".to_string(),
+                    "style=filled, fillcolor=lightblue",
+                    vec![a, b],
+                ),
+                Gate::And(a, b) => (
+                    None,
+                    "AND".to_string(),
+                    "style=filled, fillcolor=tomato",
+                    vec![a, b],
+                ),
+                Gate::Not(a) => match constant[a as usize] {
+                    Some(value) => constant_node(!value),
+                    None => (
+                        None,
+                        "NOT".to_string(),
+                        "style=filled, fillcolor=lightgrey",
+                        vec![a],
+                    ),
+                },
+            };
+            constant.push(value);
+            let label = match names.get(&(index as GateIndex)) {
+                Some(name) => format!("{}\\n{}", escape(name), label),
+                None => label,
+            };
+            writeln!(dot, "    g{} [label=\"{}\", {}];", index, label, attributes).unwrap();
+            for operand in operands {
+                writeln!(dot, "    g{} -> g{};", operand, index).unwrap();
+            }
+        }
+
+        for (index, output) in self.output_gates().iter().enumerate() {
+            writeln!(
+                dot,
+                "    out{} [label=\"out {}\", shape=doublecircle];\n    g{} -> out{};",
+                index, index, output, index
+            )
+            .unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A constant wire, drawn as a source node with no operands.
+fn constant_node(value: bool) -> (Option<bool>, String, &'static str, Vec<GateIndex>) {
+    let label = if value { "1" } else { "0" };
+    (Some(value), label.to_string(), "shape=box", vec![])
+}
+
+/// Escapes a wire name for a quoted DOT label.
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::operations::circuits::types::GateIndexVec;
+    use crate::uint::GarbledUint8;
+
+    #[test]
+    fn test_adder_to_dot() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let b = builder.input(&GarbledUint8::from(5_u8));
+        let sum = builder.add(&a, &b);
+        let dot = builder.compile(&sum).to_dot();
+
+        assert!(dot.starts_with("digraph circuit {"));
+        assert_eq!(dot.matches("label=\"contrib").count(), 16);
+        assert_eq!(dot.matches("label=\"AND\"").count(), 15);
+        assert_eq!(dot.matches("label=\"XOR\"").count(), 22);
+        assert_eq!(dot.matches("shape=doublecircle").count(), 8);
+    }
+
+    #[test]
+    fn test_constants_to_dot() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let one = builder.literal(1, 8);
+        let output = builder.and(&a, &one);
+        let dot = builder.compile(&output).to_dot();
+
+        // the zero wire and its negation are sources, not XOR and NOT gates
+        assert_eq!(dot.matches("[label=\"0\", shape=box]").count(), 1);
+        assert_eq!(dot.matches("[label=\"1\", shape=box]").count(), 1);
+        assert_eq!(dot.matches("label=\"XOR\"").count(), 0);
+        assert_eq!(dot.matches("label=\"NOT\"").count(), 0);
+        assert_eq!(dot.matches("label=\"AND\"").count(), 8);
+    }
+
+    #[test]
+    fn test_named_wires_to_dot() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let b = builder.input(&GarbledUint8::from(5_u8));
+        builder.name(&a, "a");
+        let product = builder.and(&a, &b);
+        builder.name(&GateIndexVec::from(product[7]), "top \"bit\"");
+        let circuit = builder.compile(&product);
+
+        let dot = circuit.to_dot_with_names(builder.wire_names());
+        assert!(dot.contains(&format!(
+            "g{} [label=\"a[2]\\ncontrib 2\", shape=circle];",
+            a[2]
+        )));
+        assert!(dot.contains(&format!(
+            "g{} [label=\"top \\\"bit\\\"\\nAND\", style=filled, fillcolor=tomato];",
+            product[7]
+        )));
+        assert_eq!(dot.matches("label=\"AND\"").count(), 7);
+        assert_eq!(circuit.to_dot().matches("label=\"AND\"").count(), 8);
+    }
+}
@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+use tandem::{Circuit, Gate};
+
+/// Circuits with at most this many input bits are compared on every input assignment.
+const EXHAUSTIVE_INPUT_LIMIT: usize = 16;
+
+/// Functional comparison of compiled circuits, e.g. to check that an optimization pass kept
+/// what a circuit computes.
+pub trait CircuitEquivalence {
+    /// Returns whether both circuits compute the same outputs for every input. Contributor
+    /// and evaluator inputs are matched by position, so circuits with different input or
+    /// output counts are never equivalent.
+    ///
+    /// Circuits with up to 16 input bits are evaluated on every input assignment. Wider ones
+    /// are combined into a miter, which is true exactly when some output differs, and a
+    /// bundled SAT solver searches for an input that satisfies it. Both answers are exact,
+    /// but the solver can take exponential time on circuits that are hard to tell apart.
+    fn is_equivalent(&self, other: &Circuit) -> bool;
+}
+
+impl CircuitEquivalence for Circuit {
+    fn is_equivalent(&self, other: &Circuit) -> bool {
+        if self.contrib_inputs() != other.contrib_inputs()
+            || self.eval_inputs() != other.eval_inputs()
+            || self.output_gates().len() != other.output_gates().len()
+        {
+            return false;
+        }
+        if self.contrib_inputs() + self.eval_inputs() <= EXHAUSTIVE_INPUT_LIMIT {
+            exhaustive_equivalent(self, other)
+        } else {
+            sat_equivalent(self, other)
+        }
+    }
+}
+
+/// Evaluates both circuits on every input assignment, 64 at a time: bit `lane` of a wire's
+/// word is its value under assignment `start + lane`.
+fn exhaustive_equivalent(a: &Circuit, b: &Circuit) -> bool {
+    let inputs = a.contrib_inputs() + a.eval_inputs();
+    let assignments = 1_u64 << inputs;
+    let lanes = if assignments < 64 {
+        (1 << assignments) - 1
+    } else {
+        u64::MAX
+    };
+
+    let mut start = 0;
+    while start < assignments {
+        let words: Vec<u64> = (0..inputs).map(|bit| input_word(start, bit)).collect();
+        let mut outputs = evaluate_words(a, &words)
+            .into_iter()
+            .zip(evaluate_words(b, &words));
+        if outputs.any(|(x, y)| (x ^ y) & lanes != 0) {
+            return false;
+        }
+        start += 64;
+    }
+    true
+}
+
+/// Returns the values of input `bit` under assignments `start..start + 64`.
+fn input_word(start: u64, bit: usize) -> u64 {
+    const PATTERNS: [u64; 6] = [
+        0xaaaa_aaaa_aaaa_aaaa,
+        0xcccc_cccc_cccc_cccc,
+        0xf0f0_f0f0_f0f0_f0f0,
+        0xff00_ff00_ff00_ff00,
+        0xffff_0000_ffff_0000,
+        0xffff_ffff_0000_0000,
+    ];
+    match PATTERNS.get(bit) {
+        Some(pattern) => *pattern,
+        None if (start >> bit) & 1 == 1 => u64::MAX,
+        None => 0,
+    }
+}
+
+/// Evaluates the circuit on 64 assignments at once. The contributor inputs take the first
+/// words of `inputs` and the evaluator inputs the rest.
+fn evaluate_words(circuit: &Circuit, inputs: &[u64]) -> Vec<u64> {
+    let (contrib, eval) = inputs.split_at(circuit.contrib_inputs());
+    let (mut contrib, mut eval) = (contrib.iter(), eval.iter());
+    let mut wires: Vec<u64> = Vec::with_capacity(circuit.gates().len());
+    for gate in circuit.gates() {
+        let word = match *gate {
+            Gate::InContrib => *contrib.next().unwrap(),
+            Gate::InEval => *eval.next().unwrap(),
+            Gate::Xor(x, y) => wires[x as usize] ^ wires[y as usize],
+            Gate::And(x, y) => wires[x as usize] & wires[y as usize],
+            Gate::Not(x) => !wires[x as usize],
+        };
+        wires.push(word);
+    }
+    circuit
+        .output_gates()
+        .iter()
+        .map(|output| wires[*output as usize])
+        .collect()
+}
+
+/// A literal of the solver: variable `lit >> 1`, negated when the low bit is set.
+type Lit = u32;
+
+/// The literal of the constant variable, which a unit clause fixes to false.
+const FALSE: Lit = 0;
+const TRUE: Lit = 1;
+
+/// Encodes the miter of both circuits and asks the solver for an input on which some output
+/// differs.
+fn sat_equivalent(a: &Circuit, b: &Circuit) -> bool {
+    let mut miter = Miter::default();
+    let constant = miter.solver.new_var();
+    miter.solver.add_clause(vec![constant ^ 1]);
+
+    let inputs: Vec<Lit> = (0..a.contrib_inputs() + a.eval_inputs())
+        .map(|_| miter.solver.new_var())
+        .collect();
+    let outputs_a = miter.encode(a, &inputs);
+    let outputs_b = miter.encode(b, &inputs);
+
+    let mut differences = vec![];
+    for (x, y) in outputs_a.into_iter().zip(outputs_b) {
+        match miter.xor(x, y) {
+            FALSE => {}
+            TRUE => return false,
+            difference => differences.push(difference),
+        }
+    }
+    if differences.is_empty() {
+        return true;
+    }
+    miter.solver.add_clause(differences);
+    !miter.solver.solve()
+}
+
+/// Tseitin encoding of circuits over shared inputs. Gates are hashed structurally across
+/// both circuits, so the parts they have in common become the same solver variables.
+#[derive(Default)]
+struct Miter {
+    solver: Solver,
+    gates: HashMap<(bool, Lit, Lit), Lit>,
+}
+
+impl Miter {
+    /// Encodes the circuit over the given input literals, returning its output literals.
+    fn encode(&mut self, circuit: &Circuit, inputs: &[Lit]) -> Vec<Lit> {
+        let (contrib, eval) = inputs.split_at(circuit.contrib_inputs());
+        let (mut contrib, mut eval) = (contrib.iter(), eval.iter());
+        let mut wires: Vec<Lit> = Vec::with_capacity(circuit.gates().len());
+        for gate in circuit.gates() {
+            let lit = match *gate {
+                Gate::InContrib => *contrib.next().unwrap(),
+                Gate::InEval => *eval.next().unwrap(),
+                Gate::Xor(x, y) => self.xor(wires[x as usize], wires[y as usize]),
+                Gate::And(x, y) => self.and(wires[x as usize], wires[y as usize]),
+                Gate::Not(x) => wires[x as usize] ^ 1,
+            };
+            wires.push(lit);
+        }
+        circuit
+            .output_gates()
+            .iter()
+            .map(|output| wires[*output as usize])
+            .collect()
+    }
+
+    fn xor(&mut self, x: Lit, y: Lit) -> Lit {
+        // negations move to the output, so `!x ^ y` shares the variable of `x ^ y`
+        let negated = (x ^ y) & 1;
+        let (x, y) = ((x & !1).min(y & !1), (x & !1).max(y & !1));
+        if x == y {
+            return FALSE ^ negated;
+        }
+        if x == FALSE {
+            return y ^ negated;
+        }
+        if let Some(lit) = self.gates.get(&(true, x, y)) {
+            return lit ^ negated;
+        }
+        let z = self.solver.new_var();
+        self.solver.add_clause(vec![z ^ 1, x, y]);
+        self.solver.add_clause(vec![z ^ 1, x ^ 1, y ^ 1]);
+        self.solver.add_clause(vec![z, x ^ 1, y]);
+        self.solver.add_clause(vec![z, x, y ^ 1]);
+        self.gates.insert((true, x, y), z);
+        z ^ negated
+    }
+
+    fn and(&mut self, x: Lit, y: Lit) -> Lit {
+        let (x, y) = (x.min(y), x.max(y));
+        if x == FALSE || x == y ^ 1 {
+            return FALSE;
+        }
+        if x == TRUE || x == y {
+            return y;
+        }
+        if let Some(lit) = self.gates.get(&(false, x, y)) {
+            return *lit;
+        }
+        let z = self.solver.new_var();
+        self.solver.add_clause(vec![z ^ 1, x]);
+        self.solver.add_clause(vec![z ^ 1, y]);
+        self.solver.add_clause(vec![z, x ^ 1, y ^ 1]);
+        self.gates.insert((false, x, y), z);
+        z
+    }
+}
+
+/// A conflict-driven clause-learning SAT solver: two watched literals per clause, first-UIP
+/// learning, activity-ordered decisions and phase saving.
+#[derive(Default)]
+struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    /// The clauses watching each literal, i.e. holding it in one of their first two places.
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    /// The clause that implied each assigned variable, or `None` for decisions.
+    reasons: Vec<Option<usize>>,
+    activity: Vec<f64>,
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+    trail: Vec<Lit>,
+    /// The trail length at the start of each decision level.
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    bump: f64,
+    unsatisfiable: bool,
+}
+
+fn lit_value(values: &[Option<bool>], lit: Lit) -> Option<bool> {
+    values[(lit >> 1) as usize].map(|value| value ^ (lit & 1 == 1))
+}
+
+impl Solver {
+    /// Adds a variable and returns its positive literal.
+    fn new_var(&mut self) -> Lit {
+        let lit = (self.values.len() as Lit) << 1;
+        self.values.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.0);
+        self.phases.push(false);
+        self.seen.push(false);
+        self.watches.extend([vec![], vec![]]);
+        lit
+    }
+
+    /// Adds a clause before solving.
+    fn add_clause(&mut self, mut clause: Vec<Lit>) {
+        clause.sort_unstable();
+        clause.dedup();
+        // a variable's two literals are adjacent once sorted
+        if clause.windows(2).any(|pair| pair[0] ^ 1 == pair[1]) {
+            return;
+        }
+        match clause.len() {
+            0 => self.unsatisfiable = true,
+            1 => match lit_value(&self.values, clause[0]) {
+                Some(true) => {}
+                Some(false) => self.unsatisfiable = true,
+                None => self.assign(clause[0], None),
+            },
+            _ => {
+                self.push_clause(clause);
+            }
+        }
+    }
+
+    fn push_clause(&mut self, clause: Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[clause[0] as usize].push(index);
+        self.watches[clause[1] as usize].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = (lit >> 1) as usize;
+        self.values[var] = Some(lit & 1 == 0);
+        self.levels[var] = self.trail_limits.len();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Returns whether the clauses have a satisfying assignment.
+    fn solve(&mut self) -> bool {
+        if self.unsatisfiable {
+            return false;
+        }
+        self.bump = 1.0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_limits.is_empty() {
+                    return false;
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                if learnt.len() == 1 {
+                    self.assign(learnt[0], None);
+                } else {
+                    let asserting = learnt[0];
+                    let index = self.push_clause(learnt);
+                    self.assign(asserting, Some(index));
+                }
+                self.decay();
+            } else {
+                let unassigned = (0..self.values.len()).filter(|var| self.values[*var].is_none());
+                let Some(var) =
+                    unassigned.max_by(|x, y| self.activity[*x].total_cmp(&self.activity[*y]))
+                else {
+                    return true;
+                };
+                self.trail_limits.push(self.trail.len());
+                let lit = ((var as Lit) << 1) | Lit::from(!self.phases[var]);
+                self.assign(lit, None);
+            }
+        }
+    }
+
+    /// Propagates the assignments on the trail, returning a clause whose literals are all
+    /// false if there is one.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let falsified = self.trail[self.propagated] ^ 1;
+            self.propagated += 1;
+            let mut watching = std::mem::take(&mut self.watches[falsified as usize]);
+            let mut i = 0;
+            while i < watching.len() {
+                let index = watching[i];
+                let clause = &mut self.clauses[index];
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if lit_value(&self.values, first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                let replacement =
+                    (2..clause.len()).find(|k| lit_value(&self.values, clause[*k]) != Some(false));
+                if let Some(k) = replacement {
+                    clause.swap(1, k);
+                    self.watches[clause[1] as usize].push(index);
+                    watching.swap_remove(i);
+                    continue;
+                }
+                if lit_value(&self.values, first) == Some(false) {
+                    self.watches[falsified as usize] = watching;
+                    self.propagated = self.trail.len();
+                    return Some(index);
+                }
+                self.assign(first, Some(index));
+                i += 1;
+            }
+            self.watches[falsified as usize] = watching;
+        }
+        None
+    }
+
+    /// Derives the first-UIP clause of a conflict and the level to backtrack to, where the
+    /// clause has a single unassigned literal in its first place.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let level = self.trail_limits.len();
+        let mut learnt = vec![FALSE];
+        let mut pending = 0;
+        let mut clause_index = conflict;
+        let mut trail_index = self.trail.len();
+        // the first literal of a reason clause is the one it implied, already accounted for
+        let mut skip = 0;
+        loop {
+            for &lit in &self.clauses[clause_index][skip..] {
+                let var = (lit >> 1) as usize;
+                if !self.seen[var] && self.levels[var] > 0 {
+                    self.seen[var] = true;
+                    self.activity[var] += self.bump;
+                    if self.levels[var] == level {
+                        pending += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
+                }
+            }
+            loop {
+                trail_index -= 1;
+                if self.seen[(self.trail[trail_index] >> 1) as usize] {
+                    break;
+                }
+            }
+            let lit = self.trail[trail_index];
+            let var = (lit >> 1) as usize;
+            self.seen[var] = false;
+            pending -= 1;
+            if pending == 0 {
+                learnt[0] = lit ^ 1;
+                break;
+            }
+            clause_index = self.reasons[var].unwrap();
+            skip = 1;
+        }
+
+        for lit in &learnt[1..] {
+            self.seen[(lit >> 1) as usize] = false;
+        }
+        // the literal of the highest remaining level becomes the second watch
+        let level_of = |lit: Lit| self.levels[(lit >> 1) as usize];
+        if let Some(highest) = (1..learnt.len()).max_by_key(|i| level_of(learnt[*i])) {
+            learnt.swap(1, highest);
+        }
+        let backtrack_level = learnt.get(1).map_or(0, |lit| level_of(*lit));
+        (learnt, backtrack_level)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        let limit = self.trail_limits[level];
+        for lit in self.trail.drain(limit..) {
+            let var = (lit >> 1) as usize;
+            self.phases[var] = lit & 1 == 0;
+            self.values[var] = None;
+            self.reasons[var] = None;
+        }
+        self.trail_limits.truncate(level);
+        self.propagated = self.trail.len();
+    }
+
+    /// Favors the variables of recent conflicts by growing the activity bump, rescaling every
+    /// activity before it overflows.
+    fn decay(&mut self) {
+        self.bump *= 1.05;
+        if self.bump > 1e100 {
+            for activity in &mut self.activity {
+                *activity *= 1e-100;
+            }
+            self.bump *= 1e-100;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::circuits::builder::{AdderStrategy, WRK17CircuitBuilder};
+    use crate::operations::circuits::optimize::CircuitOptimizer;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::uint::{GarbledUint16, GarbledUint8};
+
+    fn redundant_adder() -> Circuit {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let b = builder.input(&GarbledUint8::from(0_u8));
+        let sum = builder.add(&a, &b);
+        let again = builder.add(&b, &a);
+        let output = builder.and(&sum, &again);
+        builder.compile(&output)
+    }
+
+    fn adder16(adder: AdderStrategy) -> Circuit {
+        let mut builder = WRK17CircuitBuilder::default().with_adder(adder);
+        let a = builder.input(&GarbledUint16::from(0_u16));
+        let b = builder.input(&GarbledUint16::from(0_u16));
+        let sum = builder.add(&a, &b);
+        builder.compile(&sum)
+    }
+
+    /// Replaces the first AND gate of the circuit with an XOR gate.
+    fn break_first_and(circuit: &Circuit) -> Circuit {
+        let mut gates = circuit.gates().to_vec();
+        let index = gates
+            .iter()
+            .position(|gate| matches!(gate, Gate::And(_, _)))
+            .unwrap();
+        if let Gate::And(x, y) = gates[index] {
+            gates[index] = Gate::Xor(x, y);
+        }
+        Circuit::new(gates, circuit.output_gates().clone())
+    }
+
+    #[test]
+    fn test_optimized_circuit_is_equivalent() {
+        let circuit = redundant_adder();
+        let mut optimized = circuit.clone();
+        optimized.dedup_gates();
+        optimized.prune_dead_gates();
+        assert!(optimized.gates().len() < circuit.gates().len());
+
+        assert!(circuit.is_equivalent(&optimized));
+        assert!(!circuit.is_equivalent(&break_first_and(&optimized)));
+        // the solver agrees with exhaustive evaluation
+        assert!(sat_equivalent(&circuit, &optimized));
+        assert!(!sat_equivalent(&circuit, &break_first_and(&optimized)));
+    }
+
+    #[test]
+    fn test_adders_are_equivalent() {
+        // 32 input bits, so the miter goes through the solver
+        let ripple = adder16(AdderStrategy::RippleCarry);
+        let kogge_stone = adder16(AdderStrategy::KoggeStone);
+        assert!(ripple.is_equivalent(&kogge_stone));
+        assert!(!ripple.is_equivalent(&break_first_and(&kogge_stone)));
+
+        let mut swapped = ripple.output_gates().clone();
+        swapped.swap(3, 4);
+        let swapped = Circuit::new(ripple.gates().to_vec(), swapped);
+        assert!(!ripple.is_equivalent(&swapped));
+    }
+
+    #[test]
+    fn test_width_mismatch_is_not_equivalent() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(0_u8));
+        let narrow = builder.compile(&a);
+        let wide = adder16(AdderStrategy::RippleCarry);
+        assert!(!narrow.is_equivalent(&wide));
+
+        let mut fewer_outputs = wide.output_gates().clone();
+        fewer_outputs.pop();
+        let truncated = Circuit::new(wide.gates().to_vec(), fewer_outputs);
+        assert!(!wide.is_equivalent(&truncated));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::operations::circuits::optimize::CircuitOptimizer;
+use tandem::{Circuit, Gate};
+
+/// Domain separator for circuit fingerprints, bumped whenever the hashed encoding changes.
+const FINGERPRINT_DOMAIN: &[u8] = b"circuit-sdk fingerprint v1";
+
+/// Stable structural hash of a compiled [`Circuit`], e.g. for keying a cache of compiled
+/// circuits or detecting that a source change actually altered the circuit.
+pub trait CircuitFingerprint {
+    /// Returns a BLAKE3 hash of the normalized circuit: a copy is deduplicated and pruned of
+    /// dead gates, which renumbers its wires densely, and the hash then covers the input
+    /// counts, every gate with commutative operands in sorted order, and the output wires.
+    /// Circuits that are identical after common-subexpression elimination hash equal, even
+    /// if they were built with redundant or unused gates.
+    fn fingerprint(&self) -> [u8; 32];
+}
+
+impl CircuitFingerprint for Circuit {
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut normalized = self.clone();
+        normalized.dedup_gates();
+        normalized.prune_dead_gates();
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(FINGERPRINT_DOMAIN);
+        for count in [
+            normalized.contrib_inputs(),
+            normalized.eval_inputs(),
+            normalized.gates().len(),
+            normalized.output_gates().len(),
+        ] {
+            hasher.update(&(count as u64).to_le_bytes());
+        }
+
+        for gate in normalized.gates() {
+            let (tag, operands) = match *gate {
+                Gate::InContrib => (0_u8, vec![]),
+                Gate::InEval => (1, vec![]),
+                Gate::Xor(a, b) => (2, vec![a.min(b), a.max(b)]),
+                Gate::And(a, b) => (3, vec![a.min(b), a.max(b)]),
+                Gate::Not(a) => (4, vec![a]),
+            };
+            hasher.update(&[tag]);
+            for operand in operands {
+                hasher.update(&operand.to_le_bytes());
+            }
+        }
+        for output in normalized.output_gates() {
+            hasher.update(&output.to_le_bytes());
+        }
+
+        *hasher.finalize().as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::uint::GarbledUint8;
+
+    fn add_then_and(x: u8, redundant: bool) -> Circuit {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(x));
+        let b = builder.input(&GarbledUint8::from(7_u8));
+        let sum = builder.add(&b, &a);
+        if redundant {
+            // a duplicate of the adder and an unused product, both normalized away
+            builder.add(&b, &a);
+            builder.mul(&a, &b);
+        }
+        let output = builder.and(&sum, &a);
+        builder.compile(&output)
+    }
+
+    #[test]
+    fn test_fingerprint_is_structural() {
+        let circuit = add_then_and(3, false);
+        assert_eq!(circuit.fingerprint(), add_then_and(3, false).fingerprint());
+        // input values are not part of the circuit
+        assert_eq!(
+            circuit.fingerprint(),
+            add_then_and(200, false).fingerprint()
+        );
+        assert_eq!(circuit.fingerprint(), add_then_and(3, true).fingerprint());
+    }
+
+    #[test]
+    fn test_fingerprint_detects_changes() {
+        let circuit = add_then_and(3, false);
+
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let b = builder.input(&GarbledUint8::from(7_u8));
+        let sum = builder.add(&b, &a);
+        let output = builder.or(&sum, &a);
+        assert_ne!(
+            circuit.fingerprint(),
+            builder.compile(&output).fingerprint()
+        );
+
+        // the same gates with an extra output wire
+        let mut outputs = circuit.output_gates().clone();
+        outputs.push(0);
+        let gates = circuit.gates().to_vec();
+        assert_ne!(
+            circuit.fingerprint(),
+            Circuit::new(gates, outputs).fingerprint()
+        );
+    }
+}
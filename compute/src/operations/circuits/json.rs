@@ -0,0 +1,171 @@
+use crate::executor::check_wiring;
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tandem::{Circuit, Gate, GateIndex};
+
+/// A gate as a JSON array of its operation name followed by its operand wires, e.g.
+/// `["and",0,8]`, which keeps large circuits compact.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum JsonGate {
+    Binary(String, GateIndex, GateIndex),
+    Unary(String, GateIndex),
+    Input((String,)),
+}
+
+impl From<&Gate> for JsonGate {
+    fn from(gate: &Gate) -> Self {
+        match *gate {
+            Gate::InContrib => JsonGate::Input(("in_contrib".to_string(),)),
+            Gate::InEval => JsonGate::Input(("in_eval".to_string(),)),
+            Gate::Xor(a, b) => JsonGate::Binary("xor".to_string(), a, b),
+            Gate::And(a, b) => JsonGate::Binary("and".to_string(), a, b),
+            Gate::Not(a) => JsonGate::Unary("not".to_string(), a),
+        }
+    }
+}
+
+impl TryFrom<JsonGate> for Gate {
+    type Error = anyhow::Error;
+
+    fn try_from(gate: JsonGate) -> anyhow::Result<Self> {
+        Ok(match gate {
+            JsonGate::Input((op,)) if op == "in_contrib" => Gate::InContrib,
+            JsonGate::Input((op,)) if op == "in_eval" => Gate::InEval,
+            JsonGate::Binary(op, a, b) if op == "xor" => Gate::Xor(a, b),
+            JsonGate::Binary(op, a, b) if op == "and" => Gate::And(a, b),
+            JsonGate::Unary(op, a) if op == "not" => Gate::Not(a),
+            JsonGate::Input((op,)) | JsonGate::Unary(op, _) | JsonGate::Binary(op, _, _) => {
+                bail!("unknown gate `{op}` or wrong number of operands")
+            }
+        })
+    }
+}
+
+/// Number of input bits each party provides.
+#[derive(Serialize, Deserialize)]
+struct JsonInputs {
+    contrib: usize,
+    eval: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonCircuit {
+    inputs: JsonInputs,
+    outputs: Vec<GateIndex>,
+    gates: Vec<JsonGate>,
+}
+
+/// Human-readable JSON encoding of a compiled [`Circuit`] for debugging and web tooling,
+/// alongside the binary [`CircuitBytes`](super::serialize::CircuitBytes) format.
+///
+/// The encoding is an object with the input counts of both parties, the output wires and the
+/// gates in order, each an array of its operation and operand wires:
+///
+/// ```json
+/// {"inputs":{"contrib":2,"eval":0},"outputs":[2],"gates":[["in_contrib"],["in_contrib"],["and",0,1]]}
+/// ```
+pub trait CircuitJson: Sized {
+    /// Encodes the circuit as compact JSON.
+    fn to_json(&self) -> String;
+
+    /// Decodes a circuit, rejecting unknown gates, gates or outputs that reference
+    /// out-of-range wires, and input counts that do not match the input gates.
+    fn from_json(json: &str) -> anyhow::Result<Self>;
+}
+
+impl CircuitJson for Circuit {
+    fn to_json(&self) -> String {
+        let circuit = JsonCircuit {
+            inputs: JsonInputs {
+                contrib: self.contrib_inputs(),
+                eval: self.eval_inputs(),
+            },
+            outputs: self.output_gates().clone(),
+            gates: self.gates().iter().map(JsonGate::from).collect(),
+        };
+        serde_json::to_string(&circuit).expect("Serializing a circuit to JSON cannot fail")
+    }
+
+    fn from_json(json: &str) -> anyhow::Result<Self> {
+        let circuit: JsonCircuit = serde_json::from_str(json)
+            .map_err(|error| anyhow!("malformed circuit JSON: {error}"))?;
+        let gates = circuit
+            .gates
+            .into_iter()
+            .map(Gate::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let decoded = Circuit::new(gates, circuit.outputs);
+        check_wiring(&decoded)?;
+        if decoded.eval_inputs() != circuit.inputs.eval
+            || decoded.contrib_inputs() != circuit.inputs.contrib
+        {
+            bail!("recorded gate counts do not match the gate list");
+        }
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[encrypted(compile)]
+    fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> (Circuit, Vec<bool>) {
+        let res = a * b;
+        let res = res + c;
+        res - d
+    }
+
+    #[test]
+    fn test_circuit_json_round_trip() -> anyhow::Result<()> {
+        let (circuit, inputs) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+
+        let json = circuit.to_json();
+        let decoded = Circuit::from_json(&json)?;
+        assert_eq!(decoded, circuit);
+        assert_eq!(decoded.to_json(), json);
+
+        let expected = get_executor().execute(&circuit, &inputs, &[])?;
+        let result = get_executor().execute(&decoded, &inputs, &[])?;
+        assert_eq!(result, expected);
+        assert_eq!(u8::from(GarbledUint8::new(result)), 2 * 5 + 3 - 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_circuit_json_format() {
+        let circuit = Circuit::new(
+            vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1), Gate::Not(2)],
+            vec![3],
+        );
+        assert_eq!(
+            circuit.to_json(),
+            r#"{"inputs":{"contrib":1,"eval":1},"outputs":[3],"gates":[["in_contrib"],["in_eval"],["and",0,1],["not",2]]}"#
+        );
+    }
+
+    #[test]
+    fn test_circuit_json_rejects_invalid_circuits() {
+        let error = Circuit::from_json(
+            r#"{"inputs":{"contrib":1,"eval":0},"outputs":[1],"gates":[["in_contrib"],["or",0,0]]}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown gate `or`"));
+
+        let error = Circuit::from_json(
+            r#"{"inputs":{"contrib":1,"eval":0},"outputs":[1],"gates":[["in_contrib"],["not",4]]}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("wire 4 is out of range"));
+
+        let error = Circuit::from_json(
+            r#"{"inputs":{"contrib":2,"eval":0},"outputs":[0],"gates":[["in_contrib"]]}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("gate counts"));
+    }
+}
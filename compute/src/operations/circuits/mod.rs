@@ -1,3 +1,15 @@
+pub mod bristol;
 pub mod builder;
+pub mod compose;
+pub mod dot;
+pub mod equivalence;
+pub mod fingerprint;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod optimize;
+pub mod parallel;
+pub mod serialize;
+pub mod stats;
 pub mod traits;
 pub mod types;
+pub mod visit;
@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+use tandem::{Circuit, Gate, GateIndex};
+
+/// Optimization passes over compiled circuits.
+///
+/// Each pass rebuilds the circuit in place and returns the number of gates it removed. Input
+/// gates are always kept, in order, so the input vector of the original circuit stays valid.
+pub trait CircuitOptimizer {
+    /// Propagates constants through the circuit. Constants are recognised from the wires the
+    /// builder derives without inputs (`x ^ x` is false), and simplifications such as
+    /// `AND(x, false) = false`, `XOR(x, false) = x` and `NOT(NOT(x)) = x` are applied. Gates
+    /// are in topological order, so a single forward pass reaches the fixpoint.
+    fn optimize_constants(&mut self) -> usize;
+
+    /// Collapses structurally identical gates into a single wire (common-subexpression
+    /// elimination). Gates are keyed by their operation and operands, with the operands of the
+    /// commutative XOR and AND sorted, so `a & b` and `b & a` share one gate.
+    fn dedup_gates(&mut self) -> usize;
+
+    /// Removes logic gates that no output wire depends on, found by walking back from the
+    /// outputs, and renumbers the remaining gates densely.
+    fn prune_dead_gates(&mut self) -> usize;
+
+    /// Applies peephole rewrites until none applies and then prunes the gates left dead:
+    /// `NOT(NOT(x)) = x`, `XOR(x, false) = x`, `XOR(x, true) = NOT(x)`, `AND(x, true) = x`,
+    /// `AND(x, false) = false`, `AND(x, x) = x` and `XOR(x, x) = false`. Unlike
+    /// [`Self::optimize_constants`], constants keep their wires: the first `XOR(x, x)` becomes
+    /// the false wire every later one is rewritten to, and its negation is known to be true.
+    /// Returns the number of rewrites.
+    fn simplify_gates(&mut self) -> usize;
+}
+
+/// Hash-consing key for a logic gate, with commutative operands in canonical order.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum GateKey {
+    Xor(GateIndex, GateIndex),
+    And(GateIndex, GateIndex),
+    Not(GateIndex),
+}
+
+impl From<GateKey> for Gate {
+    fn from(key: GateKey) -> Self {
+        match key {
+            GateKey::Xor(a, b) => Gate::Xor(a, b),
+            GateKey::And(a, b) => Gate::And(a, b),
+            GateKey::Not(a) => Gate::Not(a),
+        }
+    }
+}
+
+/// What an original gate turned into after folding.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Folded {
+    Const(bool),
+    Wire(GateIndex),
+}
+
+/// Accumulates the gates of a rebuilt circuit.
+#[derive(Default)]
+struct Rebuilder {
+    gates: Vec<Gate>,
+    zero: Option<GateIndex>,
+    one: Option<GateIndex>,
+}
+
+impl Rebuilder {
+    fn push(&mut self, gate: Gate) -> GateIndex {
+        self.gates.push(gate);
+        (self.gates.len() - 1) as GateIndex
+    }
+
+    fn not(&mut self, wire: GateIndex) -> Folded {
+        match self.gates[wire as usize] {
+            Gate::Not(inner) => Folded::Wire(inner),
+            _ => Folded::Wire(self.push(Gate::Not(wire))),
+        }
+    }
+
+    /// Turns a folded value into a wire, deriving constants from the first gate, which is
+    /// always an input.
+    fn wire(&mut self, folded: Folded) -> GateIndex {
+        match folded {
+            Folded::Wire(wire) => wire,
+            Folded::Const(false) => match self.zero {
+                Some(zero) => zero,
+                None => {
+                    let zero = self.push(Gate::Xor(0, 0));
+                    self.zero = Some(zero);
+                    zero
+                }
+            },
+            Folded::Const(true) => match self.one {
+                Some(one) => one,
+                None => {
+                    let zero = self.wire(Folded::Const(false));
+                    let one = self.push(Gate::Not(zero));
+                    self.one = Some(one);
+                    one
+                }
+            },
+        }
+    }
+}
+
+/// The gates of a circuit being simplified, with the value of every wire known to be constant.
+#[derive(Default)]
+struct Simplifier {
+    gates: Vec<Gate>,
+    constant: Vec<Option<bool>>,
+    zero: Option<GateIndex>,
+    rewrites: usize,
+}
+
+impl Simplifier {
+    fn push(&mut self, gate: Gate, value: Option<bool>) -> GateIndex {
+        self.gates.push(gate);
+        self.constant.push(value);
+        (self.gates.len() - 1) as GateIndex
+    }
+
+    /// Negates a wire, cancelling a NOT it already is.
+    fn not(&mut self, wire: GateIndex) -> GateIndex {
+        match self.gates[wire as usize] {
+            Gate::Not(inner) => {
+                self.rewrites += 1;
+                inner
+            }
+            _ => {
+                let value = self.constant[wire as usize].map(|value| !value);
+                self.push(Gate::Not(wire), value)
+            }
+        }
+    }
+
+    fn xor(&mut self, a: GateIndex, b: GateIndex) -> GateIndex {
+        if a == b {
+            if let Some(zero) = self.zero {
+                self.rewrites += 1;
+                return zero;
+            }
+            let zero = self.push(Gate::Xor(a, b), Some(false));
+            self.zero = Some(zero);
+            return zero;
+        }
+        match (self.constant[a as usize], self.constant[b as usize]) {
+            (Some(false), _) => self.rewrite(b),
+            (_, Some(false)) => self.rewrite(a),
+            (Some(true), _) => {
+                self.rewrites += 1;
+                self.not(b)
+            }
+            (_, Some(true)) => {
+                self.rewrites += 1;
+                self.not(a)
+            }
+            _ => self.push(Gate::Xor(a, b), None),
+        }
+    }
+
+    fn and(&mut self, a: GateIndex, b: GateIndex) -> GateIndex {
+        if a == b {
+            return self.rewrite(a);
+        }
+        match (self.constant[a as usize], self.constant[b as usize]) {
+            (Some(true), _) | (_, Some(false)) => self.rewrite(b),
+            (_, Some(true)) | (Some(false), _) => self.rewrite(a),
+            _ => self.push(Gate::And(a, b), None),
+        }
+    }
+
+    fn rewrite(&mut self, wire: GateIndex) -> GateIndex {
+        self.rewrites += 1;
+        wire
+    }
+}
+
+/// One forward pass of [`CircuitOptimizer::simplify_gates`], returning the number of rewrites.
+fn simplify_pass(circuit: &mut Circuit) -> usize {
+    let mut simplifier = Simplifier::default();
+    let mut remap: Vec<GateIndex> = Vec::with_capacity(circuit.gates().len());
+    for gate in circuit.gates() {
+        let wire = match *gate {
+            Gate::InContrib => simplifier.push(Gate::InContrib, None),
+            Gate::InEval => simplifier.push(Gate::InEval, None),
+            Gate::Not(a) => simplifier.not(remap[a as usize]),
+            Gate::Xor(a, b) => simplifier.xor(remap[a as usize], remap[b as usize]),
+            Gate::And(a, b) => simplifier.and(remap[a as usize], remap[b as usize]),
+        };
+        remap.push(wire);
+    }
+
+    let outputs = circuit
+        .output_gates()
+        .iter()
+        .map(|output| remap[*output as usize])
+        .collect();
+    *circuit = Circuit::new(simplifier.gates, outputs);
+    simplifier.rewrites
+}
+
+impl CircuitOptimizer for Circuit {
+    fn optimize_constants(&mut self) -> usize {
+        use Folded::{Const, Wire};
+
+        let before = self.gates().len();
+        let mut rebuilder = Rebuilder::default();
+        let mut folded: Vec<Folded> = Vec::with_capacity(before);
+
+        for gate in self.gates() {
+            let value = match *gate {
+                Gate::InContrib => Wire(rebuilder.push(Gate::InContrib)),
+                Gate::InEval => Wire(rebuilder.push(Gate::InEval)),
+                Gate::Not(a) => match folded[a as usize] {
+                    Const(value) => Const(!value),
+                    Wire(a) => rebuilder.not(a),
+                },
+                Gate::Xor(a, b) => match (folded[a as usize], folded[b as usize]) {
+                    (Const(a), Const(b)) => Const(a ^ b),
+                    (Const(false), other) | (other, Const(false)) => other,
+                    (Const(true), Wire(wire)) | (Wire(wire), Const(true)) => rebuilder.not(wire),
+                    (Wire(a), Wire(b)) if a == b => Const(false),
+                    (Wire(a), Wire(b)) => Wire(rebuilder.push(Gate::Xor(a, b))),
+                },
+                Gate::And(a, b) => match (folded[a as usize], folded[b as usize]) {
+                    (Const(a), Const(b)) => Const(a & b),
+                    (Const(false), _) | (_, Const(false)) => Const(false),
+                    (Const(true), other) | (other, Const(true)) => other,
+                    (Wire(a), Wire(b)) if a == b => Wire(a),
+                    (Wire(a), Wire(b)) => Wire(rebuilder.push(Gate::And(a, b))),
+                },
+            };
+            folded.push(value);
+        }
+
+        let outputs = self
+            .output_gates()
+            .iter()
+            .map(|output| rebuilder.wire(folded[*output as usize]))
+            .collect();
+
+        *self = Circuit::new(rebuilder.gates, outputs);
+        before.saturating_sub(self.gates().len())
+    }
+
+    fn dedup_gates(&mut self) -> usize {
+        let before = self.gates().len();
+        let mut gates: Vec<Gate> = Vec::with_capacity(before);
+        let mut remap: Vec<GateIndex> = Vec::with_capacity(before);
+        let mut seen: HashMap<GateKey, GateIndex> = HashMap::new();
+
+        for gate in self.gates() {
+            let key = match *gate {
+                Gate::InContrib | Gate::InEval => {
+                    remap.push(gates.len() as GateIndex);
+                    gates.push(if *gate == Gate::InEval {
+                        Gate::InEval
+                    } else {
+                        Gate::InContrib
+                    });
+                    continue;
+                }
+                Gate::Xor(a, b) => {
+                    let (a, b) = (remap[a as usize], remap[b as usize]);
+                    GateKey::Xor(a.min(b), a.max(b))
+                }
+                Gate::And(a, b) => {
+                    let (a, b) = (remap[a as usize], remap[b as usize]);
+                    GateKey::And(a.min(b), a.max(b))
+                }
+                Gate::Not(a) => GateKey::Not(remap[a as usize]),
+            };
+
+            let index = *seen.entry(key).or_insert_with(|| {
+                gates.push(key.into());
+                (gates.len() - 1) as GateIndex
+            });
+            remap.push(index);
+        }
+
+        let outputs = self
+            .output_gates()
+            .iter()
+            .map(|output| remap[*output as usize])
+            .collect();
+
+        *self = Circuit::new(gates, outputs);
+        before - self.gates().len()
+    }
+
+    fn prune_dead_gates(&mut self) -> usize {
+        let before = self.gates().len();
+
+        // operands always precede their gate, so one backward sweep marks every live gate
+        let mut live = vec![false; before];
+        for output in self.output_gates() {
+            live[*output as usize] = true;
+        }
+        for (index, gate) in self.gates().iter().enumerate().rev() {
+            match *gate {
+                Gate::InContrib | Gate::InEval => live[index] = true,
+                Gate::Xor(a, b) | Gate::And(a, b) if live[index] => {
+                    live[a as usize] = true;
+                    live[b as usize] = true;
+                }
+                Gate::Not(a) if live[index] => live[a as usize] = true,
+                _ => {}
+            }
+        }
+
+        let mut gates: Vec<Gate> = Vec::with_capacity(before);
+        let mut remap: Vec<GateIndex> = vec![0; before];
+        for (index, gate) in self.gates().iter().enumerate() {
+            if !live[index] {
+                continue;
+            }
+            remap[index] = gates.len() as GateIndex;
+            gates.push(match *gate {
+                Gate::InContrib => Gate::InContrib,
+                Gate::InEval => Gate::InEval,
+                Gate::Xor(a, b) => Gate::Xor(remap[a as usize], remap[b as usize]),
+                Gate::And(a, b) => Gate::And(remap[a as usize], remap[b as usize]),
+                Gate::Not(a) => Gate::Not(remap[a as usize]),
+            });
+        }
+
+        let outputs = self
+            .output_gates()
+            .iter()
+            .map(|output| remap[*output as usize])
+            .collect();
+
+        *self = Circuit::new(gates, outputs);
+        before - self.gates().len()
+    }
+
+    fn simplify_gates(&mut self) -> usize {
+        let mut rewrites = 0;
+        loop {
+            match simplify_pass(self) {
+                0 => break,
+                pass => rewrites += pass,
+            }
+        }
+        self.prune_dead_gates();
+        rewrites
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::get_executor;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::uint::GarbledUint8;
+
+    #[test]
+    fn test_optimize_constants_and_false() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(0b1011_0110_u8));
+        let zero = builder.literal(0, 8);
+        let output = builder.and(&a, &zero);
+        let mut circuit = builder.compile(&output);
+        let expected = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+
+        // the eight AND gates fold away, leaving the inputs and one zero wire
+        assert_eq!(circuit.optimize_constants(), 8);
+        assert_eq!(circuit.gates().len(), 9);
+        assert_eq!(circuit.and_gates(), 0);
+
+        let result = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_dedup_gates() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(0b1100_1010_u8));
+        let b = builder.input::<8>(&GarbledUint8::from(0b1010_0110_u8));
+
+        // `(a & b) ^ (b & a)`, where both AND groups are built separately
+        let left = builder.and(&a, &b);
+        let right = builder.and(&b, &a);
+        let output = builder.xor(&left, &right);
+        let mut circuit = builder.compile(&output);
+        assert_eq!(circuit.and_gates(), 16);
+        let expected = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+
+        assert_eq!(circuit.dedup_gates(), 8);
+        assert_eq!(circuit.and_gates(), 8);
+
+        let result = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_prune_dead_gates() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(23_u8));
+        let b = builder.input::<8>(&GarbledUint8::from(42_u8));
+
+        // the product is never used by the output
+        let _unused = builder.mul(&a, &b);
+        let sum = builder.add(&a, &b);
+        let mut circuit = builder.compile(&sum);
+        let before = circuit.gates().len();
+
+        // besides the multiplier, the adder's final carry (two ANDs and an XOR) is dead too
+        assert_eq!(circuit.prune_dead_gates(), before - (16 + 37 - 3));
+        assert_eq!(circuit.gates().len(), 16 + 34);
+        assert_eq!(circuit.and_gates(), 13);
+        assert_eq!(circuit.contrib_inputs(), 16);
+
+        let result = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+        assert_eq!(u8::from(GarbledUint8::new(result)), 23 + 42);
+    }
+
+    #[test]
+    fn test_optimize_constants_xor_false() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(0b1011_0110_u8));
+        let zero = builder.literal(0, 8);
+        let output = builder.xor(&a, &zero);
+        let mut circuit = builder.compile(&output);
+
+        // `a ^ 0` is `a`, and the zero wire is no longer needed at all
+        assert_eq!(circuit.optimize_constants(), 9);
+        assert_eq!(circuit.output_gates(), &(0..8).collect::<Vec<GateIndex>>());
+
+        let result = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+        assert_eq!(u8::from(GarbledUint8::new(result)), 0b1011_0110);
+    }
+
+    #[test]
+    fn test_simplify_gates() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(0b1011_0110_u8));
+        let zero = builder.zero_wire();
+        let one = builder.one_wire();
+
+        let not = builder.push_not(&a[0]);
+        let outputs = vec![
+            builder.push_not(&not),
+            builder.push_xor(&a[1], &zero),
+            builder.push_xor(&one, &a[2]),
+            builder.push_and(&a[3], &one),
+            builder.push_and(&a[4], &a[4]),
+            builder.push_xor(&a[5], &a[5]),
+        ];
+        let mut circuit = builder.compile(&outputs.into());
+        let before = circuit.gates().len();
+        let expected = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+
+        assert_eq!(circuit.simplify_gates(), 6);
+        // the inputs, the zero wire and `NOT(a[2])`
+        assert!(circuit.gates().len() < before);
+        assert_eq!(circuit.gates().len(), 10);
+        assert_eq!(circuit.and_gates(), 0);
+        // a second run finds nothing to rewrite
+        assert_eq!(circuit.simplify_gates(), 0);
+
+        let result = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::executor::{core_circuit, ExecError};
+use rayon::prelude::*;
+use tandem::{Circuit, Gate, GateIndex};
+
+/// A [`Circuit`] with its gates grouped into topological layers, for evaluating wide circuits
+/// on several threads.
+///
+/// A layer holds the gates whose operands all lie in earlier layers, so the gates within a
+/// layer are independent of each other. The layering is computed once, when the
+/// `LayeredCircuit` is created, and reused by every evaluation. So is the result of a circuit
+/// whose outputs do not depend on its inputs, which evaluation then returns without visiting
+/// any gate.
+///
+/// Evaluation happens in the clear: both parties' inputs must be known locally. It computes
+/// exactly the same outputs as running the circuit through the executor.
+#[derive(Debug, Clone)]
+pub struct LayeredCircuit {
+    circuit: Circuit,
+    layers: Vec<Vec<GateIndex>>,
+    constant_output: Option<Vec<bool>>,
+}
+
+impl LayeredCircuit {
+    /// Layers the gates of `circuit`, after checking that every gate reads only wires
+    /// assigned before it and that every output is a wire of the circuit.
+    pub fn new(circuit: Circuit) -> Result<Self, ExecError> {
+        let constant_output = core_circuit(&circuit)?
+            .constant_output()
+            .map(<[bool]>::to_vec);
+
+        // operands always precede their gate, so one forward pass assigns every layer
+        let mut depths: Vec<usize> = Vec::with_capacity(circuit.gates().len());
+        let mut layers: Vec<Vec<GateIndex>> = vec![];
+        for (index, gate) in circuit.gates().iter().enumerate() {
+            let depth = match gate {
+                Gate::InContrib | Gate::InEval => {
+                    depths.push(0);
+                    continue;
+                }
+                Gate::Not(a) => depths[*a as usize] + 1,
+                Gate::Xor(a, b) | Gate::And(a, b) => {
+                    depths[*a as usize].max(depths[*b as usize]) + 1
+                }
+            };
+            depths.push(depth);
+            if layers.len() < depth {
+                layers.resize_with(depth, Vec::new);
+            }
+            layers[depth - 1].push(index as GateIndex);
+        }
+
+        Ok(LayeredCircuit {
+            circuit,
+            layers,
+            constant_output,
+        })
+    }
+
+    pub fn circuit(&self) -> &Circuit {
+        &self.circuit
+    }
+
+    /// Returns the gate layers, excluding the inputs. Gates within a layer are in circuit
+    /// order.
+    pub fn layers(&self) -> &[Vec<GateIndex>] {
+        &self.layers
+    }
+
+    /// Returns the outputs when they are the same for every input, see
+    /// [`CircuitExecution::constant_output`](crate::executor::CircuitExecution::constant_output).
+    pub fn constant_output(&self) -> Option<&[bool]> {
+        self.constant_output.as_deref()
+    }
+
+    /// Evaluates the circuit in the clear, spreading the gates of each layer across the rayon
+    /// thread pool. Returns the value of every output wire, or
+    /// [`ExecError::InputLengthMismatch`] when either party's inputs do not match the
+    /// circuit's.
+    pub fn execute_parallel(
+        &self,
+        input_contributor: &[bool],
+        input_evaluator: &[bool],
+    ) -> Result<Vec<bool>, ExecError> {
+        let gates = self.circuit.gates();
+        for (inputs, expected) in [
+            (input_contributor, self.circuit.contrib_inputs()),
+            (input_evaluator, self.circuit.eval_inputs()),
+        ] {
+            if inputs.len() != expected {
+                return Err(ExecError::InputLengthMismatch {
+                    expected,
+                    got: inputs.len(),
+                });
+            }
+        }
+
+        if let Some(outputs) = &self.constant_output {
+            return Ok(outputs.clone());
+        }
+
+        // inputs are consumed in circuit order, separately for each party
+        let mut wires = vec![false; gates.len()];
+        let mut contributor = input_contributor.iter();
+        let mut evaluator = input_evaluator.iter();
+        for (index, gate) in gates.iter().enumerate() {
+            match gate {
+                Gate::InContrib => wires[index] = *contributor.next().unwrap(),
+                Gate::InEval => wires[index] = *evaluator.next().unwrap(),
+                _ => {}
+            }
+        }
+
+        for layer in &self.layers {
+            let values: Vec<bool> = layer
+                .par_iter()
+                .map(|index| match gates[*index as usize] {
+                    Gate::Xor(a, b) => wires[a as usize] ^ wires[b as usize],
+                    Gate::And(a, b) => wires[a as usize] & wires[b as usize],
+                    Gate::Not(a) => !wires[a as usize],
+                    Gate::InContrib | Gate::InEval => unreachable!("inputs are not layered"),
+                })
+                .collect();
+            for (index, value) in layer.iter().zip(values) {
+                wires[*index as usize] = value;
+            }
+        }
+
+        Ok(self
+            .circuit
+            .output_gates()
+            .iter()
+            .map(|output| wires[*output as usize])
+            .collect())
+    }
+}
+
+impl TryFrom<Circuit> for LayeredCircuit {
+    type Error = ExecError;
+
+    fn try_from(circuit: Circuit) -> Result<Self, ExecError> {
+        LayeredCircuit::new(circuit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::get_executor;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::operations::circuits::types::GateIndexVec;
+    use crate::uint::GarbledUint32;
+
+    #[test]
+    fn test_execute_parallel_wide_adders() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let mut output = GateIndexVec::default();
+        let mut expected = vec![];
+        for i in 0..64_u32 {
+            let a = i.wrapping_mul(0x9e37_79b9);
+            let b = i.wrapping_mul(0x85eb_ca6b) ^ 0xdead_beef;
+            let a_wires = builder.input(&GarbledUint32::from(a));
+            let b_wires = builder.input(&GarbledUint32::from(b));
+            let sum = builder.add(&a_wires, &b_wires);
+            output.push_all(&sum);
+            expected.push(a.wrapping_add(b));
+        }
+        let circuit = builder.compile(&output);
+        let serial = get_executor()
+            .execute(&circuit, builder.inputs(), &[])
+            .unwrap();
+
+        // the adders are independent, so each layer holds one gate group per adder
+        let layered = LayeredCircuit::new(circuit).unwrap();
+        assert!(layered.layers().iter().all(|layer| layer.len() >= 64));
+
+        let parallel = layered.execute_parallel(builder.inputs(), &[]).unwrap();
+        assert_eq!(parallel, serial);
+        for (chunk, expected) in parallel.chunks(32).zip(expected) {
+            assert_eq!(u32::from(GarbledUint32::new(chunk.to_vec())), expected);
+        }
+    }
+
+    #[test]
+    fn test_execute_parallel_input_mismatch() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint32::from(1_u32));
+        let layered = LayeredCircuit::new(builder.compile(&a)).unwrap();
+
+        assert_eq!(
+            layered.execute_parallel(&[true], &[]),
+            Err(ExecError::InputLengthMismatch {
+                expected: 32,
+                got: 1
+            })
+        );
+        assert_eq!(
+            layered.execute_parallel(&[true; 32], &[true]),
+            Err(ExecError::InputLengthMismatch {
+                expected: 0,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_layered_circuit_rejects_malformed_wiring() {
+        // the NOT gate reads a wire beyond the end of the circuit
+        let circuit = Circuit::new(vec![Gate::InContrib, Gate::Not(5)], vec![1]);
+        assert_eq!(
+            LayeredCircuit::new(circuit).unwrap_err(),
+            ExecError::WireOutOfBounds { wire: 5, gates: 2 }
+        );
+
+        let circuit = Circuit::new(vec![Gate::InContrib, Gate::Not(1)], vec![1]);
+        assert_eq!(
+            LayeredCircuit::try_from(circuit).unwrap_err(),
+            ExecError::UnassignedWire { gate: 1, wire: 1 }
+        );
+    }
+
+    #[test]
+    fn test_execute_parallel_constant_output() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint32::from(0xdead_beef_u32));
+        let zero = builder.literal(0, 32);
+        let masked = builder.and(&a, &zero);
+        let layered = LayeredCircuit::new(builder.compile(&masked)).unwrap();
+
+        assert_eq!(layered.constant_output(), Some(&[false; 32][..]));
+        for input in [0_u32, 1, u32::MAX] {
+            let bits = GarbledUint32::from(input).bits;
+            assert_eq!(
+                layered.execute_parallel(&bits, &[]).unwrap(),
+                vec![false; 32]
+            );
+        }
+        // the inputs are still checked
+        assert!(layered.execute_parallel(&[true], &[]).is_err());
+    }
+}
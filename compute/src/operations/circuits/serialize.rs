@@ -0,0 +1,133 @@
+use crate::executor::{core_circuit, from_core};
+use tandem::Circuit;
+
+// the format is shared with the `no_std` core, which executes circuits from these bytes
+pub use circuit_core::{CIRCUIT_FORMAT_VERSION, CIRCUIT_MAGIC};
+
+/// Versioned binary encoding of a compiled [`Circuit`], so a circuit compiled in one process
+/// can be executed in another.
+///
+/// The encoding is [`CIRCUIT_MAGIC`], the little-endian [`CIRCUIT_FORMAT_VERSION`], and the
+/// gate list, output wires and input counts, encoded and decoded by `circuit_core`.
+pub trait CircuitBytes: Sized {
+    /// Encodes the circuit with the current format version.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a gate or output reads a wire that is not assigned before it, which compiled
+    /// circuits never do.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Decodes a circuit, rejecting unknown formats and versions as well as gates or outputs
+    /// that reference out-of-range wires.
+    fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self>;
+}
+
+impl CircuitBytes for Circuit {
+    fn to_bytes(&self) -> Vec<u8> {
+        core_circuit(self)
+            .unwrap_or_else(|error| panic!("cannot serialize circuit: {error}"))
+            .to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(from_core(&circuit_core::Circuit::from_bytes(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[encrypted(compile)]
+    fn multi_arithmetic(a: u8, b: u8, c: u8, d: u8) -> (Circuit, Vec<bool>) {
+        let res = a * b;
+        let res = res + c;
+        res - d
+    }
+
+    #[test]
+    fn test_circuit_bytes_round_trip() -> anyhow::Result<()> {
+        let (circuit, inputs) = multi_arithmetic(2_u8, 5_u8, 3_u8, 4_u8);
+
+        let bytes = circuit.to_bytes();
+        assert_eq!(&bytes[..4], CIRCUIT_MAGIC);
+        let deserialized_circuit = Circuit::from_bytes(&bytes)?;
+
+        assert_eq!(circuit.gates(), deserialized_circuit.gates());
+        assert_eq!(circuit.output_gates(), deserialized_circuit.output_gates());
+        assert_eq!(circuit.and_gates(), deserialized_circuit.and_gates());
+        assert_eq!(circuit.eval_inputs(), deserialized_circuit.eval_inputs());
+        assert_eq!(
+            circuit.contrib_inputs(),
+            deserialized_circuit.contrib_inputs()
+        );
+
+        let expected = get_executor().execute(&circuit, &inputs, &[])?;
+        let result = get_executor().execute(&deserialized_circuit, &inputs, &[])?;
+        assert_eq!(result, expected);
+        assert_eq!(u8::from(GarbledUint8::new(result)), 2 * 5 + 3 - 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_circuit_bytes_match_core() -> anyhow::Result<()> {
+        let (circuit, inputs) = multi_arithmetic(7_u8, 9_u8, 200_u8, 1_u8);
+        let bytes = circuit.to_bytes();
+
+        let decoded = circuit_core::Circuit::from_bytes(&bytes)?;
+        assert_eq!(decoded.gates().len(), circuit.gates().len());
+        assert_eq!(decoded.output_gates(), circuit.output_gates().as_slice());
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(
+            decoded.execute_bits(&inputs)?,
+            circuit.execute_bits(&inputs)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_circuit_bytes_rejects_bad_header() {
+        let (circuit, _) = multi_arithmetic(0_u8, 0_u8, 0_u8, 0_u8);
+        let mut bytes = circuit.to_bytes();
+
+        let error = Circuit::from_bytes(&bytes[..3]).unwrap_err();
+        assert!(error.to_string().contains("magic"));
+
+        bytes[4] = 0xff;
+        let error = Circuit::from_bytes(&bytes).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("unsupported circuit format version"));
+    }
+
+    #[test]
+    fn test_circuit_bytes_rejects_out_of_range_wires() {
+        let circuit = Circuit::new(
+            vec![Gate::InContrib, Gate::InContrib, Gate::And(0, 1)],
+            vec![2],
+        );
+        let bytes = circuit.to_bytes();
+
+        // the second operand of the AND gate, which ends the gate list
+        let mut operand = bytes.clone();
+        let at = bytes.len() - 3 * 8 - 4 - 8 - 4;
+        operand[at] = 7;
+        let error = Circuit::from_bytes(&operand).unwrap_err();
+        assert!(error.to_string().contains("wire 7 is out of range"));
+
+        let mut output = bytes;
+        let at = output.len() - 3 * 8 - 4;
+        output[at] = 3;
+        let error = Circuit::from_bytes(&output).unwrap_err();
+        assert!(error.to_string().contains("wire 3 is out of range"));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot serialize circuit")]
+    fn test_circuit_bytes_rejects_unassigned_wires() {
+        Circuit::new(vec![Gate::InContrib, Gate::Not(1)], vec![1]).to_bytes();
+    }
+}
@@ -0,0 +1,216 @@
+use tandem::{Circuit, Gate, GateIndex};
+
+/// Cost metrics for a compiled [`Circuit`], used to compare circuit implementations.
+///
+/// With free-XOR garbling, XOR and NOT gates cost no communication, so [`and_count`] is the
+/// figure that dominates execution time.
+///
+/// [`and_count`]: CircuitStats::and_count
+pub trait CircuitStats {
+    /// Returns the number of logic gates, not counting input gates.
+    fn gate_count(&self) -> usize;
+
+    /// Returns the number of AND gates.
+    fn and_count(&self) -> usize;
+
+    /// Returns the number of XOR gates.
+    fn xor_count(&self) -> usize;
+
+    /// Returns the length of the longest path of logic gates from any input to any output wire.
+    fn depth(&self) -> usize;
+
+    /// Returns the largest number of AND gates on any path from an input to an output wire.
+    ///
+    /// XOR and NOT gates are free under free-XOR garbling, so this, rather than
+    /// [`depth`](CircuitStats::depth), is the depth that bounds the rounds of protocols which
+    /// evaluate AND gates interactively.
+    fn and_depth(&self) -> usize;
+
+    /// Returns the gates along one longest path from an input to an output wire, starting with
+    /// the input gate, so that the path holds [`depth`](CircuitStats::depth) logic gates.
+    ///
+    /// Among outputs of equal depth the first one is chosen, and at each gate the path follows
+    /// its first operand unless the second one is deeper. A circuit without outputs has an
+    /// empty path.
+    fn critical_path(&self) -> Vec<GateIndex>;
+}
+
+/// Returns the depth of every gate, indexed by gate, counting only the gates for which
+/// `counts` holds.
+fn gate_depths(circuit: &Circuit, counts: fn(&Gate) -> bool) -> Vec<usize> {
+    // gates only reference earlier gates, so a single forward pass computes every depth
+    let mut depths: Vec<usize> = Vec::with_capacity(circuit.gates().len());
+    for gate in circuit.gates() {
+        let depth = match gate {
+            Gate::InContrib | Gate::InEval => 0,
+            Gate::Not(a) => depths[*a as usize],
+            Gate::Xor(a, b) | Gate::And(a, b) => depths[*a as usize].max(depths[*b as usize]),
+        };
+        depths.push(depth + counts(gate) as usize);
+    }
+    depths
+}
+
+fn is_logic_gate(gate: &Gate) -> bool {
+    !matches!(gate, Gate::InContrib | Gate::InEval)
+}
+
+/// Returns the greatest of `depths` over the output wires.
+fn output_depth(circuit: &Circuit, depths: &[usize]) -> usize {
+    circuit
+        .output_gates()
+        .iter()
+        .map(|output| depths[*output as usize])
+        .max()
+        .unwrap_or(0)
+}
+
+impl CircuitStats for Circuit {
+    fn gate_count(&self) -> usize {
+        self.gates()
+            .iter()
+            .filter(|gate| is_logic_gate(gate))
+            .count()
+    }
+
+    fn and_count(&self) -> usize {
+        self.gates()
+            .iter()
+            .filter(|gate| matches!(gate, Gate::And(_, _)))
+            .count()
+    }
+
+    fn xor_count(&self) -> usize {
+        self.gates()
+            .iter()
+            .filter(|gate| matches!(gate, Gate::Xor(_, _)))
+            .count()
+    }
+
+    fn depth(&self) -> usize {
+        output_depth(self, &gate_depths(self, is_logic_gate))
+    }
+
+    fn and_depth(&self) -> usize {
+        output_depth(
+            self,
+            &gate_depths(self, |gate| matches!(gate, Gate::And(_, _))),
+        )
+    }
+
+    fn critical_path(&self) -> Vec<GateIndex> {
+        let depths = gate_depths(self, is_logic_gate);
+        let depth_of = |gate: &GateIndex| depths[*gate as usize];
+        // `max_by_key` keeps the last maximum, so reverse to break ties towards the first output
+        let Some(mut gate) = self
+            .output_gates()
+            .iter()
+            .rev()
+            .copied()
+            .max_by_key(depth_of)
+        else {
+            return vec![];
+        };
+
+        let mut path = vec![gate];
+        loop {
+            gate = match self.gates()[gate as usize] {
+                Gate::InContrib | Gate::InEval => break,
+                Gate::Not(a) => a,
+                Gate::Xor(a, b) | Gate::And(a, b) => {
+                    if depth_of(&b) > depth_of(&a) {
+                        b
+                    } else {
+                        a
+                    }
+                }
+            };
+            path.push(gate);
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::operations::circuits::types::GateIndexVec;
+    use crate::uint::GarbledUint8;
+
+    #[test]
+    fn test_u8_addition_stats() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(3_u8));
+        let b = builder.input::<8>(&GarbledUint8::from(5_u8));
+        let sum = builder.add(&a, &b);
+        let circuit = builder.compile(&sum);
+
+        // a half adder for bit 0, then seven full adders of 3 XOR and 2 AND gates each
+        assert_eq!(circuit.gate_count(), 37);
+        assert_eq!(circuit.and_count(), 15);
+        assert_eq!(circuit.xor_count(), 22);
+
+        // the carry chain adds two gates per bit, and the top sum bit reads the last carry
+        assert_eq!(circuit.depth(), 14);
+        // each full adder puts one AND gate on the carry chain
+        assert_eq!(circuit.and_depth(), 7);
+    }
+
+    #[test]
+    fn test_inputs_only_stats() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(3_u8));
+        let circuit = builder.compile(&a);
+
+        assert_eq!(circuit.gate_count(), 0);
+        assert_eq!(circuit.depth(), 0);
+        assert_eq!(circuit.critical_path(), vec![0]);
+    }
+
+    #[test]
+    fn test_and_depth_ignores_xor_chains() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(0b1011_0010_u8));
+        let b = builder.input::<8>(&GarbledUint8::from(1_u8));
+
+        // parity of `a` through a chain of seven XOR gates, then a single AND
+        let mut parity = a[0];
+        for i in 1..8 {
+            parity = builder.push_xor(&parity, &a[i]);
+        }
+        let output = builder.push_and(&parity, &b[0]);
+        let circuit = builder.compile(&GateIndexVec::new(vec![output]));
+
+        assert_eq!(circuit.depth(), 8);
+        assert_eq!(circuit.and_depth(), 1);
+    }
+
+    #[test]
+    fn test_critical_path_follows_carry_chain() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input::<8>(&GarbledUint8::from(3_u8));
+        let b = builder.input::<8>(&GarbledUint8::from(5_u8));
+        let sum = builder.add(&a, &b);
+        let circuit = builder.compile(&sum);
+
+        let path = circuit.critical_path();
+        assert_eq!(path.len(), circuit.depth() + 1);
+
+        // the path starts at the lowest bit of an operand and ends at the top sum bit
+        assert!(path[0] == a[0] || path[0] == b[0]);
+        assert_eq!(path.last(), circuit.output_gates().last());
+
+        // each gate on the path reads the one before it
+        for pair in path.windows(2) {
+            let operands = match circuit.gates()[pair[1] as usize] {
+                Gate::Not(x) => vec![x],
+                Gate::Xor(x, y) | Gate::And(x, y) => vec![x, y],
+                Gate::InContrib | Gate::InEval => vec![],
+            };
+            assert!(operands.contains(&pair[0]));
+        }
+    }
+}
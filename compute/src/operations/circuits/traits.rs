@@ -105,6 +105,22 @@ pub trait CircuitExecutor {
     /// A vector of elements representing the difference of the input vectors.
     fn sub(&mut self, a: &Self::TypeVec, b: &Self::TypeVec) -> Self::TypeVec;
 
+    /// Performs a two's complement negation of a vector of `Type`.
+    ///
+    /// # Parameters
+    ///
+    /// - `a`: A reference to the vector of elements to negate.
+    ///
+    /// # Returns
+    ///
+    /// A vector of elements representing `!a + 1`, wrapping like `wrapping_neg`. The default
+    /// implementation subtracts `a` from the zero `a ^ a`, so existing implementors keep
+    /// compiling.
+    fn neg(&mut self, a: &Self::TypeVec) -> Self::TypeVec {
+        let zero = self.xor(a, a);
+        self.sub(&zero, a)
+    }
+
     /// Performs a multiplication operation on two vectors of `Type`.
     ///
     /// # Parameters
@@ -126,7 +142,8 @@ pub trait CircuitExecutor {
     ///
     /// # Returns
     ///
-    /// A vector of elements representing the quotient of the division.
+    /// A vector of elements representing the quotient of the division. Dividing by zero yields
+    /// a quotient with every bit set.
     fn div(&mut self, a: &Self::TypeVec, b: &Self::TypeVec) -> Self::TypeVec;
 
     /// Performs a remainder operation on two vectors of `Type`.
@@ -138,7 +155,8 @@ pub trait CircuitExecutor {
     ///
     /// # Returns
     ///
-    /// A vector of elements representing the remainder of the division.
+    /// A vector of elements representing the remainder of the division. Dividing by zero
+    /// yields the dividend unchanged.
     fn rem(&mut self, a: &Self::TypeVec, b: &Self::TypeVec) -> Self::TypeVec;
 
     /// Checks the equality of two vectors of `Type`.
@@ -30,6 +30,16 @@ impl GateIndexVec {
         self.0.iter()
     }
 
+    /// Returns the wire of bit `index`, least significant first, if the group is that wide.
+    pub fn get(&self, index: usize) -> Option<GateIndex> {
+        self.0.get(index).copied()
+    }
+
+    /// Returns the wires of the group, least significant bit first.
+    pub fn as_slice(&self) -> &[GateIndex] {
+        &self.0
+    }
+
     pub fn with_capacity(capacity: usize) -> Self {
         Self(Vec::with_capacity(capacity))
     }
@@ -106,6 +116,13 @@ impl From<&GateIndexVec> for GateIndexVec {
     }
 }
 
+/// Concatenates the wire groups of an array, e.g. for a circuit returning `[u8; K]`.
+impl<const K: usize> From<[GateIndexVec; K]> for GateIndexVec {
+    fn from(elements: [GateIndexVec; K]) -> Self {
+        Self(elements.into_iter().flat_map(|element| element.0).collect())
+    }
+}
+
 impl From<GateIndexVec> for GateIndex {
     fn from(vec: GateIndexVec) -> Self {
         vec.0[0]
@@ -0,0 +1,143 @@
+use crate::executor::operands;
+use std::iter::Enumerate;
+use std::slice;
+use tandem::{Circuit, Gate, GateIndex};
+
+/// The kind of a gate, without its operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GateKind {
+    /// An input bit of the contributor.
+    InContrib,
+    /// An input bit of the evaluator.
+    InEval,
+    Xor,
+    And,
+    Not,
+}
+
+/// One gate of a circuit together with the wire it drives. Every gate drives exactly one
+/// wire, numbered by the gate's position, and only reads wires of earlier gates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GateView<'a> {
+    /// The wire the gate drives.
+    pub wire: GateIndex,
+    pub gate: &'a Gate,
+}
+
+impl GateView<'_> {
+    pub fn kind(&self) -> GateKind {
+        match self.gate {
+            Gate::InContrib => GateKind::InContrib,
+            Gate::InEval => GateKind::InEval,
+            Gate::Xor(_, _) => GateKind::Xor,
+            Gate::And(_, _) => GateKind::And,
+            Gate::Not(_) => GateKind::Not,
+        }
+    }
+
+    /// Returns the wires the gate reads: none for inputs, one for NOT and two for XOR and AND.
+    pub fn inputs(&self) -> impl Iterator<Item = GateIndex> {
+        operands(self.gate).into_iter().flatten()
+    }
+
+    pub fn is_input(&self) -> bool {
+        matches!(self.kind(), GateKind::InContrib | GateKind::InEval)
+    }
+}
+
+/// Iterator over the gates of a circuit in topological order, see
+/// [`CircuitVisit::gate_views`].
+#[derive(Debug, Clone)]
+pub struct GateViews<'a>(Enumerate<slice::Iter<'a, Gate>>);
+
+impl<'a> Iterator for GateViews<'a> {
+    type Item = GateView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(wire, gate)| GateView {
+            wire: wire as GateIndex,
+            gate,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for GateViews<'_> {}
+
+/// Read access to the structure of a compiled [`Circuit`], for analysis passes outside the
+/// crate. The gates themselves are [`Circuit::gates`], in topological order.
+pub trait CircuitVisit {
+    /// Returns every gate with the wire it drives, in topological order.
+    fn gate_views(&self) -> GateViews<'_>;
+
+    /// Returns the wires of the input gates of both parties, in order.
+    fn inputs(&self) -> Vec<GateIndex>;
+
+    /// Returns the output wires, in order. The same as [`Circuit::output_gates`].
+    fn outputs(&self) -> &[GateIndex];
+
+    /// Returns the number of wires, which is the number of gates including input gates.
+    fn wire_count(&self) -> usize;
+}
+
+impl CircuitVisit for Circuit {
+    fn gate_views(&self) -> GateViews<'_> {
+        GateViews(self.gates().iter().enumerate())
+    }
+
+    fn inputs(&self) -> Vec<GateIndex> {
+        self.gate_views()
+            .filter(GateView::is_input)
+            .map(|view| view.wire)
+            .collect()
+    }
+
+    fn outputs(&self) -> &[GateIndex] {
+        self.output_gates()
+    }
+
+    fn wire_count(&self) -> usize {
+        self.gates().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::circuits::builder::WRK17CircuitBuilder;
+    use crate::operations::circuits::stats::CircuitStats;
+    use crate::operations::circuits::traits::CircuitExecutor;
+    use crate::uint::GarbledUint8;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_tally_gate_kinds() {
+        let mut builder = WRK17CircuitBuilder::default();
+        let a = builder.input(&GarbledUint8::from(3_u8));
+        let b = builder.input(&GarbledUint8::from(5_u8));
+        let sum = builder.add(&a, &b);
+        let output = builder.not(&sum);
+        let circuit = builder.compile(&output);
+
+        let mut tally: HashMap<GateKind, usize> = HashMap::new();
+        for view in circuit.gate_views() {
+            assert!(view.inputs().all(|input| input < view.wire));
+            *tally.entry(view.kind()).or_default() += 1;
+        }
+        assert_eq!(tally[&GateKind::InContrib], 16);
+        assert_eq!(tally.get(&GateKind::InEval), None);
+        assert_eq!(tally[&GateKind::And], circuit.and_count());
+        assert_eq!(tally[&GateKind::Xor], circuit.xor_count());
+        assert_eq!(
+            tally[&GateKind::Xor] + tally[&GateKind::And] + tally[&GateKind::Not],
+            circuit.gate_count()
+        );
+
+        assert_eq!(circuit.inputs(), (0..16).collect::<Vec<_>>());
+        assert_eq!(circuit.outputs(), output.as_slice());
+        assert_eq!(circuit.wire_count(), circuit.gate_views().len());
+    }
+}
@@ -0,0 +1,329 @@
+//! A plaintext reference for [`GarbledUint`] arithmetic.
+//!
+//! [`PlainUint<N>`] holds the same little-endian bits as a `GarbledUint<N>` but computes its
+//! operators directly in Rust, bit by bit, with the semantics of the circuits the builder
+//! generates: arithmetic wraps at `N` bits, division by zero gives an all-ones quotient and
+//! leaves the dividend as the remainder, and shifts by `N` or more clear every bit. Running
+//! the same code over both types cross-checks a circuit against a result that never went
+//! through circuit compilation.
+
+use crate::uint::GarbledUint;
+use std::cmp::Ordering;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Rem, Shl, Shr, Sub};
+
+/// An `N`-bit unsigned integer evaluated in plaintext, see the [module docs](self).
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PlainUint<const N: usize> {
+    bits: Vec<bool>,
+}
+
+impl<const N: usize> PlainUint<N> {
+    /// Creates a value from its bits, least significant bit first. Missing high bits are zero
+    /// and bits past `N` are dropped.
+    pub fn from_bits(bits: &[bool]) -> Self {
+        let mut bits: Vec<bool> = bits.iter().copied().take(N).collect();
+        bits.resize(N, false);
+        PlainUint { bits }
+    }
+
+    /// Returns the bits of the value, least significant bit first.
+    pub fn to_bits(&self) -> Vec<bool> {
+        self.bits.clone()
+    }
+
+    pub fn zero() -> Self {
+        PlainUint {
+            bits: vec![false; N],
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.bits.iter().all(|bit| !bit)
+    }
+
+    /// Adds with a carry into the lowest bit, wrapping at `N` bits.
+    fn add_with_carry(&self, rhs: &Self, mut carry: bool) -> Self {
+        let bits = self
+            .bits
+            .iter()
+            .zip(&rhs.bits)
+            .map(|(a, b)| {
+                let sum = a ^ b ^ carry;
+                carry = (a & b) | (carry & (a ^ b));
+                sum
+            })
+            .collect();
+        PlainUint { bits }
+    }
+
+    /// Restoring long division, one quotient bit per step from the top, as the circuit does.
+    fn div_rem(&self, rhs: &Self) -> (Self, Self) {
+        if rhs.is_zero() {
+            return (!Self::zero(), self.clone());
+        }
+        let mut quotient = Self::zero();
+        let mut remainder = Self::zero();
+        for i in (0..N).rev() {
+            // the remainder stays below the divisor, so shifting it left loses no set bit
+            remainder = remainder << 1;
+            remainder.bits[0] = self.bits[i];
+            if remainder >= *rhs {
+                remainder = remainder - rhs.clone();
+                quotient.bits[i] = true;
+            }
+        }
+        (quotient, remainder)
+    }
+}
+
+impl<const N: usize> Default for PlainUint<N> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+/// Renders the width and the bits as a binary number, e.g. `PlainUint<4>(0b0101)`.
+impl<const N: usize> std::fmt::Debug for PlainUint<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PlainUint<{}>(0b", N)?;
+        for bit in self.bits.iter().rev() {
+            write!(f, "{}", *bit as u8)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<const N: usize> Ord for PlainUint<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.bits.iter().rev().cmp(other.bits.iter().rev())
+    }
+}
+
+impl<const N: usize> PartialOrd for PlainUint<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const N: usize> From<GarbledUint<N>> for PlainUint<N> {
+    fn from(value: GarbledUint<N>) -> Self {
+        PlainUint::from_bits(&value.bits)
+    }
+}
+
+impl<const N: usize> From<PlainUint<N>> for GarbledUint<N> {
+    fn from(value: PlainUint<N>) -> Self {
+        GarbledUint::new(value.bits)
+    }
+}
+
+macro_rules! plain_primitive {
+    ($($primitive:ty),*) => {
+        $(
+            impl<const N: usize> From<$primitive> for PlainUint<N> {
+                fn from(value: $primitive) -> Self {
+                    GarbledUint::<N>::from(value).into()
+                }
+            }
+
+            impl<const N: usize> From<PlainUint<N>> for $primitive {
+                fn from(value: PlainUint<N>) -> Self {
+                    GarbledUint::<N>::from(value).into()
+                }
+            }
+        )*
+    };
+}
+
+plain_primitive!(bool, u8, u16, u32, u64, u128);
+
+impl<const N: usize> Add for PlainUint<N> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.add_with_carry(&rhs, false)
+    }
+}
+
+impl<const N: usize> Sub for PlainUint<N> {
+    type Output = Self;
+
+    /// `a - b` is `a + !b + 1` in two's complement.
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.add_with_carry(&!rhs, true)
+    }
+}
+
+impl<const N: usize> Mul for PlainUint<N> {
+    type Output = Self;
+
+    /// Shift-and-add over the bits of `rhs`.
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut product = Self::zero();
+        for (i, bit) in rhs.bits.iter().enumerate() {
+            if *bit {
+                product = product + (self.clone() << i);
+            }
+        }
+        product
+    }
+}
+
+impl<const N: usize> Div for PlainUint<N> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.div_rem(&rhs).0
+    }
+}
+
+impl<const N: usize> Rem for PlainUint<N> {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self::Output {
+        self.div_rem(&rhs).1
+    }
+}
+
+macro_rules! plain_bitwise {
+    ($($trait:ident, $method:ident, $op:tt;)*) => {
+        $(
+            impl<const N: usize> $trait for PlainUint<N> {
+                type Output = Self;
+
+                fn $method(self, rhs: Self) -> Self::Output {
+                    let bits = self.bits.iter().zip(&rhs.bits).map(|(a, b)| a $op b).collect();
+                    PlainUint { bits }
+                }
+            }
+        )*
+    };
+}
+
+plain_bitwise! {
+    BitAnd, bitand, &;
+    BitOr, bitor, |;
+    BitXor, bitxor, ^;
+}
+
+impl<const N: usize> Not for PlainUint<N> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        PlainUint {
+            bits: self.bits.iter().map(|bit| !bit).collect(),
+        }
+    }
+}
+
+impl<const N: usize> Shl<usize> for PlainUint<N> {
+    type Output = Self;
+
+    fn shl(self, amount: usize) -> Self::Output {
+        let bits = (0..N)
+            .map(|i| i >= amount && self.bits[i - amount])
+            .collect();
+        PlainUint { bits }
+    }
+}
+
+impl<const N: usize> Shr<usize> for PlainUint<N> {
+    type Output = Self;
+
+    fn shr(self, amount: usize) -> Self::Output {
+        let bits = (0..N)
+            .map(|i| amount < N - i && self.bits[i + amount])
+            .collect();
+        PlainUint { bits }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uint::{GarbledUint128, GarbledUint8};
+
+    const SAMPLES: [u8; 8] = [0, 1, 2, 7, 100, 128, 200, 255];
+
+    #[test]
+    fn test_plain_matches_native_wrapping() {
+        for a in 0..=255_u8 {
+            for b in SAMPLES {
+                let (x, y) = (PlainUint::<8>::from(a), PlainUint::<8>::from(b));
+                assert_eq!(u8::from(x.clone() + y.clone()), a.wrapping_add(b));
+                assert_eq!(u8::from(x.clone() - y.clone()), a.wrapping_sub(b));
+                assert_eq!(u8::from(x.clone() * y.clone()), a.wrapping_mul(b));
+                assert_eq!(u8::from(x.clone() & y.clone()), a & b);
+                assert_eq!(u8::from(x.clone() | y.clone()), a | b);
+                assert_eq!(u8::from(x.clone() ^ y.clone()), a ^ b);
+                assert_eq!(x.cmp(&y), a.cmp(&b));
+                if b != 0 {
+                    assert_eq!(u8::from(x.clone() / y.clone()), a / b);
+                    assert_eq!(u8::from(x % y), a % b);
+                }
+            }
+            let x = PlainUint::<8>::from(a);
+            assert_eq!(u8::from(!x.clone()), !a);
+            for amount in 0..10 {
+                assert_eq!(
+                    u8::from(x.clone() << amount),
+                    a.checked_shl(amount as u32).unwrap_or(0)
+                );
+                assert_eq!(
+                    u8::from(x.clone() >> amount),
+                    a.checked_shr(amount as u32).unwrap_or(0)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_plain_matches_circuits() {
+        for (a, b) in [(13_u8, 7_u8), (200, 100), (255, 255), (9, 0), (0, 3)] {
+            let (x, y) = (GarbledUint8::from(a), GarbledUint8::from(b));
+            let (p, q) = (PlainUint::<8>::from(a), PlainUint::<8>::from(b));
+
+            assert_eq!(
+                PlainUint::from(x.clone() + y.clone()),
+                p.clone() + q.clone()
+            );
+            assert_eq!(
+                PlainUint::from(x.clone() - y.clone()),
+                p.clone() - q.clone()
+            );
+            assert_eq!(
+                PlainUint::from(x.clone() * y.clone()),
+                p.clone() * q.clone()
+            );
+            // including division by zero, which does not panic in a circuit
+            assert_eq!(
+                PlainUint::from(x.clone() / y.clone()),
+                p.clone() / q.clone()
+            );
+            assert_eq!(
+                PlainUint::from(x.clone() % y.clone()),
+                p.clone() % q.clone()
+            );
+            assert_eq!(
+                PlainUint::from(x.clone() & y.clone()),
+                p.clone() & q.clone()
+            );
+            assert_eq!(PlainUint::from(x ^ y), p ^ q);
+        }
+    }
+
+    #[test]
+    fn test_plain_wide_values() {
+        let a = PlainUint::<128>::from(u128::MAX - 5);
+        let b = PlainUint::<128>::from(10_u128);
+        assert_eq!(u128::from(a.clone() + b.clone()), 4);
+        assert_eq!(u128::from(a.clone() / b.clone()), (u128::MAX - 5) / 10);
+
+        let garbled = GarbledUint128::from(a.clone());
+        assert_eq!(PlainUint::from(garbled), a);
+        assert_eq!(
+            format!("{:?}", PlainUint::<4>::from_bits(&[true, false, true])),
+            "PlainUint<4>(0b0101)"
+        );
+    }
+}
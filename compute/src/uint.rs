@@ -20,7 +20,7 @@ pub type GarbledUint512 = GarbledUint<512>;
 pub type GarbledUint1024 = GarbledUint<1024>;
 
 // Define a new type Uint<N>
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Clone, Serialize, Deserialize, Default)]
 pub struct GarbledUint<const N: usize> {
     pub bits: Vec<bool>,
     _phantom: PhantomData<[bool; N]>,
@@ -44,9 +44,25 @@ impl<const N: usize> GarbledUint<N> {
     }
 }
 
+/// Renders the decoded value: in decimal up to 128 bits, and as `0x` followed by the
+/// big-endian hex bytes for wider values.
 impl<const N: usize> Display for GarbledUint<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", u128::from(self.clone()))
+        if N <= 128 {
+            return write!(f, "{}", u128::from(self.clone()));
+        }
+        write!(f, "0x")?;
+        for byte in self.to_bytes_le().iter().rev() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the width and the decoded value, e.g. `GarbledUint<8>(42)`.
+impl<const N: usize> std::fmt::Debug for GarbledUint<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GarbledUint<{}>({})", N, self)
     }
 }
 
@@ -83,6 +99,57 @@ impl<const N: usize> GarbledUint<N> {
             _phantom: PhantomData,
         }
     }
+
+    /// Creates a value from exactly `N` bits, least significant bit first.
+    pub fn from_bits(bits: &[bool]) -> Self {
+        assert_eq!(bits.len(), N, "The number of bits must be {}", N);
+        GarbledUint::new(bits.to_vec())
+    }
+
+    /// Returns the bits of the value, least significant bit first.
+    pub fn to_bits(&self) -> Vec<bool> {
+        self.bits.clone()
+    }
+
+    /// Creates a value from `N.div_ceil(8)` little-endian bytes. When `N` is not a multiple of
+    /// 8, the unused top bits of the last byte are ignored.
+    pub fn from_bytes_le(bytes: &[u8]) -> Self {
+        assert_eq!(
+            bytes.len(),
+            N.div_ceil(8),
+            "GarbledUint<{}> is encoded in {} bytes",
+            N,
+            N.div_ceil(8)
+        );
+
+        let bits = (0..N).map(|i| (bytes[i / 8] >> (i % 8)) & 1 == 1).collect();
+        GarbledUint::new(bits)
+    }
+
+    /// Encodes the value as `N.div_ceil(8)` little-endian bytes. When `N` is not a multiple of
+    /// 8, the unused top bits of the last byte are zero.
+    pub fn to_bytes_le(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; N.div_ceil(8)];
+        for (i, &bit) in self.bits.iter().take(N).enumerate() {
+            if bit {
+                bytes[i / 8] |= 1 << (i % 8);
+            }
+        }
+        bytes
+    }
+}
+
+/// A struct laid out as a fixed-width integer, for passing records into circuits. Its fields
+/// are concatenated in declaration order, the first field in the lowest bits.
+///
+/// `#[derive(CircuitInput)]` implements this trait for structs of primitive integer and
+/// `bool` fields, together with conversions to and from `GarbledUint<WIDTH>`.
+pub trait CircuitInput {
+    /// The total width of the fields in bits.
+    const WIDTH: usize;
+
+    /// The name, width in bits and signedness of every field, in declaration order.
+    const FIELDS: &'static [(&'static str, usize, bool)];
 }
 
 impl<const N: usize> From<GarbledInt<N>> for GarbledUint<N> {
@@ -286,6 +353,71 @@ impl<const N: usize> From<GarbledUint<N>> for u128 {
     }
 }
 
+// Signed values are stored as their two's complement bit pattern
+impl<const N: usize> From<i8> for GarbledUint<N> {
+    fn from(value: i8) -> Self {
+        GarbledInt::<N>::from(value).into()
+    }
+}
+
+// Signed values are stored as their two's complement bit pattern
+impl<const N: usize> From<i16> for GarbledUint<N> {
+    fn from(value: i16) -> Self {
+        GarbledInt::<N>::from(value).into()
+    }
+}
+
+// Signed values are stored as their two's complement bit pattern
+impl<const N: usize> From<i32> for GarbledUint<N> {
+    fn from(value: i32) -> Self {
+        GarbledInt::<N>::from(value).into()
+    }
+}
+
+// Signed values are stored as their two's complement bit pattern
+impl<const N: usize> From<i64> for GarbledUint<N> {
+    fn from(value: i64) -> Self {
+        GarbledInt::<N>::from(value).into()
+    }
+}
+
+// Signed values are stored as their two's complement bit pattern
+impl<const N: usize> From<i128> for GarbledUint<N> {
+    fn from(value: i128) -> Self {
+        GarbledInt::<N>::from(value).into()
+    }
+}
+
+impl<const N: usize> From<GarbledUint<N>> for i8 {
+    fn from(guint: GarbledUint<N>) -> Self {
+        GarbledInt::<N>::from(guint).into()
+    }
+}
+
+impl<const N: usize> From<GarbledUint<N>> for i16 {
+    fn from(guint: GarbledUint<N>) -> Self {
+        GarbledInt::<N>::from(guint).into()
+    }
+}
+
+impl<const N: usize> From<GarbledUint<N>> for i32 {
+    fn from(guint: GarbledUint<N>) -> Self {
+        GarbledInt::<N>::from(guint).into()
+    }
+}
+
+impl<const N: usize> From<GarbledUint<N>> for i64 {
+    fn from(guint: GarbledUint<N>) -> Self {
+        GarbledInt::<N>::from(guint).into()
+    }
+}
+
+impl<const N: usize> From<GarbledUint<N>> for i128 {
+    fn from(guint: GarbledUint<N>) -> Self {
+        GarbledInt::<N>::from(guint).into()
+    }
+}
+
 impl<const N: usize> From<usize> for GarbledUint<N> {
     fn from(value: usize) -> Self {
         assert!(
@@ -356,9 +488,64 @@ mod tests {
         assert_eq!(GarbledUint::from(uint128), guint);
     }
 
+    #[test]
+    fn test_garbled_uint_from_signed() {
+        let guint = GarbledUint::<8>::from(-2_i8);
+        assert_eq!(u8::from(guint.clone()), 0b1111_1110);
+        assert_eq!(i8::from(guint), -2);
+    }
+
     #[test]
     fn test_garbled_uint_from_bool() {
         let uint = GarbledUint::<1>::from(true);
         assert!(bool::from(uint));
     }
+
+    #[test]
+    fn test_bytes_le_round_trip() {
+        fn round_trip<const N: usize>(bytes: &[u8]) {
+            let uint = GarbledUint::<N>::from_bytes_le(bytes);
+            assert_eq!(uint.len(), N);
+            assert_eq!(GarbledUint::<N>::from_bytes_le(&uint.to_bytes_le()), uint);
+            assert_eq!(GarbledUint::<N>::from_bits(&uint.to_bits()), uint);
+        }
+
+        round_trip::<8>(&[0xa5]);
+        round_trip::<12>(&[0x34, 0x0b]);
+        round_trip::<16>(&[0x34, 0x12]);
+        round_trip::<24>(&[0x56, 0x34, 0x12]);
+        round_trip::<64>(&0x0123_4567_89ab_cdef_u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_bytes_le_bit_alignment() {
+        let uint = GarbledUint::<16>::from_bytes_le(&[0x34, 0x12]);
+        assert_eq!(u16::from(uint), 0x1234);
+
+        let uint = GarbledUint::<64>::from(0x0123_4567_89ab_cdef_u64);
+        assert_eq!(uint.to_bytes_le(), 0x0123_4567_89ab_cdef_u64.to_le_bytes());
+
+        // the top four bits of the last byte are masked off
+        let uint = GarbledUint::<12>::from_bytes_le(&[0x34, 0xfb]);
+        assert_eq!(u16::from(uint.clone()), 0xb34);
+        assert_eq!(uint.to_bytes_le(), vec![0x34, 0x0b]);
+    }
+
+    #[test]
+    fn test_garbled_uint_formatting() {
+        let value = GarbledUint::<8>::from(42_u8);
+        assert_eq!(value.to_string(), "42");
+        assert_eq!(format!("{:?}", value), "GarbledUint<8>(42)");
+
+        let value = GarbledUint::<128>::from(u128::MAX);
+        assert_eq!(value.to_string(), u128::MAX.to_string());
+
+        let value = GarbledUint::<160>::from_bytes_le(&[
+            0x01, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff,
+        ]);
+        assert_eq!(
+            format!("{:?}", value),
+            "GarbledUint<160>(0xff00000000000000000000000000000000000201)"
+        );
+    }
 }
@@ -58,12 +58,15 @@ fn test_uint_larger_comparison() {
 
 // test signed integer comparison with different sizes
 #[test]
-#[ignore = "reason: int with negative values not implemented"]
 fn test_int_comparison() {
     let d8: GarbledInt8 = (-100_i8).into();
     let e8: GarbledInt8 = 100_i8.into();
 
     assert!(d8 < e8);
+
+    let f16: GarbledInt16 = (-1000_i16).into();
+    let g16: GarbledInt16 = (-2000_i16).into();
+    assert!(f16 > g16);
 }
 
 #[test]
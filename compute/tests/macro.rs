@@ -522,6 +522,84 @@ fn test_macro_bool_return() {
     assert!(!result);
 }
 
+#[test]
+fn test_macro_bit() {
+    #[encrypted(execute)]
+    fn sign_bit(a: u8) -> bool {
+        a.bit(7)
+    }
+
+    #[encrypted(execute)]
+    fn low_bits_differ(a: u8) -> bool {
+        a.bit(0) ^ a.bit(1)
+    }
+
+    for a in [0_u8, 1, 2, 3, 127, 128, 200, 255] {
+        assert_eq!(sign_bit(a), a >> 7 == 1, "{a}");
+        assert_eq!(low_bits_differ(a), (a ^ (a >> 1)) & 1 == 1, "{a}");
+    }
+}
+
+#[test]
+fn test_macro_in_range() {
+    #[encrypted(execute)]
+    fn is_percentage(a: u8) -> bool {
+        a.in_range(0, 100)
+    }
+
+    #[encrypted(execute)]
+    fn clamp_invalid(a: u8) -> u8 {
+        if a.in_range(18, 65) {
+            a
+        } else {
+            0
+        }
+    }
+
+    for a in 0..=255_u8 {
+        assert_eq!(is_percentage(a), a <= 100, "{a}");
+        let expected = if (18..=65).contains(&a) { a } else { 0 };
+        assert_eq!(clamp_invalid(a), expected, "{a}");
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, CircuitInput)]
+struct Point {
+    x: u8,
+    y: u8,
+}
+
+#[test]
+fn test_macro_record_input() {
+    // the fields are concatenated in declaration order, `x` in the low byte
+    let encoded = GarbledUint::<16>::from(Point { x: 0x34, y: 0x12 });
+    assert_eq!(u16::from(encoded.clone()), 0x1234);
+    assert_eq!(Point::from(encoded), Point { x: 0x34, y: 0x12 });
+
+    #[encrypted(execute)]
+    fn sum(p: Point) -> u8 {
+        p.x + p.y
+    }
+
+    #[encrypted(execute)]
+    fn above(p: Point, threshold: u8) -> bool {
+        p.y > threshold
+    }
+
+    // the fields are added at their own width, so the sum wraps before it is halved
+    #[encrypted(execute)]
+    fn mean(p: Point) -> u8 {
+        (p.x + p.y) / 2
+    }
+
+    assert_eq!(sum(Point { x: 20, y: 22 }), 42);
+    assert_eq!(sum(Point { x: 200, y: 100 }), 200_u8.wrapping_add(100));
+    assert_eq!(mean(Point { x: 20, y: 22 }), 21);
+    assert_eq!(mean(Point { x: 200, y: 100 }), 200_u8.wrapping_add(100) / 2);
+    assert!(above(Point { x: 0, y: 9 }, 8));
+    assert!(!above(Point { x: 255, y: 8 }, 8));
+}
+
 // div
 #[test]
 fn test_macro_div() {
@@ -619,6 +697,51 @@ fn test_macro_embedded_constants() {
     assert_eq!(result, 30_u8);
 }
 
+#[test]
+fn test_macro_named_constants() {
+    #[encrypted(execute)]
+    fn low_nibble(a: u8) -> u8 {
+        const MASK: u8 = 0x0F;
+        const OFFSET: u8 = MASK + 1;
+        let masked = a & MASK;
+        masked + OFFSET
+    }
+
+    #[encrypted(compile)]
+    fn masked(a: u8) -> (Circuit, Vec<bool>) {
+        const MASK: u8 = 0x0F;
+        a & MASK
+    }
+
+    // a field of the same name as a constant is not replaced
+    #[encrypted(execute)]
+    fn shifted(p: Point) -> u8 {
+        const x: u8 = 3;
+        p.x + x
+    }
+
+    // a local shadows the loop variable for the rest of the iteration
+    #[encrypted(execute)]
+    fn even_offsets(a: u8) -> u8 {
+        let mut total = a;
+        for i in 0..3 {
+            let i = i * 2;
+            total = total + i;
+        }
+        total
+    }
+
+    assert_eq!(low_nibble(0xAB_u8), 0x0B + 16);
+    assert_eq!(low_nibble(0x30_u8), 16);
+    assert_eq!(shifted(Point { x: 4, y: 0 }), 7);
+    assert_eq!(even_offsets(10_u8), 16);
+
+    // the constant is wired in, so the only input is `a`
+    let (circuit, inputs) = masked(0xAB_u8);
+    assert_eq!(inputs.len(), 8);
+    assert_eq!(circuit.contrib_inputs() + circuit.eval_inputs(), 8);
+}
+
 #[test]
 fn test_order_of_operations() {
     #[encrypted(execute)]
@@ -647,6 +770,41 @@ fn test_order_of_operations2() {
     assert_eq!(result, 900);
 }
 
+#[test]
+fn test_nested_binary_operands() {
+    #[encrypted(execute)]
+    fn nested(a: u8, b: u8, c: u8) -> u8 {
+        a + (b * c) - (a ^ b) / (c | 1) + (b & c) % 4
+    }
+
+    for (a, b, c) in [(1_u8, 2_u8, 3_u8), (200, 17, 9), (0, 255, 255), (13, 0, 8)] {
+        let expected = a
+            .wrapping_add(b.wrapping_mul(c))
+            .wrapping_sub((a ^ b) / (c | 1))
+            .wrapping_add((b & c) % 4);
+        assert_eq!(nested(a, b, c), expected, "nested({a}, {b}, {c})");
+    }
+}
+
+// an `$e:expr` fragment reaches the attribute as an invisible `Expr::Group`
+macro_rules! scaled_sum {
+    ($name:ident, $a:ident, $b:ident, $c:ident, $sum:expr) => {
+        #[encrypted(execute)]
+        fn $name($a: u8, $b: u8, $c: u8) -> u8 {
+            $sum * $c
+        }
+    };
+}
+
+scaled_sum!(grouped_sum, a, b, c, a + b);
+
+#[test]
+fn test_order_of_operations_macro_fragment() {
+    // the fragment keeps its grouping, so this is `(a + b) * c`
+    assert_eq!(grouped_sum(3_u8, 4_u8, 5_u8), 35);
+    assert_eq!(grouped_sum(100_u8, 50_u8, 2_u8), 150_u8.wrapping_mul(2));
+}
+
 #[test]
 fn test_add_assign() {
     #[encrypted(execute)]
@@ -785,6 +943,41 @@ fn test_if_elif_else() {
     assert_eq!(result, 40_u8);
 }
 
+#[test]
+fn test_if_else_if_chain() {
+    #[encrypted(execute)]
+    fn ordering(a: u8, b: u8) -> u8 {
+        if a > b {
+            2
+        } else if a == b {
+            1
+        } else {
+            0
+        }
+    }
+
+    assert_eq!(ordering(9_u8, 4_u8), 2);
+    assert_eq!(ordering(7_u8, 7_u8), 1);
+    assert_eq!(ordering(4_u8, 9_u8), 0);
+
+    #[encrypted(execute)]
+    fn distance(a: u8, b: u8) -> u8 {
+        let mut d = 0;
+        if a > b {
+            d = a - b;
+        } else if a == b {
+            d = 0;
+        } else {
+            d = b - a;
+        }
+        d
+    }
+
+    assert_eq!(distance(9_u8, 4_u8), 5);
+    assert_eq!(distance(7_u8, 7_u8), 0);
+    assert_eq!(distance(4_u8, 9_u8), 5);
+}
+
 #[test]
 fn test_nested_if() {
     #[encrypted(execute)]
@@ -1310,3 +1503,1405 @@ fn test_macro_if() {
     let result = if_test(a);
     assert_eq!(result, 100);
 }
+
+#[test]
+fn test_macro_for_loop() {
+    #[encrypted(execute)]
+    fn sum_loop(a: u8) -> u8 {
+        let mut acc = 0;
+        for i in 0..4 {
+            acc = acc + a + i;
+        }
+        acc
+    }
+
+    let a = 5_u8;
+    let result = sum_loop(a);
+    assert_eq!(result, 4 * a + 6);
+}
+
+#[test]
+fn test_macro_for_loop_gate_count() {
+    #[encrypted(compile)]
+    fn sum_loop(a: u8) -> (Circuit, Vec<bool>) {
+        let mut acc = a;
+        for _ in 1..=3 {
+            acc = acc + a;
+        }
+        acc
+    }
+
+    #[encrypted(compile)]
+    fn sum_chain(a: u8) -> (Circuit, Vec<bool>) {
+        a + a + a + a
+    }
+
+    let (loop_circuit, loop_inputs) = sum_loop(7_u8);
+    let (chain_circuit, _) = sum_chain(7_u8);
+    assert_eq!(loop_circuit.gates().len(), chain_circuit.gates().len());
+
+    let result = get_executor()
+        .execute(&loop_circuit, &loop_inputs, &[])
+        .unwrap();
+    let result: u8 = GarbledUint::<8>::new(result).into();
+    assert_eq!(result, 28);
+}
+
+#[test]
+fn test_macro_signed_subtraction() {
+    #[encrypted(execute)]
+    fn signed_sub(a: i8, b: i8) -> i8 {
+        a - b
+    }
+
+    let result = signed_sub(3_i8, 5_i8);
+    assert_eq!(result, -2_i8);
+
+    let result = signed_sub(-100_i8, 50_i8);
+    assert_eq!(result, (-100_i8).wrapping_sub(50));
+}
+
+#[test]
+fn test_macro_signed_comparison() {
+    #[encrypted(execute)]
+    fn signed_min(a: i16, b: i16) -> i16 {
+        if a < b {
+            a
+        } else {
+            b
+        }
+    }
+
+    assert_eq!(signed_min(-5_i16, 3_i16), -5);
+    assert_eq!(signed_min(3_i16, -5_i16), -5);
+    assert_eq!(signed_min(-300_i16, -7_i16), -300);
+
+    #[encrypted(execute)]
+    fn signed_ge(a: i32, b: i32) -> bool {
+        a >= b
+    }
+
+    assert!(signed_ge(0_i32, -1_i32));
+    assert!(!signed_ge(-2_i32, 1_i32));
+}
+
+#[test]
+fn test_macro_shift_constant() {
+    #[encrypted(execute)]
+    fn shift_left(x: u8) -> u8 {
+        x << 3
+    }
+
+    #[encrypted(execute)]
+    fn shift_right(x: u8) -> u8 {
+        x >> 3
+    }
+
+    let x = 0b1011_0111_u8;
+    assert_eq!(shift_left(x), x << 3);
+    assert_eq!(shift_right(x), x >> 3);
+}
+
+#[test]
+fn test_macro_shift_constant_wires() {
+    #[encrypted(compile)]
+    fn shift_left(x: u8) -> (Circuit, Vec<bool>) {
+        x << 3
+    }
+
+    let (circuit, inputs) = shift_left(0xff_u8);
+
+    // the vacated low bits are all wired to the same derived constant-false wire
+    let low_bits = &circuit.output_gates()[..3];
+    assert!(low_bits.iter().all(|bit| *bit == low_bits[0]));
+    assert!(matches!(
+        circuit.gates()[low_bits[0] as usize],
+        Gate::Xor(l, r) if l == r
+    ));
+    assert_eq!(inputs.len(), 8);
+
+    let result = get_executor().execute(&circuit, &inputs, &[]).unwrap();
+    assert!(result[..3].iter().all(|bit| !bit));
+    assert!(result[3..].iter().all(|bit| *bit));
+}
+
+#[test]
+fn test_macro_shift_dynamic() {
+    #[encrypted(execute)]
+    fn shift(x: u8, s: u8) -> u8 {
+        (x >> s) << s
+    }
+
+    assert_eq!(shift(0b1111_0101_u8, 2_u8), 0b1111_0100);
+    assert_eq!(shift(0b1111_0101_u8, 4_u8), 0b1111_0000);
+
+    #[encrypted(execute)]
+    fn shift_left(x: u8, s: u8) -> u8 {
+        x << s
+    }
+
+    for s in 0..12_u8 {
+        let expected = 0b1011_0011_u8.checked_shl(s.into()).unwrap_or(0);
+        assert_eq!(shift_left(0b1011_0011_u8, s), expected, "shift by {s}");
+    }
+}
+
+#[test]
+fn test_macro_shift_signed() {
+    #[encrypted(execute)]
+    fn arithmetic_shift(x: i8) -> i8 {
+        x >> 2
+    }
+
+    assert_eq!(arithmetic_shift(-16_i8), -4);
+    assert_eq!(arithmetic_shift(16_i8), 4);
+}
+
+#[test]
+fn test_macro_if_without_else() {
+    #[encrypted(execute)]
+    fn conditional_increment(a: u8, b: u8) -> u8 {
+        let mut x = a;
+        if a > b {
+            x = x + 1;
+        }
+        x
+    }
+
+    assert_eq!(conditional_increment(5_u8, 3_u8), 6);
+    assert_eq!(conditional_increment(3_u8, 5_u8), 3);
+}
+
+#[test]
+fn test_macro_if_without_else_multiple_assignments() {
+    #[encrypted(execute)]
+    fn conditional_update(a: u8, b: u8) -> u8 {
+        let mut x = a;
+        let mut y = b;
+        if a == b {
+            x = x * 2;
+            y = 0;
+        }
+        x + y
+    }
+
+    assert_eq!(conditional_update(4_u8, 4_u8), 8);
+    assert_eq!(conditional_update(4_u8, 5_u8), 9);
+}
+
+#[test]
+fn test_macro_match_select_inputs() {
+    #[encrypted(execute)]
+    fn select(sel: u8, a: u8, b: u8, c: u8) -> u8 {
+        match sel {
+            0 => a,
+            1 => b,
+            _ => c,
+        }
+    }
+
+    let (a, b, c) = (11_u8, 22_u8, 33_u8);
+    assert_eq!(select(0_u8, a, b, c), a);
+    assert_eq!(select(1_u8, a, b, c), b);
+    assert_eq!(select(2_u8, a, b, c), c);
+    assert_eq!(select(255_u8, a, b, c), c);
+}
+
+#[test]
+fn test_macro_constant_operand_allocates_no_input() {
+    #[encrypted(compile)]
+    fn identity(a: u8) -> (Circuit, Vec<bool>) {
+        a
+    }
+
+    #[encrypted(compile)]
+    fn double(a: u8) -> (Circuit, Vec<bool>) {
+        a * 2
+    }
+
+    #[encrypted(compile)]
+    fn offset(a: u8) -> (Circuit, Vec<bool>) {
+        5 + a
+    }
+
+    let (_, identity_inputs) = identity(21_u8);
+    let (double_circuit, double_inputs) = double(21_u8);
+    let (offset_circuit, offset_inputs) = offset(21_u8);
+    assert_eq!(double_inputs.len(), identity_inputs.len());
+    assert_eq!(offset_inputs.len(), identity_inputs.len());
+
+    // multiplying by a constant power of two is pure rewiring, no AND gates
+    assert!(!double_circuit
+        .gates()
+        .iter()
+        .any(|gate| matches!(gate, Gate::And(_, _))));
+
+    let result = get_executor()
+        .execute(&double_circuit, &double_inputs, &[])
+        .unwrap();
+    assert_eq!(u8::from(GarbledUint8::new(result)), 42);
+
+    let result = get_executor()
+        .execute(&offset_circuit, &offset_inputs, &[])
+        .unwrap();
+    assert_eq!(u8::from(GarbledUint8::new(result)), 26);
+}
+
+#[test]
+fn test_macro_mixed_input_types() {
+    #[encrypted(execute)]
+    fn widen_add(a: u8, b: u16) -> u16 {
+        a + b
+    }
+
+    assert_eq!(widen_add(200_u8, 1000_u16), 1200);
+    assert_eq!(widen_add(255_u8, 65535_u16), 254);
+
+    #[encrypted(execute)]
+    fn signed_widen_add(a: i8, b: i16) -> i16 {
+        a + b
+    }
+
+    assert_eq!(signed_widen_add(-3_i8, 1000_i16), 997);
+    assert_eq!(signed_widen_add(-128_i8, -1_i16), -129);
+
+    #[encrypted(execute)]
+    fn select(flag: bool, a: u8, b: u8) -> u8 {
+        if flag {
+            a
+        } else {
+            b
+        }
+    }
+
+    assert_eq!(select(true, 7_u8, 9_u8), 7);
+    assert_eq!(select(false, 7_u8, 9_u8), 9);
+}
+
+#[test]
+fn test_macro_tuple_return() {
+    #[encrypted(execute)]
+    fn sum_and_difference(a: u8, b: u8) -> (u8, u8) {
+        (a + b, a - b)
+    }
+
+    assert_eq!(sum_and_difference(9_u8, 4_u8), (13, 5));
+    assert_eq!(sum_and_difference(4_u8, 9_u8), (13, 251));
+
+    #[encrypted(execute)]
+    fn divmod(a: u8, b: u8) -> (u8, u8, bool) {
+        (a / b, a % b, a < b)
+    }
+
+    assert_eq!(divmod(47_u8, 5_u8), (9, 2, false));
+    assert_eq!(divmod(3_u8, 5_u8), (0, 3, true));
+}
+
+#[test]
+fn test_macro_tuple_return_compiler() {
+    #[encrypted(compile)]
+    fn sum_and_difference(a: u8, b: u8) -> (Circuit, Vec<bool>) {
+        (a + b, a - b)
+    }
+
+    let (circuit, inputs) = sum_and_difference(9_u8, 4_u8);
+
+    // the elements are concatenated in order, 8 bits each
+    assert_eq!(circuit.output_gates().len(), 16);
+    let result = get_executor().execute(&circuit, &inputs, &[]).unwrap();
+    assert_eq!(u8::from(GarbledUint8::new(result[..8].to_vec())), 13);
+    assert_eq!(u8::from(GarbledUint8::new(result[8..].to_vec())), 5);
+}
+
+#[test]
+fn test_macro_tuple_element_widths() {
+    // each element is decoded at its declared width, whatever the width of the circuit
+    #[encrypted(execute)]
+    fn split(a: u16, b: u8) -> (u8, u16, bool) {
+        (b + 1, a + b, a < b)
+    }
+
+    assert_eq!(split(1000_u16, 255_u8), (0, 1255, false));
+    assert_eq!(split(3_u16, 4_u8), (5, 7, true));
+
+    #[encrypted(execute)]
+    fn widen(a: i8) -> (i16, i8) {
+        (a, -a)
+    }
+
+    assert_eq!(widen(-5_i8), (-5, 5));
+
+    // compile mode reports the widths with `output_widths`
+    #[encrypted(compile, output_widths)]
+    fn split_compiled(a: u16, b: u8) -> (u8, u16, bool) {
+        (b + 1, a + b, a < b)
+    }
+
+    let (circuit, inputs, widths) = split_compiled(1000_u16, 255_u8);
+    assert_eq!(widths, vec![8, 16, 1]);
+    assert_eq!(circuit.output_gates().len(), 25);
+    let bits = circuit.execute_bits(&inputs).unwrap();
+    assert_eq!(u8::from(GarbledUint8::new(bits[..8].to_vec())), 0);
+    assert_eq!(u16::from(GarbledUint16::new(bits[8..24].to_vec())), 1255);
+    assert!(!bits[24]);
+
+    #[encrypted(compile, layout, output_widths)]
+    fn pair(a: u8) -> [u8; 2] {
+        [a, a + 1]
+    }
+
+    let (circuit, _, layout, widths) = pair(7_u8);
+    assert_eq!(layout.len(), 1);
+    assert_eq!(widths, vec![8, 8]);
+    assert_eq!(circuit.output_gates().len(), 16);
+}
+
+#[test]
+fn test_macro_neg() {
+    #[encrypted(execute)]
+    fn double_neg(a: u8) -> u8 {
+        -(-a)
+    }
+
+    for value in [0_u8, 1, 2, 127, 128, 200, 255] {
+        assert_eq!(double_neg(value), value);
+    }
+
+    #[encrypted(execute)]
+    fn neg(a: i8) -> i8 {
+        -a
+    }
+
+    assert_eq!(neg(0_i8), 0);
+    assert_eq!(neg(5_i8), -5);
+    assert_eq!(neg(i8::MIN), i8::MIN);
+
+    #[encrypted(execute)]
+    fn add_negative_constant(a: i16) -> i16 {
+        a + -300
+    }
+
+    assert_eq!(add_negative_constant(100_i16), -200);
+}
+
+#[test]
+fn test_macro_array_input() {
+    #[encrypted(execute)]
+    fn sum(values: [u8; 4]) -> u8 {
+        values[0] + values[1] + values[2] + values[3]
+    }
+
+    assert_eq!(sum([1_u8, 2, 3, 4]), 10);
+    assert_eq!(sum([200_u8, 100, 0, 1]), 45);
+
+    #[encrypted(execute)]
+    fn dot(values: [u8; 4], weight: u8) -> u8 {
+        let mut acc = 0;
+        for i in 0..4 {
+            acc = acc + values[i] * weight;
+        }
+        acc
+    }
+
+    assert_eq!(dot([1_u8, 2, 3, 4], 3_u8), 30);
+}
+
+#[test]
+fn test_macro_array_iteration() {
+    #[encrypted(execute)]
+    fn checksum(xs: [u8; 16]) -> u8 {
+        let mut checksum = 0;
+        {
+            // shadows the parameter only until the end of the block
+            let xs = xs[0];
+            checksum = checksum ^ xs;
+        }
+        for x in xs {
+            checksum = checksum ^ x;
+        }
+        checksum
+    }
+
+    let values: [u8; 16] = std::array::from_fn(|i| 200 + i as u8);
+    let expected = values[1..]
+        .iter()
+        .fold(0, |checksum, value| checksum ^ value);
+    assert_eq!(checksum(values), expected);
+    assert_eq!(checksum([0; 16]), 0);
+
+    #[encrypted(execute)]
+    fn max_of(xs: &[u8; 4]) -> u8 {
+        let mut best = 0;
+        for x in xs {
+            best = if x > best { x } else { best };
+        }
+        best
+    }
+
+    assert_eq!(max_of(&[3_u8, 250, 7, 12]), 250);
+}
+
+#[test]
+fn test_macro_array_sum() {
+    #[encrypted(execute)]
+    fn total(xs: [u8; 8]) -> u16 {
+        xs.sum()
+    }
+
+    let values = [200_u8, 17, 255, 3, 99, 128, 64, 1];
+    assert_eq!(
+        total(values),
+        values.iter().map(|value| *value as u16).sum::<u16>()
+    );
+
+    #[encrypted(execute)]
+    fn wrapping_total(xs: [u8; 3]) -> u8 {
+        xs.sum()
+    }
+
+    assert_eq!(wrapping_total([200_u8, 100, 1]), 45);
+}
+
+#[test]
+fn test_macro_component() {
+    #[encrypted(component)]
+    fn add3(a: u8, b: u8, c: u8) -> u8 {
+        a + b + c
+    }
+
+    #[encrypted(component)]
+    fn scaled_sum(a: u8, b: u8) -> u8 {
+        add3(a, b, 1) * 2
+    }
+
+    #[encrypted(execute)]
+    fn outer(a: u8, b: u8) -> u8 {
+        let t = scaled_sum(a, b);
+        t - add3(a, a, a)
+    }
+
+    assert_eq!(outer(5_u8, 7_u8), (5 + 7 + 1) * 2 - 15);
+
+    #[encrypted(compile)]
+    fn outer_compiled(a: u8, b: u8) -> (Circuit, Vec<bool>) {
+        scaled_sum(a, b)
+    }
+
+    // the components are spliced into one circuit over the caller's inputs
+    let (circuit, inputs) = outer_compiled(5_u8, 7_u8);
+    assert_eq!(circuit.contrib_inputs(), inputs.len());
+    let result = get_executor().execute(&circuit, &inputs, &[]).unwrap();
+    assert_eq!(u8::from(GarbledUint8::new(result)), 26);
+}
+
+#[test]
+#[should_panic(expected = "component `double` takes `x` as 16 bits, found 8")]
+fn test_macro_component_argument_width() {
+    #[encrypted(component)]
+    fn double(x: u16) -> u16 {
+        x + x
+    }
+
+    #[encrypted(execute)]
+    fn narrowed(a: u16) -> u16 {
+        double(a as u8)
+    }
+
+    narrowed(3_u16);
+}
+
+#[test]
+fn test_macro_compound_assignment() {
+    #[encrypted(execute)]
+    fn checksum(a: u8, b: u8, c: u8, d: u8) -> u8 {
+        let mut acc = a;
+        acc ^= b;
+        acc ^= c;
+        acc ^= d;
+        acc
+    }
+
+    assert_eq!(checksum(0x12, 0x34, 0x56, 0x78), 0x12 ^ 0x34 ^ 0x56 ^ 0x78);
+
+    #[encrypted(execute)]
+    fn compound(a: u8, b: u8) -> u8 {
+        let mut x = a;
+        x += b;
+        x *= 3;
+        x -= a;
+        x <<= 1;
+        x |= b;
+        x &= 0xfe;
+        x >>= 2;
+        x
+    }
+
+    let (a, b) = (9_u8, 5_u8);
+    let mut x = a;
+    x = x.wrapping_add(b);
+    x = x.wrapping_mul(3);
+    x = x.wrapping_sub(a);
+    x <<= 1;
+    x |= b;
+    x &= 0xfe;
+    x >>= 2;
+    assert_eq!(compound(a, b), x);
+}
+
+#[test]
+fn test_macro_rotate() {
+    #[encrypted(execute)]
+    fn rotate(a: u8, b: u8) -> u8 {
+        a.rotate_left(3) ^ b.rotate_right(1)
+    }
+
+    let (a, b) = (0b1001_0110_u8, 0b0000_0011_u8);
+    assert_eq!(rotate(a, b), a.rotate_left(3) ^ b.rotate_right(1));
+}
+
+#[test]
+fn test_macro_wrapping_methods() {
+    #[encrypted(execute)]
+    fn chained(a: u8, b: u8) -> u8 {
+        a.wrapping_add(b).rotate_left(2)
+    }
+
+    #[encrypted(execute)]
+    fn wrapping(a: u8, b: u8) -> u8 {
+        a.wrapping_mul(b).wrapping_sub(b)
+    }
+
+    for (a, b) in [(200_u8, 100_u8), (3, 5), (255, 255), (0, 1)] {
+        assert_eq!(chained(a, b), a.wrapping_add(b).rotate_left(2), "{a}, {b}");
+        assert_eq!(
+            wrapping(a, b),
+            a.wrapping_mul(b).wrapping_sub(b),
+            "{a}, {b}"
+        );
+    }
+}
+
+#[test]
+fn test_macro_saturating() {
+    #[encrypted(execute)]
+    fn clamp(a: u8, b: u8) -> u8 {
+        let high = a.saturating_add(b);
+        high.saturating_sub(b + b)
+    }
+
+    assert_eq!(clamp(250_u8, 10_u8), 255 - 20);
+    assert_eq!(clamp(5_u8, 10_u8), 0);
+
+    #[encrypted(execute)]
+    fn increment(a: u8) -> u8 {
+        a.saturating_add(1)
+    }
+
+    assert_eq!(increment(u8::MAX), u8::MAX);
+    assert_eq!(increment(41_u8), 42);
+}
+
+#[test]
+fn test_macro_bool_comparison_output() {
+    #[encrypted(execute)]
+    fn greater(a: u8, b: u8) -> bool {
+        a > b
+    }
+
+    assert!(greater(9_u8, 4_u8));
+    assert!(!greater(4_u8, 9_u8));
+
+    #[encrypted(execute)]
+    fn low_bit(a: u8, b: u8) -> bool {
+        a & b
+    }
+
+    assert!(low_bit(0b0111_u8, 0b0101_u8));
+    assert!(!low_bit(0b0110_u8, 0b0111_u8));
+
+    #[encrypted(compile)]
+    fn greater_compiled(a: u8, b: u8) -> (Circuit, Vec<bool>) {
+        a > b
+    }
+
+    // the comparison result is a single wire
+    let (circuit, inputs) = greater_compiled(9_u8, 4_u8);
+    assert_eq!(circuit.output_gates().len(), 1);
+    let result = get_executor().execute(&circuit, &inputs, &[]).unwrap();
+    assert_eq!(result, vec![true]);
+}
+
+#[test]
+fn test_macro_early_return() {
+    #[encrypted(execute)]
+    fn max(a: u8, b: u8) -> u8 {
+        if a > b {
+            return a;
+        }
+        b
+    }
+
+    assert_eq!(max(9_u8, 4_u8), 9);
+    assert_eq!(max(4_u8, 9_u8), 9);
+    assert_eq!(max(7_u8, 7_u8), 7);
+
+    #[encrypted(execute)]
+    fn clamp(a: u8, low: u8, high: u8) -> u8 {
+        if a < low {
+            return low;
+        } else if a > high {
+            return high;
+        }
+        let offset = a - low;
+        low + offset
+    }
+
+    assert_eq!(clamp(3_u8, 5_u8, 10_u8), 5);
+    assert_eq!(clamp(12_u8, 5_u8, 10_u8), 10);
+    assert_eq!(clamp(7_u8, 5_u8, 10_u8), 7);
+}
+
+#[test]
+fn test_macro_if_else_assignments() {
+    #[encrypted(execute)]
+    fn pick(a: u8, b: u8) -> u8 {
+        let mut x = a;
+        let mut y = b;
+        if a > b {
+            x = a - b;
+        } else {
+            y = b - a;
+        }
+        x + y
+    }
+
+    // each branch sees the values from before the `if`
+    assert_eq!(pick(9_u8, 4_u8), 9);
+    assert_eq!(pick(4_u8, 9_u8), 9);
+
+    #[encrypted(execute)]
+    fn spread(a: u8, b: u8) -> u8 {
+        let mut x = a;
+        if a > b {
+            // `t` is local to the branch, only `x` is selected after the `if`
+            let mut t = a;
+            t = t - b;
+            x = t;
+        } else {
+            x = x + b;
+        }
+        x
+    }
+
+    assert_eq!(spread(9_u8, 4_u8), 5);
+    assert_eq!(spread(4_u8, 9_u8), 13);
+}
+
+#[test]
+fn test_macro_count_ones() {
+    #[encrypted(execute)]
+    fn weight(a: u8) -> u8 {
+        a.count_ones()
+    }
+
+    for x in [0_u8, 1, 0b1011_0110, 0x7f, u8::MAX] {
+        assert_eq!(weight(x), x.count_ones() as u8);
+    }
+
+    #[encrypted(execute)]
+    fn distance(a: u16, b: u16) -> u16 {
+        (a ^ b).count_ones()
+    }
+
+    assert_eq!(distance(0xffff_u16, 0x0f0f_u16), 8);
+    assert_eq!(distance(0x1234_u16, 0x1234_u16), 0);
+}
+
+#[test]
+fn test_macro_leading_trailing_zeros() {
+    #[encrypted(execute)]
+    fn leading(a: u8) -> u8 {
+        a.leading_zeros()
+    }
+
+    #[encrypted(execute)]
+    fn trailing(a: u8) -> u8 {
+        a.trailing_zeros()
+    }
+
+    for x in [0_u8, 1, 0b0001_0100, 0x80, u8::MAX] {
+        assert_eq!(leading(x), x.leading_zeros() as u8);
+        assert_eq!(trailing(x), x.trailing_zeros() as u8);
+    }
+}
+
+#[test]
+fn test_macro_bounded_while() {
+    // Newton's method for the integer square root, converging within 8 steps for any `n`
+    // that fits in a byte
+    #[encrypted(execute)]
+    fn isqrt(n: u16) -> u16 {
+        let mut x = n;
+        #[circuit_loop(max = 8)]
+        while x * x > n {
+            x = (x + n / x) >> 1;
+        }
+        x
+    }
+
+    for n in [0_u16, 1, 2, 3, 4, 15, 16, 17, 99, 100, 200, 255] {
+        let mut expected = 0;
+        while (expected + 1) * (expected + 1) <= n {
+            expected += 1;
+        }
+        assert_eq!(isqrt(n), expected, "isqrt({n})");
+    }
+
+    // the bound stops the loop even though the condition still holds
+    #[encrypted(execute)]
+    fn count_to(a: u8) -> u8 {
+        let mut i = 0;
+        #[circuit_loop(max = 8)]
+        while i < a {
+            i = i + 1;
+        }
+        i
+    }
+
+    assert_eq!(count_to(5_u8), 5);
+    assert_eq!(count_to(100_u8), 8);
+}
+
+#[test]
+fn test_macro_input_layout() {
+    #[encrypted(compile, layout)]
+    fn widened(a: u8, b: u16) -> (Circuit, Vec<bool>, Vec<InputSpec>) {
+        a + b
+    }
+
+    let (circuit, inputs, layout) = widened(3_u8, 500_u16);
+    assert_eq!(
+        layout,
+        vec![
+            InputSpec {
+                name: Some("a".to_string()),
+                width: 8,
+                wires: 0..8,
+            },
+            InputSpec {
+                name: Some("b".to_string()),
+                width: 16,
+                wires: 8..24,
+            },
+        ]
+    );
+    assert_eq!(inputs.len(), 24);
+    assert_eq!(circuit.contrib_inputs(), 24);
+
+    // the ranges locate each parameter's bits in the input vector
+    let b = GarbledUint16::new(inputs[layout[1].wires.clone()].to_vec());
+    assert_eq!(u16::from(b), 500);
+
+    // without the flag a function returning three values still compiles to a circuit
+    #[encrypted(compile)]
+    fn spread(a: u8) -> (u8, u8, u8) {
+        (a, a + 1, a + 2)
+    }
+
+    let (circuit, inputs): (Circuit, Vec<bool>) = spread(7_u8);
+    assert_eq!(inputs.len(), 8);
+    assert_eq!(circuit.contrib_inputs(), 8);
+}
+
+#[test]
+fn test_macro_abs() {
+    #[encrypted(execute)]
+    fn distance(a: i8, b: i8) -> i8 {
+        (a - b).abs()
+    }
+
+    assert_eq!(distance(3_i8, 10_i8), 7);
+    assert_eq!(distance(10_i8, 3_i8), 7);
+    assert_eq!(distance(-5_i8, -5_i8), 0);
+    // `i8::MIN` has no positive counterpart and wraps like `wrapping_abs`
+    assert_eq!(distance(i8::MIN, 0_i8), i8::MIN);
+}
+
+#[test]
+fn test_macro_min_max() {
+    #[encrypted(execute)]
+    fn clamp(a: u8, low: u8, high: u8) -> u8 {
+        a.max(low).min(high)
+    }
+
+    assert_eq!(clamp(3_u8, 5_u8, 10_u8), 5);
+    assert_eq!(clamp(12_u8, 5_u8, 10_u8), 10);
+    assert_eq!(clamp(7_u8, 5_u8, 10_u8), 7);
+
+    #[encrypted(execute)]
+    fn signed_max(a: i8, b: i8) -> i8 {
+        a.max(b)
+    }
+
+    assert_eq!(signed_max(-3_i8, 2_i8), 2);
+    assert_eq!(signed_max(-3_i8, -20_i8), -3);
+}
+
+#[test]
+fn test_macro_wider_output() {
+    #[encrypted(execute)]
+    fn full_product(a: u8, b: u8) -> u16 {
+        a * b
+    }
+
+    // the product is computed at 16 bits, so nothing is truncated
+    assert_eq!(full_product(255_u8, 255_u8), 65025);
+    assert_eq!(full_product(200_u8, 3_u8), 600);
+
+    #[encrypted(execute)]
+    fn signed_product(a: i8, b: i8) -> i16 {
+        a * b
+    }
+
+    assert_eq!(signed_product(-128_i8, 127_i8), -16256);
+    assert_eq!(signed_product(-100_i8, -100_i8), 10000);
+}
+
+#[test]
+fn test_macro_width_conversions() {
+    #[encrypted(execute)]
+    fn low_byte_product(a: u8, b: u8) -> u16 {
+        (a * b).trunc::<8>().zext::<16>()
+    }
+
+    assert_eq!(low_byte_product(200_u8, 3_u8), 600 & 0xff);
+
+    #[encrypted(execute)]
+    fn sign_of_low_byte(a: i8, b: i8) -> i16 {
+        (a * b).trunc::<8>().sext::<16>()
+    }
+
+    // 100 * 2 = 200 overflows a byte and reads back as -56
+    assert_eq!(sign_of_low_byte(100_i8, 2_i8), -56);
+}
+
+#[test]
+fn test_macro_array_output() {
+    #[encrypted(execute)]
+    fn fill(a: u8, b: u8) -> [u8; 3] {
+        let mut out = [0; 3];
+        out[0] = a + b;
+        out[1] = a - b;
+        out[2] = a ^ b;
+        out
+    }
+
+    assert_eq!(fill(7_u8, 3_u8), [10, 4, 4]);
+
+    #[encrypted(execute)]
+    fn pair(a: u8, b: u8) -> [u8; 2] {
+        [a + b, a ^ b]
+    }
+
+    assert_eq!(pair(7_u8, 3_u8), [10, 4]);
+
+    #[encrypted(execute)]
+    fn offsets(a: u8) -> [u8; 4] {
+        let mut out = [a; 4];
+        for i in 1..4 {
+            out[i] = a + i;
+        }
+        out
+    }
+
+    assert_eq!(offsets(40_u8), [40, 41, 42, 43]);
+
+    // parameters of different types build the array at the widest, and each element is
+    // decoded at its own width
+    #[encrypted(execute)]
+    fn halves(a: u8, b: u16) -> [u8; 2] {
+        let mut out = [0; 2];
+        out[0] = a + b;
+        out[1] = b >> 8;
+        out
+    }
+
+    assert_eq!(halves(200_u8, 0x0140_u16), [8, 1]);
+}
+
+#[test]
+fn test_macro_tuple_destructuring() {
+    #[encrypted(component)]
+    fn divmod(a: u8, b: u8) -> (u8, u8) {
+        (a / b, a % b)
+    }
+
+    #[encrypted(execute)]
+    fn recombine(a: u8, b: u8) -> u8 {
+        let (q, r) = divmod(a, b);
+        q * b + r
+    }
+
+    for (a, b) in [(47_u8, 5_u8), (255, 16), (3, 7)] {
+        assert_eq!(recombine(a, b), a);
+    }
+
+    #[encrypted(execute)]
+    fn swap_difference(a: u8, b: u8) -> u8 {
+        let (mut x, y, _) = (b, a, a ^ b);
+        x = x + 1;
+        x - y
+    }
+
+    assert_eq!(swap_difference(3_u8, 10_u8), 8);
+}
+
+#[test]
+fn test_macro_reference_and_owned_inputs() {
+    #[encrypted(execute)]
+    fn add_ref(a: &u8, b: u8) -> u8 {
+        a + b
+    }
+
+    let a = 5_u8;
+    assert_eq!(add_ref(&a, 7_u8), 12);
+    assert_eq!(a, 5);
+
+    #[encrypted(execute)]
+    fn widen_ref(a: &u8, b: &u16) -> u16 {
+        a + b
+    }
+
+    assert_eq!(widen_ref(&200_u8, &1000_u16), 1200);
+
+    // dereferencing a by-reference parameter reads its value
+    #[encrypted(execute)]
+    fn add_deref(a: &u8, b: &u8) -> u8 {
+        *a + *b
+    }
+
+    assert_eq!(add_deref(&3_u8, &4_u8), 7);
+    assert_eq!(add_deref(&250_u8, &10_u8), 4);
+
+    // owned inputs are consumed, so the input type does not need to be `Clone`
+    struct Secret(u8);
+
+    impl<const N: usize> From<Secret> for GarbledUint<N> {
+        fn from(secret: Secret) -> Self {
+            secret.0.into()
+        }
+    }
+
+    impl<const N: usize> From<GarbledUint<N>> for Secret {
+        fn from(value: GarbledUint<N>) -> Self {
+            Secret(value.into())
+        }
+    }
+
+    // an explicit width accepts any type that converts to and from `GarbledUint` of it
+    #[encrypted(execute, width = 8)]
+    fn add(a: u8, b: u8) -> u8 {
+        a + b
+    }
+
+    let sum: Secret = add(Secret(3), Secret(4));
+    assert_eq!(sum.0, 7);
+    assert_eq!(add(3_u8, 4_u8), 7);
+}
+
+#[test]
+fn test_macro_if_else_expression() {
+    #[encrypted(execute)]
+    fn max3(a: u8, b: u8, c: u8) -> u8 {
+        let m = if a > b { a } else { b };
+        if m > c {
+            m
+        } else {
+            c
+        }
+    }
+
+    for (a, b, c) in [(3_u8, 9_u8, 5_u8), (9, 3, 5), (7, 7, 7), (0, 1, 255)] {
+        assert_eq!(max3(a, b, c), a.max(b).max(c));
+    }
+
+    #[encrypted(execute)]
+    fn spread(a: u8, b: u8) -> u8 {
+        let high = if a > b { a } else { b };
+        let low = if a > b { b } else { a };
+        high - low
+    }
+
+    assert_eq!(spread(3_u8, 10_u8), 7);
+    assert_eq!(spread(10_u8, 3_u8), 7);
+
+    // a one-bit comparison in one branch and an eight-bit literal in the other
+    #[encrypted(execute)]
+    fn three_or_zero(a: u8, b: u8) -> bool {
+        let flag = if a > b { false } else { a == 3 };
+        flag | (b == 0)
+    }
+
+    assert!(three_or_zero(3_u8, 5_u8));
+    assert!(!three_or_zero(4_u8, 5_u8));
+    assert!(!three_or_zero(3_u8, 1_u8));
+    assert!(three_or_zero(9_u8, 0_u8));
+}
+
+#[test]
+fn test_macro_logical_operators() {
+    #[encrypted(execute)]
+    fn descending(a: u8, b: u8, c: u8) -> bool {
+        (a > b) && (b > c)
+    }
+
+    assert!(descending(9_u8, 5_u8, 1_u8));
+    assert!(!descending(9_u8, 5_u8, 7_u8));
+    assert!(!descending(3_u8, 5_u8, 1_u8));
+
+    #[encrypted(execute)]
+    fn outside(a: u8, low: u8, high: u8) -> bool {
+        a < low || a > high
+    }
+
+    assert!(outside(2_u8, 5_u8, 10_u8));
+    assert!(outside(12_u8, 5_u8, 10_u8));
+    assert!(!outside(7_u8, 5_u8, 10_u8));
+
+    #[encrypted(execute)]
+    fn majority(x: bool, y: bool, z: bool) -> bool {
+        x && y || x && z || y && z
+    }
+
+    for row in 0..8_u8 {
+        let (x, y, z) = (row & 1 == 1, row & 2 == 2, row & 4 == 4);
+        assert_eq!(majority(x, y, z), (x as u8 + y as u8 + z as u8) >= 2);
+    }
+
+    #[encrypted(execute)]
+    fn gated(flag: bool, a: u8, b: u8) -> bool {
+        flag && a == b
+    }
+
+    assert!(gated(true, 4_u8, 4_u8));
+    assert!(!gated(false, 4_u8, 4_u8));
+    assert!(!gated(true, 4_u8, 5_u8));
+}
+
+#[test]
+fn test_macro_eq_constant() {
+    #[encrypted(execute)]
+    fn is_three(sel: u8) -> bool {
+        sel == 3
+    }
+
+    assert!(is_three(3_u8));
+    assert!(!is_three(4_u8));
+    assert!(!is_three(131_u8));
+
+    #[encrypted(execute)]
+    fn is_three_either_side(sel: u8) -> bool {
+        3 == sel
+    }
+
+    assert!(is_three_either_side(3_u8));
+    assert!(!is_three_either_side(7_u8));
+
+    #[encrypted(compile)]
+    fn compare_constant(sel: u8) -> (Circuit, Vec<bool>) {
+        sel == 3
+    }
+
+    #[encrypted(compile)]
+    fn compare_input(sel: u8, other: u8) -> (Circuit, Vec<bool>) {
+        sel == other
+    }
+
+    // the constant needs no XNOR per bit
+    let (constant_circuit, _) = compare_constant(3_u8);
+    let (input_circuit, _) = compare_input(3_u8, 3_u8);
+    assert!(constant_circuit.gate_count() < input_circuit.gate_count());
+}
+
+#[test]
+fn test_macro_zero_test() {
+    #[encrypted(execute)]
+    fn is_zero(a: u8) -> bool {
+        a == 0
+    }
+
+    #[encrypted(execute)]
+    fn is_nonzero(a: u8) -> bool {
+        0 != a
+    }
+
+    for value in [0_u8, 1, 2, 64, 255] {
+        assert_eq!(is_zero(value), value == 0);
+        assert_eq!(is_nonzero(value), value != 0);
+    }
+}
+
+#[test]
+fn test_macro_build_fragments() {
+    #[encrypted(build)]
+    fn sum(a: u8, b: u8) -> (WRK17CircuitBuilder, GateIndexVec) {
+        a + b
+    }
+
+    #[encrypted(build)]
+    fn product(a: u8, b: u8) -> (WRK17CircuitBuilder, GateIndexVec) {
+        a * b
+    }
+
+    let (mut context, sum) = sum(3_u8, 4_u8);
+    let (fragment, product) = product(5_u8, 6_u8);
+    let product = context.merge(fragment, &product);
+    let output = context.xor(&sum, &product);
+
+    let circuit = context.compile(&output);
+    let result: u8 = context.execute::<8>(&circuit).unwrap().into();
+    assert_eq!(result, 7 ^ 30);
+}
+
+#[test]
+fn test_macro_checked_arithmetic() {
+    #[encrypted(execute)]
+    fn checked_add(a: u8, b: u8) -> (u8, u8) {
+        a.checked_add(b)
+    }
+
+    #[encrypted(execute)]
+    fn checked_sub(a: u8, b: u8) -> (u8, u8) {
+        a.checked_sub(b)
+    }
+
+    #[encrypted(execute)]
+    fn wrapped_or_zero(a: u8, b: u8) -> u8 {
+        let (sum, overflow) = a.checked_add(b);
+        if overflow == 1 {
+            0
+        } else {
+            sum
+        }
+    }
+
+    for (x, y) in [(100_u8, 100_u8), (200, 100), (255, 1), (0, 0)] {
+        let (sum, overflow) = x.overflowing_add(y);
+        assert_eq!(checked_add(x, y), (sum, overflow as u8), "{x} + {y}");
+        let (diff, overflow) = x.overflowing_sub(y);
+        assert_eq!(checked_sub(x, y), (diff, overflow as u8), "{x} - {y}");
+        assert_eq!(wrapped_or_zero(x, y), x.checked_add(y).unwrap_or(0));
+    }
+}
+
+#[test]
+fn test_macro_literal_bases() {
+    #[encrypted(execute)]
+    fn set_high(a: u8) -> u8 {
+        a | 0b1010_0000
+    }
+
+    #[encrypted(execute)]
+    fn high_nibble(a: u8) -> u8 {
+        a & 0xF0
+    }
+
+    #[encrypted(execute)]
+    fn minus_two(a: u8) -> u8 {
+        a + (-2)
+    }
+
+    #[encrypted(execute)]
+    fn signed_minus_two(a: i8) -> i8 {
+        a + (-2)
+    }
+
+    assert_eq!(set_high(0x0F_u8), 0xAF);
+    assert_eq!(high_nibble(0xAB_u8), 0xA0);
+    assert_eq!(minus_two(10_u8), 8);
+    assert_eq!(minus_two(1_u8), 255);
+    assert_eq!(signed_minus_two(-127_i8), 127);
+    assert_eq!(signed_minus_two(5_i8), 3);
+}
+
+#[test]
+fn test_macro_power_of_two_divisor() {
+    #[encrypted(compile)]
+    fn rem_eight(a: u8) -> (Circuit, Vec<bool>) {
+        a % 8
+    }
+
+    #[encrypted(compile)]
+    fn rem_seven(a: u8) -> (Circuit, Vec<bool>) {
+        a % 7
+    }
+
+    #[encrypted(execute)]
+    fn quarter_and_rem(a: u8) -> (u8, u8) {
+        (a / 4, a % 8)
+    }
+
+    let (circuit, _) = rem_eight(29_u8);
+    assert_eq!(circuit.and_gates(), 0);
+    let (circuit, _) = rem_seven(29_u8);
+    assert!(circuit.and_gates() > 0);
+
+    assert_eq!(quarter_and_rem(29_u8), (7, 5));
+    assert_eq!(quarter_and_rem(255_u8), (63, 7));
+}
+
+#[test]
+fn test_macro_integer_casts() {
+    #[encrypted(execute)]
+    fn wide_product(a: u8, b: u8) -> u16 {
+        (a as u16) * (b as u16)
+    }
+
+    #[encrypted(execute)]
+    fn low_byte(a: u16) -> u16 {
+        (a as u8) as u16
+    }
+
+    #[encrypted(execute)]
+    fn low_byte_signed(a: i16) -> i16 {
+        (a as i8) as i16
+    }
+
+    #[encrypted(execute)]
+    fn low_byte_unsigned(a: i16) -> i16 {
+        (a as u8) as i16
+    }
+
+    assert_eq!(wide_product(200_u8, 250_u8), 50_000);
+    assert_eq!(low_byte(0x1234_u16), 0x34);
+    assert_eq!(low_byte_signed(0x0180_i16), -128);
+    assert_eq!(low_byte_signed(-200_i16), 56);
+    assert_eq!(low_byte_unsigned(0x0180_i16), 128);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Word(u16);
+
+impl From<Word> for GarbledUint<16> {
+    fn from(word: Word) -> Self {
+        word.0.into()
+    }
+}
+
+impl From<GarbledUint<16>> for Word {
+    fn from(value: GarbledUint<16>) -> Self {
+        Word(value.into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Celsius(i8);
+
+impl From<Celsius> for GarbledUint<8> {
+    fn from(celsius: Celsius) -> Self {
+        celsius.0.into()
+    }
+}
+
+impl From<GarbledUint<8>> for Celsius {
+    fn from(value: GarbledUint<8>) -> Self {
+        Celsius(value.into())
+    }
+}
+
+/// A 24-bit unsigned field, stored in the low bits of a `u32`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct U24(u32);
+
+impl From<U24> for GarbledUint<24> {
+    fn from(value: U24) -> Self {
+        value.0.into()
+    }
+}
+
+impl From<GarbledUint<24>> for U24 {
+    fn from(value: GarbledUint<24>) -> Self {
+        U24(value.into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct U256(ruint::aliases::U256);
+
+impl From<U256> for GarbledUint<256> {
+    fn from(value: U256) -> Self {
+        value.0.into()
+    }
+}
+
+impl From<GarbledUint<256>> for U256 {
+    fn from(value: GarbledUint<256>) -> Self {
+        U256(value.try_into().unwrap())
+    }
+}
+
+#[test]
+fn test_macro_explicit_width() {
+    // `type_name` of a newtype is not a primitive, so only an explicit width can dispatch it
+    #[encrypted(execute, width = 16)]
+    fn mix(a: Word, b: Word) -> Word {
+        a * b + a
+    }
+
+    assert_eq!(mix(Word(300), Word(7)), Word(2400));
+    assert_eq!(mix(Word(60_000), Word(3)), Word(60_000_u16.wrapping_mul(4)));
+
+    #[encrypted(execute, width = 8, signed)]
+    fn colder(a: Celsius, b: Celsius) -> Celsius {
+        if a < b {
+            a
+        } else {
+            b
+        }
+    }
+
+    assert_eq!(colder(Celsius(-5), Celsius(3)), Celsius(-5));
+    assert_eq!(colder(Celsius(12), Celsius(-40)), Celsius(-40));
+}
+
+#[test]
+fn test_macro_custom_width() {
+    #[encrypted(execute, width = 24)]
+    fn add24(a: U24, b: U24) -> U24 {
+        a + b
+    }
+
+    assert_eq!(add24(U24(0x12_3456), U24(0x11_1111)), U24(0x23_4567));
+    // the sum wraps at 24 bits, not at the 32 bits of the storage
+    assert_eq!(add24(U24(0xFF_FFFF), U24(2)), U24(1));
+
+    #[encrypted(execute, width = 256)]
+    fn add256(a: U256, b: U256) -> U256 {
+        a + b + 1
+    }
+
+    let max = ruint::aliases::U256::MAX;
+    let half = max >> 1;
+    assert_eq!(
+        add256(U256(half), U256(half)),
+        U256(max),
+        "carries propagate across all 256 bits"
+    );
+    assert_eq!(
+        add256(U256(max), U256(max)),
+        U256(max),
+        "the sum wraps at 256 bits"
+    );
+
+    #[encrypted(execute, width = 256)]
+    fn dec256(a: U256) -> U256 {
+        a + -1
+    }
+
+    assert_eq!(dec256(U256(max)), U256(max - ruint::aliases::U256::from(1)));
+    assert_eq!(
+        dec256(U256(ruint::aliases::U256::ZERO)),
+        U256(max),
+        "negative literals are sign extended above 128 bits"
+    );
+}
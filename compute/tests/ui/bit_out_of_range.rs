@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn high_bit(a: u8) -> bool {
+    a.bit(8)
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn wrapped(a: u8) -> u8 {
+    u8::from(a)
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn offset(a: u8) -> u8 {
+    const OFFSET: u8 = 300;
+    a + OFFSET
+}
+
+fn main() {}
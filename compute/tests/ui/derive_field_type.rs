@@ -0,0 +1,10 @@
+use compute::prelude::*;
+
+#[derive(CircuitInput)]
+#[allow(dead_code)]
+struct Reading {
+    id: u8,
+    samples: [u8; 4],
+}
+
+fn main() {}
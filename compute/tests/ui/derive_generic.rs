@@ -0,0 +1,9 @@
+use compute::prelude::*;
+
+#[derive(CircuitInput)]
+#[allow(dead_code)]
+struct Wrapper<T> {
+    value: T,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use compute::prelude::*;
+
+#[derive(CircuitInput)]
+#[allow(dead_code)]
+struct Pair(u8, u8);
+
+fn main() {}
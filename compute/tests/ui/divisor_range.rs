@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn shrink(a: u8) -> u8 {
+    a / 512
+}
+
+fn main() {}
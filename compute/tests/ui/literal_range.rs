@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn mask(a: u8) -> u8 {
+    a | 0x1FF
+}
+
+fn main() {}
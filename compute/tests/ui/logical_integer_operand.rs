@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn both(a: u8, b: u8) -> bool {
+    (a > b) && 1
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn either(a: u8, b: u8) -> bool {
+    a || (b > 3)
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn select(sel: u8, a: u8, b: u8) -> u8 {
+    match sel {
+        0 => a,
+        1 => b,
+    }
+}
+
+fn main() {}
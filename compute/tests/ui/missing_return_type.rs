@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn no_return(a: u8) {
+    a + 1;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn mixed(a: u8, b: i16) -> i16 {
+    a + b
+}
+
+fn main() {}
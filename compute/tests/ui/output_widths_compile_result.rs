@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(compile, output_widths)]
+fn add(a: u8, b: u8) -> (Circuit, Vec<bool>) {
+    a + b
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn first(xs: [u8; 4]) -> u8 {
+    let xs = xs[0];
+    let mut total = 0;
+    for x in xs {
+        total = total ^ x;
+    }
+    total
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn sum(xs: &[u8]) -> u16 {
+    xs[0]
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn pair(a: u8, b: u8) -> u8 {
+    let (x, y) = (a, b, a + b);
+    x + y
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn unbounded(a: u8) -> u8 {
+    while a > 10 {}
+    a
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn unknown_method(a: u8) -> u8 {
+    a.pow(2)
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn unsupported_closure(a: u8) -> u8 {
+    let double = |x: u8| x * 2;
+    a
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn unsupported_if_let(a: u8) -> u8 {
+    if let Some(5) = a {
+        a + 1
+    } else {
+        a
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use compute::prelude::*;
+
+#[encrypted(execute)]
+fn traced(a: u8) -> u8 {
+    let b = dbg!(a);
+    b + 1
+}
+
+fn main() {}